@@ -0,0 +1,44 @@
+//! Book checksum on a deep book: the original sort-per-call algorithm against
+//! [`LocalOrderBook::calculate_checksum`], which walks the already ordered levels.
+//!
+//! Run with `cargo bench --bench checksum`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use kraken_sdk::models::LocalOrderBook;
+use std::hint::black_box;
+
+include!("support/reference_checksum.rs");
+
+/// A 2,000-level book, as with a `book` subscription at depth 1000.
+fn deep_book() -> LocalOrderBook {
+    let mut book = LocalOrderBook::new();
+    for i in 0..1000 {
+        let offset = i as f64 * 0.1;
+        book.asks.insert(
+            format!("{:.1}", 65000.1 + offset).into(),
+            format!("{:.8}", 0.5 + i as f64 * 0.001),
+        );
+        book.bids.insert(
+            format!("{:.1}", 65000.0 - offset).into(),
+            format!("{:.8}", 0.7 + i as f64 * 0.001),
+        );
+    }
+    book
+}
+
+fn bench_checksum(c: &mut Criterion) {
+    let book = deep_book();
+    assert_eq!(book.calculate_checksum(), reference_checksum(&book));
+
+    let mut group = c.benchmark_group("checksum_depth_1000");
+    group.bench_function("sort_per_call", |b| {
+        b.iter(|| reference_checksum(black_box(&book)))
+    });
+    group.bench_function("ordered_book", |b| {
+        b.iter(|| black_box(&book).calculate_checksum())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_checksum);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+//! Parsing throughput over `examples/benchmark_data.json` (5,000 trade and 5,000 book frames).
+//!
+//! Run with `cargo bench --bench parse`. Deserialization and the typed conversions are
+//! measured separately, so a regression can be traced to one of them.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use kraken_sdk::models::KrakenEvent;
+use serde_json::Value;
+use std::hint::black_box;
+
+/// Every frame of the data set, serialized back to the text a socket would deliver.
+fn frames() -> Vec<String> {
+    let content = std::fs::read_to_string("examples/benchmark_data.json")
+        .expect("Failed to read benchmark_data.json");
+    let frames: Vec<Value> = serde_json::from_str(&content).expect("Failed to parse JSON array");
+    frames.iter().map(Value::to_string).collect()
+}
+
+/// The frames of `channel` as raw arrays, i.e. [`KrakenEvent::Data`] before classification.
+fn raw_frames(frames: &[String], channel: &str) -> Vec<KrakenEvent> {
+    frames
+        .iter()
+        .filter_map(
+            |frame| match serde_json::from_str::<Value>(frame).unwrap() {
+                Value::Array(vec)
+                    if vec.get(vec.len().wrapping_sub(2)) == Some(&channel.into()) =>
+                {
+                    Some(KrakenEvent::Data(vec))
+                }
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+fn bench_deserialize(c: &mut Criterion) {
+    let frames = frames();
+    let mut group = c.benchmark_group("deserialize");
+    group.throughput(Throughput::Elements(frames.len() as u64));
+    group.bench_function("kraken_event", |b| {
+        b.iter(|| {
+            for frame in &frames {
+                black_box(serde_json::from_str::<KrakenEvent>(frame).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_conversions(c: &mut Criterion) {
+    let frames = frames();
+    let parsed: Vec<KrakenEvent> = frames
+        .iter()
+        .map(|frame| serde_json::from_str(frame).unwrap())
+        .collect();
+    let raw_trades = raw_frames(&frames, "trade");
+    let raw_books = raw_frames(&frames, "book-10");
+
+    let mut group = c.benchmark_group("conversions");
+    // Conversions consume the event, so each iteration gets a fresh clone (not timed)
+    let mut convert = |name: &str, events: &[KrakenEvent], f: fn(KrakenEvent) -> bool| {
+        group.throughput(Throughput::Elements(events.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || events.to_vec(),
+                |events| {
+                    for event in events {
+                        black_box(f(event));
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    };
+    convert("try_into_trade_data", &parsed, |e| {
+        e.try_into_trade_data().is_some()
+    });
+    convert("try_into_orderbook_data", &parsed, |e| {
+        e.try_into_orderbook_data().is_some()
+    });
+    convert("try_into_trade_data/raw", &raw_trades, |e| {
+        e.try_into_trade_data().is_some()
+    });
+    convert("try_into_orderbook_data/raw", &raw_books, |e| {
+        e.try_into_orderbook_data().is_some()
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_deserialize, bench_conversions);
+criterion_main!(benches);
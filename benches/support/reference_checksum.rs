@@ -0,0 +1,17 @@
+// Shared by `benches/checksum.rs` and the `models` unit tests through `include!`, so the
+// benchmark's baseline is the same algorithm the tests check `calculate_checksum` against.
+
+/// The original algorithm: sort every level by float, then strip the strings.
+fn reference_checksum(book: &LocalOrderBook) -> u32 {
+    let strip = |s: &str| s.replace('.', "").trim_start_matches('0').to_string();
+    let mut asks: Vec<_> = book.asks.iter().collect();
+    asks.sort_by(|a, b| a.0.parse::<f64>().unwrap().total_cmp(&b.0.parse().unwrap()));
+    let mut bids: Vec<_> = book.bids.iter().collect();
+    bids.sort_by(|a, b| b.0.parse::<f64>().unwrap().total_cmp(&a.0.parse().unwrap()));
+    let mut hasher = crc32fast::Hasher::new();
+    for (price, volume) in asks.iter().take(10).chain(bids.iter().take(10)) {
+        hasher.update(strip(price).as_bytes());
+        hasher.update(strip(volume).as_bytes());
+    }
+    hasher.finalize()
+}
@@ -0,0 +1,46 @@
+//! Owned vs borrowed parsing of trade frames.
+//!
+//! Run with `cargo bench --bench trade_ref`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use kraken_sdk::models::{parse_borrowed, KrakenEvent};
+use std::hint::black_box;
+
+/// The trade frames of `examples/benchmark_data.json`, one JSON string per frame.
+fn trade_frames() -> Vec<String> {
+    let content = std::fs::read_to_string("examples/benchmark_data.json")
+        .expect("Failed to read benchmark_data.json");
+    let frames: Vec<serde_json::Value> =
+        serde_json::from_str(&content).expect("Failed to parse JSON array");
+    frames
+        .iter()
+        .filter(|frame| frame.get(2).and_then(|name| name.as_str()) == Some("trade"))
+        .map(|frame| frame.to_string())
+        .collect()
+}
+
+fn bench_trade_parsing(c: &mut Criterion) {
+    let frames = trade_frames();
+    let mut group = c.benchmark_group("trade_frames");
+    group.throughput(Throughput::Elements(frames.len() as u64));
+
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            for frame in &frames {
+                let event: KrakenEvent = serde_json::from_str(frame).unwrap();
+                black_box(event.try_into_trade_data());
+            }
+        })
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            for frame in &frames {
+                black_box(parse_borrowed(frame).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_trade_parsing);
+criterion_main!(benches);
@@ -1,3 +1,4 @@
+use futures_util::StreamExt;
 use kraken_sdk::KrakenClient;
 use tracing::info;
 
@@ -6,17 +7,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
     info!("Starting Basic Subscribe Example...");
 
-    let client = KrakenClient::new();
-    let mut rx = client.subscribe_events();
+    // Subscribe to XBT/USD trades as soon as the connection opens
+    let client = KrakenClient::builder()
+        .subscribe_on_connect(vec!["XBT/USD".to_string()], &["trade"])
+        .build();
+    let mut events = client.event_stream();
 
     client.connect().await?;
 
-    // Subscribe to XBT/USD trades
-    client
-        .subscribe(vec!["XBT/USD".to_string()], "trade", None)
-        .await?;
-
-    while let Ok(event) = rx.recv().await {
+    while let Some(event) = events.next().await {
         if let Some(trade) = event.try_into_trade_data() {
             info!("Received {} trades for {}", trade.data.len(), trade.pair);
             for t in trade.data {
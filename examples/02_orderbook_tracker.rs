@@ -1,5 +1,9 @@
-use kraken_sdk::KrakenClient;
-use tracing::info;
+use kraken_sdk::{
+    book::BookMaintainer,
+    models::{ConnectionState, KrakenEvent},
+    KrakenClient,
+};
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -16,19 +20,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .subscribe(vec!["XBT/USD".to_string()], "book", None)
         .await?;
 
-    let mut local_book = kraken_sdk::models::LocalOrderBook::new();
+    // Keeps the local book, resubscribes automatically if a checksum fails and
+    // clears it when the connection drops
+    let mut books = BookMaintainer::new();
 
     while let Ok(event) = rx.recv().await {
-        if let Some(book) = event.try_into_orderbook_data() {
-            local_book.update(&book);
+        let consistent = books.process(&client, &event).await?;
+        match &event {
+            KrakenEvent::ChecksumMismatch { pair } => {
+                warn!(
+                    "❌ Checksum mismatch on {}, waiting for a fresh snapshot",
+                    pair
+                );
+                continue;
+            }
+            KrakenEvent::ConnectionState(ConnectionState::Reconnecting) => {
+                warn!("Connection lost, book cleared until the next snapshot");
+                continue;
+            }
+            _ => {}
+        }
 
-            if let Some(checksum) = book.checksum {
-                if local_book.validate_checksum(&checksum) {
+        if let Some(book) = event.try_into_orderbook_data() {
+            if consistent {
+                if let Some(checksum) = &book.checksum {
                     info!("✅ Checksum Validated: {}", checksum);
-                } else {
-                    // warn!("❌ Checksum Mismatch! Remote: {}, Local: {}", checksum, local_book.calculate_checksum());
-                    // Note: Mismatches can happen if we miss a message or if our sort logic is slightly off.
-                    // For the demo, we log it but don't panic.
                 }
             }
 
@@ -1,4 +1,4 @@
-use kraken_sdk::KrakenClient;
+use kraken_sdk::{filter::TradeFilter, KrakenClient};
 use tracing::info;
 
 #[tokio::main]
@@ -19,21 +19,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .await?;
 
+    let whales = TradeFilter::new().min_value(1000.0);
+
     while let Ok(event) = rx.recv().await {
         if let Some(trade_data) = event.try_into_trade_data() {
-            for trade in trade_data.data {
-                let price: f64 = trade.price.parse().unwrap_or(0.0);
-                let volume: f64 = trade.volume.parse().unwrap_or(0.0);
-                let value = price * volume;
-
-                if value > 1000.0 {
-                    info!(
-                        "🚨 WHALE ALERT on {}: ${:.2} ({} @ {})",
-                        trade_data.pair, value, volume, price
-                    );
-                } else {
-                    info!("Trade on {}: ${:.2}", trade_data.pair, value);
-                }
+            for trade in trade_data.filter(whales) {
+                info!(
+                    "🚨 WHALE ALERT on {}: {:?} {} @ {}",
+                    trade_data.pair,
+                    trade.taker_side(),
+                    trade.volume,
+                    trade.price
+                );
             }
         }
     }
@@ -1,4 +1,7 @@
-use kraken_sdk::{models::KrakenEvent, KrakenClient};
+use kraken_sdk::{
+    models::{ChannelData, KrakenEvent, SystemState},
+    KrakenClient,
+};
 use tracing::{info, warn};
 
 #[tokio::main]
@@ -20,20 +23,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             KrakenEvent::Heartbeat(_) => {
                 // Ignore heartbeats to keep logs clean
             }
-            KrakenEvent::SystemStatus(status) => {
-                if status.status != "online" {
-                    warn!("System is not online: {:?}", status);
-                }
+            KrakenEvent::SystemStatusChanged { from, to } => match SystemState::from(to) {
+                SystemState::Online => info!("System back online (was {:?})", from),
+                state => warn!("System went from {:?} to {}: halt trading", from, state),
+            },
+            // Data frames arrive already classified by channel
+            KrakenEvent::Channel(ChannelData::Trade(trade)) => {
+                info!("Trade Event: {:?}", trade.pair);
             }
-            KrakenEvent::Data(_) => {
-                // Use the helper to check for trades
-                if let Some(trade) = event.clone().try_into_trade_data() {
-                    info!("Trade Event: {:?}", trade.pair);
-                }
-                // Or check for orderbook
-                else if let Some(book) = event.try_into_orderbook_data() {
-                    info!("Book Event: {:?}", book.pair);
-                }
+            KrakenEvent::Channel(ChannelData::Book(book)) => {
+                info!("Book Event: {:?}", book.pair);
             }
             _ => {}
         }
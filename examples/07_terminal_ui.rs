@@ -19,75 +19,54 @@ use std::{
 
 use kraken_sdk::{
     aggregator::TradeAggregator,
-    models::{Candle, LocalOrderBook},
+    metrics::{ClientStats, LatencyTracker},
+    models::{Candle, LocalOrderBook, Side},
+    tape::TradeTape,
     KrakenClient,
 };
 
-struct TradeInfo {
-    time: String,
-    price: String,
-    volume: String,
-    side: String, // "b" or "s"
-}
-
 struct App {
     local_book: LocalOrderBook,
-    trades: Vec<TradeInfo>,
+    trades: TradeTape,
     status: String,
+    connected: bool,
     selected_tab: usize,
     aggregator: TradeAggregator,
     candles: Vec<Candle>,
     // New Fields
     price_history: Vec<u64>,
-    msg_count: u64,
+    stats: ClientStats,
     start_time: Instant,
-    last_latency: u128,
+    latency: LatencyTracker,
 }
 
 impl App {
     fn new() -> Self {
         Self {
-            local_book: LocalOrderBook::new(),
-            trades: Vec::new(),
+            local_book: LocalOrderBook::with_depth(10), // default depth of the book channel
+            trades: TradeTape::new(50),
             status: "Initializing...".to_string(),
+            connected: false,
             selected_tab: 0,
             aggregator: TradeAggregator::new(10), // 10-second candles for demo
             candles: Vec::new(),
             price_history: Vec::new(),
-            msg_count: 0,
+            stats: ClientStats::default(),
             start_time: Instant::now(),
-            last_latency: 0,
+            latency: LatencyTracker::new(500),
         }
     }
 
     fn get_spread(&self) -> (f64, f64) {
-        // Simple spread calculation
-        // Asks are sorted Low -> High (Best ask is first)
-        // Bids are sorted High -> Low (Best bid is first)
-        // Note: LocalOrderBook stores strings in BTreeMap.
-        // We need to find the best ask and best bid.
-
-        // Since BTreeMap sorts strings lexicographically, we need to be careful.
-        // However, for this demo, we'll iterate and parse to find true best.
-        // Optimization: Cache this or use a better data structure in production.
-
-        let best_ask = self
-            .local_book
-            .asks
-            .keys()
-            .filter_map(|p| p.parse::<f64>().ok())
-            .fold(f64::MAX, |a, b| a.min(b));
-
-        let best_bid = self
-            .local_book
-            .bids
-            .keys()
-            .filter_map(|p| p.parse::<f64>().ok())
-            .fold(f64::MIN, |a, b| a.max(b));
-
-        if best_ask == f64::MAX || best_bid == f64::MIN {
+        // Asks are sorted Low -> High (best ask first), bids High -> Low from the back
+        let best_ask = self.local_book.asks.keys().next();
+        let best_bid = self.local_book.bids.keys().next_back();
+        let (Some(Ok(best_ask)), Some(Ok(best_bid))) = (
+            best_ask.map(|p| p.parse::<f64>()),
+            best_bid.map(|p| p.parse::<f64>()),
+        ) else {
             return (0.0, 0.0);
-        }
+        };
 
         (
             best_ask - best_bid,
@@ -157,6 +136,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
             loop {
                 match rx.try_recv() {
                     Ok(event) => {
+                        app.latency.record(&event);
                         if let Some(trade) = event.clone().try_into_trade_data() {
                             for t in trade.data {
                                 // Update Aggregator
@@ -169,14 +149,6 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                 }
                                 app.aggregator.update(&t);
 
-                                // Update Stats
-                                app.msg_count += 1;
-                                app.last_latency = std::time::SystemTime::now()
-                                    .duration_since(std::time::UNIX_EPOCH)
-                                    .unwrap()
-                                    .as_millis()
-                                    - (t.time.parse::<f64>().unwrap_or(0.0) * 1000.0) as u128;
-
                                 // Update Price History (Sparkline)
                                 let price = t.price.parse::<f64>().unwrap_or(0.0);
                                 app.price_history.push(price as u64);
@@ -184,21 +156,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                     app.price_history.remove(0);
                                 }
 
-                                // Whale Alert
-                                let volume = t.volume.parse::<f64>().unwrap_or(0.0);
-                                let value = price * volume;
-                                let whale_emoji = if value > 50000.0 { "🐋 " } else { "" };
-
-                                let info = TradeInfo {
-                                    time: t.time,
-                                    price: t.price,
-                                    volume: t.volume,
-                                    side: format!("{}{}", whale_emoji, t.side),
-                                };
-                                app.trades.insert(0, info);
-                                if app.trades.len() > 50 {
-                                    app.trades.pop();
-                                }
+                                app.trades.push(t);
                             }
                         } else if let Some(book) = event.try_into_orderbook_data() {
                             app.local_book.update(&book);
@@ -208,6 +166,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     Err(_) => break,
                 }
             }
+
+            app.stats = client.stats();
+            app.connected = client.is_connected();
+            app.status = if app.connected {
+                "Connected. Streaming XBT/USD...".to_string()
+            } else {
+                "Reconnecting to Kraken WS...".to_string()
+            };
             last_tick = std::time::Instant::now();
         }
     }
@@ -256,7 +222,7 @@ fn ui(f: &mut Frame, app: &App) {
         )
         .block(Block::default().borders(Borders::ALL));
 
-    let status_color = if app.status.contains("Connected") {
+    let status_color = if app.connected {
         Color::Green
     } else {
         Color::Yellow
@@ -304,14 +270,16 @@ fn ui(f: &mut Frame, app: &App) {
     // --- Footer ---
     let elapsed = app.start_time.elapsed().as_secs_f64();
     let msg_rate = if elapsed > 0.0 {
-        app.msg_count as f64 / elapsed
+        app.stats.total() as f64 / elapsed
     } else {
         0.0
     };
 
     let footer_text = format!(
-        "Controls: [q] Quit | [1] Market | [2] Analytics | [3] 10s [4] 30s [5] 60s | Latency: {}ms | Msgs/sec: {:.0}", 
-        app.last_latency, msg_rate
+        "Controls: [q] Quit | [1] Market | [2] Analytics | [3] 10s [4] 30s [5] 60s | Latency: {:.0}ms (p99 {:.0}ms) | Msgs/sec: {:.0}", 
+        app.latency.last().unwrap_or(0.0),
+        app.latency.percentile(99.0).unwrap_or(0.0),
+        msg_rate
     );
     let footer = Paragraph::new(footer_text).style(Style::default().fg(Color::DarkGray));
     f.render_widget(footer, chunks[2]);
@@ -330,24 +298,7 @@ fn render_market_tab(f: &mut Frame, app: &App, area: Rect) {
         .split(main_chunks[0]);
 
     // Liquidity Meter (Top of Orderbook)
-    let total_bid_vol: f64 = app
-        .local_book
-        .bids
-        .values()
-        .filter_map(|v| v.parse::<f64>().ok())
-        .sum();
-    let total_ask_vol: f64 = app
-        .local_book
-        .asks
-        .values()
-        .filter_map(|v| v.parse::<f64>().ok())
-        .sum();
-    let total_vol = total_bid_vol + total_ask_vol;
-    let bid_ratio = if total_vol > 0.0 {
-        total_bid_vol / total_vol
-    } else {
-        0.5
-    };
+    let bid_ratio = app.local_book.imbalance(10); // Default book depth
 
     let gauge = Gauge::default()
         .block(
@@ -378,31 +329,18 @@ fn render_market_tab(f: &mut Frame, app: &App, area: Rect) {
 
     // ... (Use inner_book_chunks instead of book_chunks for tables)
 
-    // Prepare Bids (Green) - Sorted High to Low
-    let mut bids: Vec<(&String, &String)> = app.local_book.bids.iter().collect();
-    bids.sort_by(|a, b| {
-        let p1 = a.0.parse::<f64>().unwrap_or(0.0);
-        let p2 = b.0.parse::<f64>().unwrap_or(0.0);
-        p2.partial_cmp(&p1).unwrap_or(std::cmp::Ordering::Equal)
-    });
-
-    // Prepare Asks (Red) - Sorted Low to High
-    let mut asks: Vec<(&String, &String)> = app.local_book.asks.iter().collect();
-    asks.sort_by(|a, b| {
-        let p1 = a.0.parse::<f64>().unwrap_or(0.0);
-        let p2 = b.0.parse::<f64>().unwrap_or(0.0);
-        p1.partial_cmp(&p2).unwrap_or(std::cmp::Ordering::Equal)
-    });
+    // Bids (Green) - High to Low; Asks (Red) - Low to High
+    let bids = app.local_book.bids.iter().rev();
+    let asks = app.local_book.asks.iter();
 
     // Render Bids
     let bid_rows: Vec<Row> = bids
-        .iter()
         .take(25)
         .map(|(p, v)| {
             let vol = v.parse::<f64>().unwrap_or(0.0);
             let bar = create_volume_bar(vol, 10.0, 10); // Assume max vol 10 for bar scaling
             Row::new(vec![
-                Cell::from(format!("{}", p)).style(Style::default().fg(Color::Green)),
+                Cell::from(p.to_string()).style(Style::default().fg(Color::Green)),
                 Cell::from(v.as_str()),
                 Cell::from(bar).style(Style::default().fg(Color::DarkGray)),
             ])
@@ -427,13 +365,12 @@ fn render_market_tab(f: &mut Frame, app: &App, area: Rect) {
 
     // Render Asks
     let ask_rows: Vec<Row> = asks
-        .iter()
         .take(25)
         .map(|(p, v)| {
             let vol = v.parse::<f64>().unwrap_or(0.0);
             let bar = create_volume_bar(vol, 10.0, 10);
             Row::new(vec![
-                Cell::from(format!("{}", p)).style(Style::default().fg(Color::Red)),
+                Cell::from(p.to_string()).style(Style::default().fg(Color::Red)),
                 Cell::from(v.as_str()),
                 Cell::from(bar).style(Style::default().fg(Color::DarkGray)),
             ])
@@ -459,20 +396,27 @@ fn render_market_tab(f: &mut Frame, app: &App, area: Rect) {
     // Trades (Right)
     let trade_rows: Vec<Row> = app
         .trades
-        .iter()
+        .latest(app.trades.capacity())
         .map(|t| {
-            let color = if t.side == "b" {
+            let color = if t.taker_side() == Some(Side::Buy) {
                 Color::Green
             } else {
                 Color::Red
             };
+            // Whale Alert
+            let value = t.price_f64().unwrap_or(0.0) * t.volume_f64().unwrap_or(0.0);
+            let whale_emoji = if value > 50000.0 { "🐋 " } else { "" };
             Row::new(vec![
                 Cell::from(t.time.as_str()),
                 Cell::from(t.price.as_str()).style(Style::default().fg(color)),
-                Cell::from(t.volume.as_str()),
+                Cell::from(format!("{}{}", whale_emoji, t.volume)),
             ])
         })
         .collect();
+    let trades_title = match app.trades.buy_sell_ratio() {
+        Some(ratio) => format!("Recent Trades (buy/sell {:.2})", ratio),
+        None => "Recent Trades".to_string(),
+    };
 
     let trades_table = Table::new(
         trade_rows,
@@ -486,11 +430,7 @@ fn render_market_tab(f: &mut Frame, app: &App, area: Rect) {
         Row::new(vec!["Time", "Price", "Vol"])
             .style(Style::default().add_modifier(Modifier::UNDERLINED)),
     )
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Recent Trades"),
-    );
+    .block(Block::default().borders(Borders::ALL).title(trades_title));
 
     f.render_widget(trades_table, main_chunks[1]);
 }
@@ -591,11 +531,7 @@ fn render_analytics_tab(f: &mut Frame, app: &App, area: Rect) {
             } else {
                 "-".to_string()
             };
-            let trend = if c.close >= c.open {
-                "███"
-            } else {
-                "███"
-            };
+            let trend = "███";
 
             Row::new(vec![
                 Cell::from(c.start_time.to_string()),
@@ -604,6 +540,14 @@ fn render_analytics_tab(f: &mut Frame, app: &App, area: Rect) {
                 Cell::from(format!("{:.2}", c.low)),
                 Cell::from(format!("{:.2}", c.close)).style(Style::default().fg(color)),
                 Cell::from(format!("{:.4}", c.volume)),
+                Cell::from(c.count.to_string()),
+                Cell::from(format!("{:+.4}", c.delta())).style(Style::default().fg(
+                    if c.delta() >= 0.0 {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    },
+                )),
                 Cell::from(sma).style(Style::default().fg(Color::Yellow)),
                 Cell::from(trend).style(Style::default().fg(color)),
             ])
@@ -619,13 +563,15 @@ fn render_analytics_tab(f: &mut Frame, app: &App, area: Rect) {
             Constraint::Length(10),
             Constraint::Length(10),
             Constraint::Length(10),
+            Constraint::Length(7),
+            Constraint::Length(10),
             Constraint::Length(10),
             Constraint::Length(5),
         ],
     )
     .header(
         Row::new(vec![
-            "Time", "Open", "High", "Low", "Close", "Volume", "SMA-10", "Trend",
+            "Time", "Open", "High", "Low", "Close", "Volume", "Trades", "Delta", "SMA-10", "Trend",
         ])
         .style(Style::default().add_modifier(Modifier::UNDERLINED)),
     )
@@ -641,6 +587,6 @@ fn render_analytics_tab(f: &mut Frame, app: &App, area: Rect) {
 fn create_volume_bar(volume: f64, max_volume: f64, width: usize) -> String {
     let ratio = (volume / max_volume).min(1.0);
     let filled = (ratio * width as f64).round() as usize;
-    let bar: String = std::iter::repeat("█").take(filled).collect();
+    let bar = "█".repeat(filled);
     format!("{:<width$}", bar, width = width)
 }
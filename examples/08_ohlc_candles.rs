@@ -1,11 +1,18 @@
-use kraken_sdk::{aggregator::TradeAggregator, KrakenClient};
+use kraken_sdk::{
+    aggregator::SessionVwap,
+    models::{Candle, KrakenEvent},
+    KrakenClient,
+};
 use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     tracing_subscriber::fmt::init();
 
-    let client = KrakenClient::new();
+    // The client aggregates trades into 60-second candles and broadcasts each closed one
+    let client = KrakenClient::builder().aggregate_trades(60).build();
     let mut rx = client.subscribe_events();
 
     // Connect and subscribe to XBT/USD trades
@@ -17,33 +24,35 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("🕯️  Starting Candle Aggregator (1-minute candles)...");
     println!("Waiting for trades...");
 
-    // Aggregate into 60-second candles
-    let mut aggregator = TradeAggregator::new(60);
+    // Cumulative VWAP since start, printed alongside each closed candle
+    let mut session = SessionVwap::new();
+
+    // Optionally persist closed candles, e.g. `CANDLES_CSV=candles.csv cargo run --example ...`
+    let mut csv = match std::env::var("CANDLES_CSV") {
+        Ok(path) => {
+            let mut file = BufWriter::new(File::create(&path)?);
+            writeln!(file, "{}", Candle::CSV_HEADER)?;
+            println!("💾 Writing candles to {}", path);
+            Some(file)
+        }
+        Err(_) => None,
+    };
 
     loop {
         match rx.recv().await {
+            Ok(KrakenEvent::CandleClosed { pair, candle }) => {
+                println!("🔥 NEW CANDLE [{}]: {}", candle.start_time, candle);
+                if let Some(vwap) = session.session_vwap(&pair) {
+                    println!("   Session VWAP: {:.2}", vwap);
+                }
+                if let Some(file) = csv.as_mut() {
+                    candle.write_csv_row(&mut *file)?;
+                    file.flush()?;
+                }
+            }
             Ok(event) => {
                 if let Some(trade_data) = event.try_into_trade_data() {
-                    for trade in trade_data.data {
-                        // Check if a candle closed
-                        // In a real app, you'd use the trade time, but for this demo we use the trade time too.
-                        let trade_time = trade.time.parse::<f64>().unwrap_or(0.0);
-
-                        if let Some(candle) = aggregator.check_flush(trade_time) {
-                            println!(
-                                "🔥 NEW CANDLE [{}]: O: {:.2} H: {:.2} L: {:.2} C: {:.2} V: {:.4}",
-                                candle.start_time,
-                                candle.open,
-                                candle.high,
-                                candle.low,
-                                candle.close,
-                                candle.volume
-                            );
-                        }
-
-                        // Update aggregator
-                        aggregator.update(&trade);
-                    }
+                    session.process(&trade_data);
                 }
             }
             Err(e) => eprintln!("Error receiving event: {}", e),
@@ -16,7 +16,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // 2. Get WebSocket Token
     println!("🔑 Fetching WebSocket Token...");
-    let auth = Authenticator::new(api_key, api_secret);
+    // Transient outages (EService:Unavailable, 5xx, timeouts) are retried with backoff.
+    let auth =
+        Authenticator::new(api_key, api_secret).with_retries(5, std::time::Duration::from_secs(1));
     let token = auth.get_ws_token().await?;
     println!("✅ Token received: {}...", &token[0..10]);
 
@@ -30,9 +32,16 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("📡 Subscribing to 'ownTrades'...");
     client.subscribe(vec![], "ownTrades", Some(token)).await?;
 
-    // 4. Print Events
+    // 4. Print Fills
     while let Ok(event) = rx.recv().await {
-        println!("Received: {:?}", event);
+        if let Some(own) = event.try_into_own_trades() {
+            for trade in own.trades {
+                println!(
+                    "💰 {} {} {} @ {} (fee {}) [{}]",
+                    trade.side, trade.vol, trade.pair, trade.price, trade.fee, trade.trade_id
+                );
+            }
+        }
     }
 
     Ok(())
@@ -1,4 +1,4 @@
-use kraken_sdk::{aggregator::TradeAggregator, KrakenClient};
+use kraken_sdk::{aggregator::TradeAggregator, indicators::CandleSeries, KrakenClient};
 use std::error::Error;
 
 #[tokio::main]
@@ -20,11 +20,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // 3. Strategy State
     // We'll use 10-second candles for this demo (faster feedback)
     let mut aggregator = TradeAggregator::new(10);
-    let mut candles = Vec::new();
 
     // SMA Periods
     let fast_period = 5;
     let slow_period = 20;
+    let rsi_period = 14;
+    // Keep extra history so Wilder's RSI smoothing has settled
+    let mut candles = CandleSeries::with_max_len(100);
 
     println!(
         "📈 Strategy: SMA Crossover (Fast={}, Slow={})",
@@ -41,24 +43,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                 // Check if a new candle is formed
                 if let Some(candle) = aggregator.check_flush(trade_time) {
-                    candles.push(candle.clone());
-
-                    // Keep history manageable
-                    if candles.len() > slow_period + 1 {
-                        candles.remove(0);
-                    }
+                    candles.push(candle);
 
                     // Calculate Indicators
-                    if candles.len() >= slow_period {
-                        let fast_sma = calculate_sma(&candles, fast_period);
-                        let slow_sma = calculate_sma(&candles, slow_period);
-
+                    if let (Some(fast_sma), Some(slow_sma)) =
+                        (candles.sma(fast_period), candles.sma(slow_period))
+                    {
                         let price = candle.close;
 
                         println!(
                             "🕯️ Candle Closed: ${:.2} | SMA({}): {:.2} | SMA({}): {:.2}",
                             price, fast_period, fast_sma, slow_period, slow_sma
                         );
+                        if let Some(rsi) = candles.rsi(rsi_period) {
+                            println!("   RSI({}): {:.1}", rsi_period, rsi);
+                        }
 
                         // Signal Logic
                         if fast_sma > slow_sma {
@@ -81,12 +80,3 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
-
-fn calculate_sma(candles: &[kraken_sdk::models::Candle], period: usize) -> f64 {
-    if candles.len() < period {
-        return 0.0;
-    }
-    let start = candles.len() - period;
-    let sum: f64 = candles[start..].iter().map(|c| c.close).sum();
-    sum / period as f64
-}
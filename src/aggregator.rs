@@ -1,25 +1,144 @@
-use crate::models::{Candle, Trade};
+use crate::clock::{Clock, SystemClock};
+use crate::models::{Candle, Side, Trade, TradeData};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+use tracing::warn;
 
-pub struct TradeAggregator {
+/// Where [`TradeAggregator`] puts candle boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CandleAlignment {
+    /// Boundaries at multiples of the interval since the Unix epoch (default), matching
+    /// Kraken's own OHLC channel. The first candle is usually truncated: it only holds the
+    /// trades from the first one received until the next boundary.
+    #[default]
+    Epoch,
+    /// Boundaries at multiples of the interval since the first trade received, so the
+    /// first candle is a full interval. Trades older than the first one fall into the
+    /// first candle.
+    FirstTrade,
+}
+
+/// Builds OHLCV candles from trades, closing them on trade time or, with
+/// [`flush_due`](Self::flush_due), on the time of its [`Clock`].
+pub struct TradeAggregator<C = SystemClock> {
+    clock: C,
     interval_seconds: u64,
     current_candle: Option<Candle>,
+    fill_gaps: bool,
+    alignment: CandleAlignment,
+    // Start of the first candle, fixed by the first trade
+    origin: Option<u64>,
+    // Start of the newest candle, open or already closed
+    latest_start: Option<u64>,
+    out_of_order: u64,
+    malformed: u64,
 }
 
 impl TradeAggregator {
+    /// Creates an aggregator with [`CandleAlignment::Epoch`] boundaries.
     pub fn new(interval_seconds: u64) -> Self {
+        Self::new_with_alignment(interval_seconds, CandleAlignment::Epoch)
+    }
+
+    /// Creates an aggregator whose candle boundaries follow `alignment`.
+    pub fn new_with_alignment(interval_seconds: u64, alignment: CandleAlignment) -> Self {
         Self {
+            clock: SystemClock,
             interval_seconds,
             current_candle: None,
+            fill_gaps: false,
+            alignment,
+            origin: None,
+            latest_start: None,
+            out_of_order: 0,
+            malformed: 0,
+        }
+    }
+}
+
+impl<C: Clock> TradeAggregator<C> {
+    /// Replaces the clock used by [`flush_due`](Self::flush_due), e.g. with a
+    /// [`MockClock`](crate::clock::MockClock) for deterministic tests.
+    pub fn with_clock<D: Clock>(self, clock: D) -> TradeAggregator<D> {
+        TradeAggregator {
+            clock,
+            interval_seconds: self.interval_seconds,
+            current_candle: self.current_candle,
+            fill_gaps: self.fill_gaps,
+            alignment: self.alignment,
+            origin: self.origin,
+            latest_start: self.latest_start,
+            out_of_order: self.out_of_order,
+            malformed: self.malformed,
         }
     }
 
+    /// Number of trades dropped because they belonged to an earlier candle than the
+    /// newest one. Non-zero means the emitted candles are missing some late trades.
+    pub fn out_of_order_trades(&self) -> u64 {
+        self.out_of_order
+    }
+
+    /// Number of trades dropped because their price, volume or time is not a finite
+    /// number. They are skipped rather than counted as zeros, which would drag the
+    /// candle's low (and volume-weighted figures) down.
+    pub fn malformed_trades(&self) -> u64 {
+        self.malformed
+    }
+
+    /// Start time of the candle that `time` (Unix seconds) belongs to.
+    fn candle_start(&self, time: u64) -> u64 {
+        let origin = match self.alignment {
+            CandleAlignment::Epoch => 0,
+            CandleAlignment::FirstTrade => self.origin.unwrap_or(time),
+        };
+        let elapsed = time.saturating_sub(origin);
+        origin + (elapsed / self.interval_seconds) * self.interval_seconds
+    }
+
+    /// Makes [`flush`](Self::flush) emit a flat candle (open = high = low = close =
+    /// previous close, volume 0) for every interval without trades, so the series has
+    /// no time gaps. Off by default.
+    pub fn with_gap_filling(mut self, enabled: bool) -> Self {
+        self.fill_gaps = enabled;
+        self
+    }
+
     pub fn update(&mut self, trade: &Trade) {
-        let price = trade.price.parse::<f64>().unwrap_or(0.0);
-        let volume = trade.volume.parse::<f64>().unwrap_or(0.0);
-        let time = trade.time.parse::<f64>().unwrap_or(0.0) as u64;
+        let parsed = (trade.price_f64(), trade.volume_f64(), trade.time_f64());
+        let (Ok(price), Ok(volume), Ok(time)) = parsed else {
+            self.drop_malformed(trade);
+            return;
+        };
+        if !(price.is_finite() && volume.is_finite() && time.is_finite()) {
+            self.drop_malformed(trade);
+            return;
+        }
+        let time = time as u64;
+        let (buy_volume, sell_volume) = match trade.taker_side() {
+            Some(Side::Buy) => (volume, 0.0),
+            Some(Side::Sell) => (0.0, volume),
+            None => (0.0, 0.0),
+        };
 
         // Determine the start time of the candle this trade belongs to
-        let candle_start = (time / self.interval_seconds) * self.interval_seconds;
+        self.origin.get_or_insert(time);
+        let candle_start = self.candle_start(time);
+
+        // A late trade for a candle that is closed (or being replaced) would otherwise
+        // open a new candle in the past, so it is dropped and counted instead
+        if self
+            .latest_start
+            .is_some_and(|latest| candle_start < latest)
+        {
+            self.out_of_order += 1;
+            warn!(
+                "Dropping out-of-order trade at {} (candle {} is already closed)",
+                time, candle_start
+            );
+            return;
+        }
+        self.latest_start = Some(candle_start);
 
         if let Some(candle) = &mut self.current_candle {
             if candle.start_time == candle_start {
@@ -28,6 +147,9 @@ impl TradeAggregator {
                 candle.low = candle.low.min(price);
                 candle.close = price;
                 candle.volume += volume;
+                candle.buy_volume += buy_volume;
+                candle.sell_volume += sell_volume;
+                candle.count += 1;
                 return;
             } else {
                 // This trade belongs to a new candle (or we missed some, but we assume stream is roughly ordered)
@@ -46,14 +168,24 @@ impl TradeAggregator {
             volume,
             start_time: candle_start,
             interval_seconds: self.interval_seconds,
+            buy_volume,
+            sell_volume,
+            count: 1,
         });
     }
 
+    fn drop_malformed(&mut self, trade: &Trade) {
+        self.malformed += 1;
+        warn!(
+            "Dropping trade with unparseable numbers (price {:?}, volume {:?}, time {:?})",
+            trade.price, trade.volume, trade.time
+        );
+    }
+
     /// Checks if the current candle is "done" based on the new time, returning it if so.
     /// This is a simplified logic: we return the *previous* candle if the *new* time belongs to a later interval.
     pub fn check_flush(&mut self, new_trade_time: f64) -> Option<Candle> {
-        let time = new_trade_time as u64;
-        let new_candle_start = (time / self.interval_seconds) * self.interval_seconds;
+        let new_candle_start = self.candle_start(new_trade_time as u64);
 
         if let Some(candle) = &self.current_candle {
             if new_candle_start > candle.start_time {
@@ -63,4 +195,672 @@ impl TradeAggregator {
         }
         None
     }
+
+    /// Like [`check_flush`](Self::check_flush), but also returns the flat candles for
+    /// skipped intervals when [gap filling](Self::with_gap_filling) is on, oldest first.
+    pub fn flush(&mut self, new_trade_time: f64) -> Vec<Candle> {
+        let Some(closed) = self.check_flush(new_trade_time) else {
+            return Vec::new();
+        };
+        let mut candles = vec![closed];
+        if self.fill_gaps {
+            let new_candle_start = self.candle_start(new_trade_time as u64);
+            let mut start = closed.start_time + self.interval_seconds;
+            while start < new_candle_start {
+                candles.push(Candle {
+                    open: closed.close,
+                    high: closed.close,
+                    low: closed.close,
+                    close: closed.close,
+                    volume: 0.0,
+                    start_time: start,
+                    interval_seconds: self.interval_seconds,
+                    ..Default::default()
+                });
+                start += self.interval_seconds;
+            }
+        }
+        candles
+    }
+
+    /// Closes the current candle once the clock has passed its end, even if no newer
+    /// trade arrived, so candles keep coming in a quiet market. Returns what
+    /// [`flush`](Self::flush) would for a trade at the clock's time.
+    ///
+    /// Trades that arrive afterwards for a candle closed this way are dropped and
+    /// counted in [`out_of_order_trades`](Self::out_of_order_trades).
+    pub fn flush_due(&mut self) -> Vec<Candle> {
+        let now = self.clock.now();
+        let candles = self.flush(now);
+        if !candles.is_empty() {
+            let start = self.candle_start(now as u64);
+            self.latest_start = self.latest_start.max(Some(start));
+        }
+        candles
+    }
+}
+
+/// One [`TradeAggregator`] per pair, fed whole trade messages.
+///
+/// # Example
+///
+/// ```rust
+/// use kraken_sdk::aggregator::MultiPairAggregator;
+/// use kraken_sdk::models::KrakenEvent;
+///
+/// let mut candles = MultiPairAggregator::new(60);
+/// for frame in [
+///     r#"[0,[["100.0","1.0","10.0","b","m",""]],"trade","XBT/USD"]"#,
+///     r#"[0,[["101.0","1.0","70.0","s","m",""]],"trade","XBT/USD"]"#,
+/// ] {
+///     let event: KrakenEvent = serde_json::from_str(frame).unwrap();
+///     for candle in candles.process(&event.try_into_trade_data().unwrap()) {
+///         assert_eq!((candle.start_time, candle.close), (0, 100.0));
+///     }
+/// }
+/// ```
+pub struct MultiPairAggregator {
+    interval_seconds: u64,
+    pairs: HashMap<String, TradeAggregator>,
+}
+
+impl MultiPairAggregator {
+    pub fn new(interval_seconds: u64) -> Self {
+        Self {
+            interval_seconds,
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Adds every trade in `data` to its pair's candle, returning the candles the
+    /// trades closed, oldest first.
+    pub fn process(&mut self, data: &TradeData) -> Vec<Candle> {
+        let aggregator = self
+            .pairs
+            .entry(data.pair.clone())
+            .or_insert_with(|| TradeAggregator::new(self.interval_seconds));
+        let mut closed = Vec::new();
+        for trade in &data.data {
+            if let Ok(time) = trade.time_f64() {
+                closed.extend(aggregator.flush(time));
+            }
+            aggregator.update(trade);
+        }
+        closed
+    }
+
+    /// The aggregator for `pair`, once a trade for it has been processed.
+    pub fn aggregator(&self, pair: &str) -> Option<&TradeAggregator> {
+        self.pairs.get(pair)
+    }
+}
+
+/// A hole in the trade tape: the trades with ids `first_missing..=last_missing` were never seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeGap {
+    pub pair: String,
+    pub first_missing: u64,
+    pub last_missing: u64,
+}
+
+impl TradeGap {
+    /// Number of trades missing from the tape.
+    pub fn missing(&self) -> u64 {
+        self.last_missing - self.first_missing + 1
+    }
+}
+
+/// Detects missing trades by checking that trade ids are contiguous per pair.
+///
+/// Kraken assigns sequential trade ids per pair, so a jump (e.g. after a reconnect)
+/// means the tape has a hole that could be backfilled via REST. Trades without an id
+/// are ignored, as are repeated or older ids (that is deduplication, not a gap).
+#[derive(Debug, Default)]
+pub struct TradeGapDetector {
+    last_ids: HashMap<String, u64>,
+}
+
+impl TradeGapDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks a single trade for `pair`, returning the gap before it if any.
+    pub fn check(&mut self, pair: &str, trade: &Trade) -> Option<TradeGap> {
+        let id = trade.trade_id?;
+        let last = self.last_ids.get(pair).copied();
+        match last {
+            Some(last) if id <= last => None,
+            Some(last) => {
+                self.last_ids.insert(pair.to_string(), id);
+                if id > last + 1 {
+                    let gap = TradeGap {
+                        pair: pair.to_string(),
+                        first_missing: last + 1,
+                        last_missing: id - 1,
+                    };
+                    warn!(
+                        "TradeGap on {}: missing {} trade(s) ({}..={})",
+                        gap.pair,
+                        gap.missing(),
+                        gap.first_missing,
+                        gap.last_missing
+                    );
+                    Some(gap)
+                } else {
+                    None
+                }
+            }
+            None => {
+                self.last_ids.insert(pair.to_string(), id);
+                None
+            }
+        }
+    }
+
+    /// Checks every trade in a `TradeData` message, returning all gaps found.
+    pub fn process(&mut self, data: &TradeData) -> Vec<TradeGap> {
+        data.data
+            .iter()
+            .filter_map(|trade| self.check(&data.pair, trade))
+            .collect()
+    }
+}
+
+/// How far back a [`VwapTracker`] looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VwapWindow {
+    /// Trades whose timestamp is within this duration of the newest trade.
+    Time(Duration),
+    /// The most recent `n` trades.
+    Trades(usize),
+}
+
+/// Volume-weighted average price over a rolling window of trades.
+///
+/// Running sums are kept alongside a deque of the trades in the window, so each
+/// update is O(1) amortized. Trades are expected in (roughly) time order, as Kraken
+/// sends them.
+#[derive(Debug, Clone)]
+pub struct VwapTracker {
+    window: VwapWindow,
+    trades: VecDeque<(f64, f64, f64)>, // (time, price, volume)
+    notional: f64,
+    volume: f64,
+}
+
+impl VwapTracker {
+    pub fn new(window: VwapWindow) -> Self {
+        Self {
+            window,
+            trades: VecDeque::new(),
+            notional: 0.0,
+            volume: 0.0,
+        }
+    }
+
+    /// Adds a trade to the window and evicts trades that fell out of it.
+    /// Trades with an unparseable price, volume or time are ignored.
+    pub fn update(&mut self, trade: &Trade) {
+        let (Ok(price), Ok(volume), Ok(time)) =
+            (trade.price_f64(), trade.volume_f64(), trade.time_f64())
+        else {
+            return;
+        };
+        self.trades.push_back((time, price, volume));
+        self.notional += price * volume;
+        self.volume += volume;
+
+        loop {
+            let expired = match (self.window, self.trades.front()) {
+                (VwapWindow::Time(span), Some(&(oldest, _, _))) => {
+                    oldest < time - span.as_secs_f64()
+                }
+                (VwapWindow::Trades(n), Some(_)) => self.trades.len() > n,
+                (_, None) => false,
+            };
+            if !expired {
+                break;
+            }
+            let (_, price, volume) = self.trades.pop_front().unwrap();
+            self.notional -= price * volume;
+            self.volume -= volume;
+        }
+        if self.trades.is_empty() {
+            // Don't let floating point residue accumulate across empty windows
+            self.notional = 0.0;
+            self.volume = 0.0;
+        }
+    }
+
+    /// Adds every trade in a `TradeData` message.
+    pub fn process(&mut self, data: &TradeData) {
+        for trade in &data.data {
+            self.update(trade);
+        }
+    }
+
+    /// The current VWAP, or `None` while the window holds no volume.
+    pub fn vwap(&self) -> Option<f64> {
+        (self.volume > 0.0).then(|| self.notional / self.volume)
+    }
+
+    /// Total volume in the window. A VWAP over little volume deserves little trust.
+    pub fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    /// Number of trades in the window.
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+}
+
+/// Cumulative per-pair VWAP over every trade seen since creation or the last
+/// [`reset`](Self::reset), a common benchmark for execution quality.
+///
+/// Unlike [`VwapTracker`] nothing is ever evicted, so only two running sums are kept
+/// per pair. Nothing resets implicitly, not even a reconnect; call `reset` to start a
+/// new session.
+#[derive(Debug, Default)]
+pub struct SessionVwap {
+    totals: HashMap<String, (f64, f64)>, // pair -> (notional, volume)
+}
+
+impl SessionVwap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a trade for `pair`. Trades with an unparseable price or volume are ignored.
+    pub fn update(&mut self, pair: &str, trade: &Trade) {
+        let (Ok(price), Ok(volume)) = (trade.price_f64(), trade.volume_f64()) else {
+            return;
+        };
+        let (notional, total) = self.totals.entry(pair.to_string()).or_default();
+        *notional += price * volume;
+        *total += volume;
+    }
+
+    /// Adds every trade in a `TradeData` message.
+    pub fn process(&mut self, data: &TradeData) {
+        for trade in &data.data {
+            self.update(&data.pair, trade);
+        }
+    }
+
+    /// The session VWAP of `pair`, or `None` while no volume has traded.
+    pub fn session_vwap(&self, pair: &str) -> Option<f64> {
+        let &(notional, volume) = self.totals.get(pair)?;
+        (volume > 0.0).then(|| notional / volume)
+    }
+
+    /// Total volume traded in `pair` this session.
+    pub fn session_volume(&self, pair: &str) -> f64 {
+        self.totals.get(pair).map_or(0.0, |&(_, volume)| volume)
+    }
+
+    /// Starts a new session for every pair.
+    pub fn reset(&mut self) {
+        self.totals.clear();
+    }
+
+    /// Starts a new session for `pair` only.
+    pub fn reset_pair(&mut self, pair: &str) {
+        self.totals.remove(pair);
+    }
+}
+
+/// Volume traded in one time-of-day bucket of a [`VolumeProfile`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VolumeBucket {
+    pub volume: f64,
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+    /// Number of trades.
+    pub count: u64,
+}
+
+/// Volume by time of day (UTC), for spotting recurring intraday liquidity patterns.
+///
+/// Unlike candles, trades from different days land in the same bucket: with hourly
+/// buckets, every trade between 14:00 and 15:00 adds to the 14:00 bucket whatever its
+/// date. Trades with an unparseable volume or time are ignored.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use kraken_sdk::aggregator::VolumeProfile;
+/// use std::time::Duration;
+/// # async fn example(client: kraken_sdk::KrakenClient) {
+/// let mut profile = VolumeProfile::new(Duration::from_secs(3600));
+/// let mut rx = client.subscribe_events();
+/// while let Ok(event) = rx.recv().await {
+///     if let Some(trades) = event.try_into_trade_data() {
+///         profile.process(&trades);
+///     }
+/// }
+/// for (start, bucket) in profile.buckets() {
+///     println!("{:02}:00 UTC  {:.4}", start / 3600, bucket.volume);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct VolumeProfile {
+    bucket_seconds: u32,
+    buckets: BTreeMap<u32, VolumeBucket>, // bucket start, in seconds since midnight
+}
+
+impl VolumeProfile {
+    const DAY: u32 = 86_400;
+
+    /// A profile with buckets of `bucket` (at least a second, at most a day), starting
+    /// at midnight UTC. A width that does not divide the day leaves a shorter last bucket.
+    pub fn new(bucket: Duration) -> Self {
+        Self {
+            bucket_seconds: bucket.as_secs().clamp(1, u64::from(Self::DAY)) as u32,
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, trade: &Trade) {
+        let (Ok(volume), Ok(time)) = (trade.volume_f64(), trade.time_f64()) else {
+            return;
+        };
+        let second_of_day = (time as u64 % Self::DAY as u64) as u32;
+        let start = second_of_day - second_of_day % self.bucket_seconds;
+        let bucket = self.buckets.entry(start).or_default();
+        bucket.volume += volume;
+        match trade.taker_side() {
+            Some(Side::Buy) => bucket.buy_volume += volume,
+            Some(Side::Sell) => bucket.sell_volume += volume,
+            None => {}
+        }
+        bucket.count += 1;
+    }
+
+    /// Adds every trade in a `TradeData` message.
+    pub fn process(&mut self, data: &TradeData) {
+        for trade in &data.data {
+            self.update(trade);
+        }
+    }
+
+    /// The buckets that saw trades, keyed by their start in seconds since midnight UTC.
+    pub fn buckets(&self) -> &BTreeMap<u32, VolumeBucket> {
+        &self.buckets
+    }
+
+    /// The bucket containing `second_of_day`, if it saw trades.
+    pub fn bucket_at(&self, second_of_day: u32) -> Option<&VolumeBucket> {
+        let second_of_day = second_of_day % Self::DAY;
+        self.buckets
+            .get(&(second_of_day - second_of_day % self.bucket_seconds))
+    }
+
+    /// Volume across all buckets.
+    pub fn total_volume(&self) -> f64 {
+        self.buckets.values().map(|bucket| bucket.volume).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn trade_with_id(id: u64) -> Trade {
+        serde_json::from_value(serde_json::json!([
+            "50000.0",
+            "0.1",
+            "1700000000.0",
+            "b",
+            "m",
+            "",
+            id
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_gap_detector_reports_missing_id() {
+        let mut detector = TradeGapDetector::new();
+        assert_eq!(detector.check("XBT/USD", &trade_with_id(10)), None);
+        assert_eq!(detector.check("XBT/USD", &trade_with_id(11)), None);
+
+        let gap = detector.check("XBT/USD", &trade_with_id(13)).unwrap();
+        assert_eq!(gap.first_missing, 12);
+        assert_eq!(gap.last_missing, 12);
+        assert_eq!(gap.missing(), 1);
+
+        assert_eq!(detector.check("XBT/USD", &trade_with_id(14)), None);
+    }
+
+    #[test]
+    fn test_gap_detector_ignores_duplicates_and_other_pairs() {
+        let mut detector = TradeGapDetector::new();
+        detector.check("XBT/USD", &trade_with_id(10));
+        assert_eq!(detector.check("XBT/USD", &trade_with_id(10)), None);
+        assert_eq!(detector.check("ETH/USD", &trade_with_id(500)), None);
+        assert_eq!(detector.check("XBT/USD", &trade_with_id(11)), None);
+    }
+
+    fn trade_at(price: &str, volume: &str, time: &str) -> Trade {
+        trade_on_side(price, volume, time, "b")
+    }
+
+    fn trade_on_side(price: &str, volume: &str, time: &str, side: &str) -> Trade {
+        serde_json::from_value(serde_json::json!([price, volume, time, side, "m", ""])).unwrap()
+    }
+
+    #[test]
+    fn test_session_vwap_accumulates_per_pair_until_reset() {
+        let mut session = SessionVwap::new();
+        assert_eq!(session.session_vwap("XBT/USD"), None);
+
+        session.update("XBT/USD", &trade_at("100.0", "1.0", "1"));
+        session.update("XBT/USD", &trade_at("200.0", "3.0", "2"));
+        session.update("XBT/USD", &trade_at("oops", "5.0", "3"));
+        session.update("ETH/USD", &trade_at("10.0", "2.0", "3"));
+        assert_eq!(session.session_vwap("XBT/USD"), Some(175.0));
+        assert_eq!(session.session_volume("XBT/USD"), 4.0);
+        assert_eq!(session.session_vwap("ETH/USD"), Some(10.0));
+
+        session.reset_pair("ETH/USD");
+        assert_eq!(session.session_vwap("ETH/USD"), None);
+        assert_eq!(session.session_vwap("XBT/USD"), Some(175.0));
+
+        session.reset();
+        assert_eq!(session.session_vwap("XBT/USD"), None);
+        assert_eq!(session.session_volume("XBT/USD"), 0.0);
+    }
+
+    #[test]
+    fn test_vwap_count_window() {
+        let mut vwap = VwapTracker::new(VwapWindow::Trades(2));
+        assert_eq!(vwap.vwap(), None);
+
+        vwap.update(&trade_at("100.0", "1.0", "1.0"));
+        vwap.update(&trade_at("200.0", "3.0", "2.0"));
+        assert_eq!(vwap.vwap(), Some(175.0));
+
+        // The first trade drops out
+        vwap.update(&trade_at("300.0", "1.0", "3.0"));
+        assert_eq!(vwap.len(), 2);
+        assert_eq!(vwap.volume(), 4.0);
+        assert_eq!(vwap.vwap(), Some(225.0));
+    }
+
+    #[test]
+    fn test_vwap_time_window() {
+        let mut vwap = VwapTracker::new(VwapWindow::Time(Duration::from_secs(60)));
+        vwap.update(&trade_at("100.0", "2.0", "1000.0"));
+        vwap.update(&trade_at("110.0", "2.0", "1030.0"));
+        assert_eq!(vwap.vwap(), Some(105.0));
+
+        // 1000 is more than 60s before 1070
+        vwap.update(&trade_at("120.0", "2.0", "1070.0"));
+        assert_eq!(vwap.len(), 2);
+        assert_eq!(vwap.vwap(), Some(115.0));
+
+        vwap.update(&trade_at("bad", "2.0", "1071.0"));
+        assert_eq!(vwap.len(), 2);
+    }
+
+    #[test]
+    fn test_volume_profile_buckets_by_time_of_day() {
+        let mut profile = VolumeProfile::new(Duration::from_secs(3600));
+        let day = 86_400.0;
+        profile.update(&trade_at("100.0", "1.0", "3600.0")); // 01:00, day 0
+        profile.update(&trade_at("100.0", "2.0", &(day + 5399.0).to_string())); // 01:29, day 1
+        profile.update(&trade_at(
+            "100.0",
+            "0.5",
+            &(2.0 * day + 86_399.0).to_string(),
+        )); // 23:59
+        profile.update(&trade_at("100.0", "bad", "7200.0"));
+
+        let starts: Vec<u32> = profile.buckets().keys().copied().collect();
+        assert_eq!(starts, vec![3600, 82_800]);
+        let one_am = profile.bucket_at(5000).unwrap();
+        assert_eq!((one_am.volume, one_am.count), (3.0, 2));
+        assert_eq!(profile.bucket_at(7200), None);
+        assert_eq!(profile.total_volume(), 3.5);
+
+        // Widths beyond a day clamp to one bucket per day, even past u32::MAX seconds
+        let huge = VolumeProfile::new(Duration::from_secs((1 << 32) + 5));
+        assert_eq!(huge.bucket_seconds, 86_400);
+        assert_eq!(VolumeProfile::new(Duration::ZERO).bucket_seconds, 1);
+    }
+
+    #[test]
+    fn test_flush_fills_skipped_intervals() {
+        let mut aggregator = TradeAggregator::new(60).with_gap_filling(true);
+        aggregator.update(&trade_at("100.0", "1.0", "0.0"));
+        aggregator.update(&trade_at("105.0", "1.0", "30.0"));
+        assert!(aggregator.flush(59.0).is_empty());
+
+        // Next trade lands three intervals later: one real candle, two flat ones
+        let candles = aggregator.flush(200.0);
+        let starts: Vec<u64> = candles.iter().map(|c| c.start_time).collect();
+        assert_eq!(starts, vec![0, 60, 120]);
+        assert_eq!(candles[0].close, 105.0);
+        assert_eq!(candles[0].count, 2);
+        for filler in &candles[1..] {
+            assert_eq!(
+                (filler.open, filler.high, filler.low),
+                (105.0, 105.0, 105.0)
+            );
+            assert_eq!(filler.close, 105.0);
+            assert_eq!(filler.volume, 0.0);
+            assert_eq!(filler.count, 0);
+        }
+
+        let mut plain = TradeAggregator::new(60);
+        plain.update(&trade_at("100.0", "1.0", "0.0"));
+        assert_eq!(plain.flush(200.0).len(), 1);
+    }
+
+    #[test]
+    fn test_flush_due_closes_candles_on_clock_time() {
+        let clock = MockClock::new(10.0);
+        let mut aggregator = TradeAggregator::new(60)
+            .with_gap_filling(true)
+            .with_clock(clock.clone());
+        aggregator.update(&trade_at("100.0", "1.0", "5.0"));
+        assert!(aggregator.flush_due().is_empty());
+
+        clock.advance(Duration::from_secs(49));
+        assert!(aggregator.flush_due().is_empty());
+        clock.advance(Duration::from_secs(80));
+        let candles = aggregator.flush_due();
+        let starts: Vec<u64> = candles.iter().map(|c| c.start_time).collect();
+        assert_eq!(starts, vec![0, 60]);
+        assert!(aggregator.flush_due().is_empty());
+
+        // A straggler for the closed candle does not reopen it
+        aggregator.update(&trade_at("101.0", "1.0", "59.0"));
+        assert_eq!(aggregator.out_of_order_trades(), 1);
+        aggregator.update(&trade_at("102.0", "1.0", "140.0"));
+        clock.set(180.0);
+        assert_eq!(aggregator.flush_due()[0].start_time, 120);
+    }
+
+    #[test]
+    fn test_first_trade_alignment_starts_at_first_trade() {
+        let mut epoch = TradeAggregator::new(60);
+        let mut aligned = TradeAggregator::new_with_alignment(60, CandleAlignment::FirstTrade);
+        epoch.update(&trade_at("100.0", "1.0", "50.0"));
+        aligned.update(&trade_at("100.0", "1.0", "50.0"));
+
+        // Epoch: [0, 60) is cut short after one trade; first-trade: [50, 110) holds both
+        let truncated = epoch.check_flush(70.0).unwrap();
+        assert_eq!((truncated.start_time, truncated.count), (0, 1));
+        assert!(aligned.check_flush(70.0).is_none());
+        aligned.update(&trade_at("101.0", "1.0", "70.0"));
+        assert!(aligned.check_flush(109.0).is_none());
+        let full = aligned.check_flush(110.0).unwrap();
+        assert_eq!((full.start_time, full.count), (50, 2));
+
+        aligned.update(&trade_at("102.0", "1.0", "175.0"));
+        assert_eq!(aligned.check_flush(230.0).unwrap().start_time, 170);
+    }
+
+    #[test]
+    fn test_malformed_trades_are_skipped_and_counted() {
+        let mut aggregator = TradeAggregator::new(60);
+        aggregator.update(&trade_at("100.0", "1.0", "10.0"));
+        aggregator.update(&trade_at("garbage", "2.0", "11.0"));
+        aggregator.update(&trade_at("99.0", "", "12.0"));
+        aggregator.update(&trade_at("98.0", "1.0", "later"));
+        aggregator.update(&trade_at("NaN", "1.0", "13.0"));
+        aggregator.update(&trade_at("101.0", "1.0", "14.0"));
+        assert_eq!(aggregator.malformed_trades(), 4);
+
+        let candle = aggregator.check_flush(60.0).unwrap();
+        assert_eq!((candle.low, candle.high), (100.0, 101.0));
+        assert_eq!((candle.volume, candle.count), (2.0, 2));
+    }
+
+    #[test]
+    fn test_out_of_order_trades_are_dropped_and_counted() {
+        let mut aggregator = TradeAggregator::new(60);
+        aggregator.update(&trade_at("100.0", "1.0", "10.0"));
+        let closed = aggregator.check_flush(65.0).unwrap();
+        aggregator.update(&trade_at("101.0", "1.0", "65.0"));
+
+        // Belongs to the closed [0, 60) candle: dropped, the open candle is untouched
+        aggregator.update(&trade_at("999.0", "5.0", "59.0"));
+        assert_eq!(aggregator.out_of_order_trades(), 1);
+        // Late within the open candle is fine
+        aggregator.update(&trade_at("102.0", "1.0", "61.0"));
+        assert_eq!(aggregator.out_of_order_trades(), 1);
+
+        let open = aggregator.check_flush(120.0).unwrap();
+        assert_eq!(closed.count, 1);
+        assert_eq!((open.start_time, open.count, open.high), (60, 2, 102.0));
+    }
+
+    #[test]
+    fn test_candle_splits_volume_by_aggressor() {
+        let mut aggregator = TradeAggregator::new(60);
+        aggregator.update(&trade_on_side("100.0", "1.5", "1.0", "b"));
+        aggregator.update(&trade_on_side("100.0", "0.5", "1.0", "s"));
+        aggregator.update(&trade_on_side("100.0", "2.0", "1.0", "b"));
+
+        let candle = aggregator.check_flush(60.0).unwrap();
+        assert_eq!(candle.volume, 4.0);
+        assert_eq!(candle.count, 3);
+        assert_eq!(candle.buy_volume, 3.5);
+        assert_eq!(candle.sell_volume, 0.5);
+        assert_eq!(candle.delta(), 3.0);
+    }
 }
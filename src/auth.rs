@@ -1,17 +1,44 @@
+use crate::error::{KrakenError, Result};
 use base64::{engine::general_purpose, Engine as _};
-use eyre::Result;
 use hmac::{Hmac, Mac};
 use reqwest::Client;
 use serde::Deserialize;
 use sha2::{Digest, Sha256, Sha512};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
 
 type HmacSha512 = Hmac<Sha512>;
 
+const KRAKEN_API_URL: &str = "https://api.kraken.com";
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_BREAKER_THRESHOLD: u32 = 5;
+const DEFAULT_BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+
 pub struct Authenticator {
     api_key: String,
     api_secret: String,
     client: Client,
+    base_url: String,
+    last_nonce: AtomicU64,
+    max_retries: u32,
+    retry_backoff: Duration,
+    breaker_threshold: u32,
+    breaker_cooldown: Duration,
+    breaker: Mutex<BreakerState>,
+    status_sender: Option<mpsc::UnboundedSender<KrakenError>>,
+}
+
+/// Consecutive failed token requests and, once tripped, when requests may resume.
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    // Start of the single request let through after the cooldown (half-open)
+    probe_started: Option<Instant>,
 }
 
 #[derive(Deserialize)]
@@ -27,21 +54,236 @@ struct TokenResult {
 
 impl Authenticator {
     pub fn new(api_key: String, api_secret: String) -> Self {
+        Self::with_client(api_key, api_secret, Client::new())
+    }
+
+    /// Like [`new`](Self::new), but sends requests through an existing `reqwest::Client`.
+    ///
+    /// Use this to apply your own timeouts, proxy or TLS settings, or to share a
+    /// connection pool with the rest of the application.
+    pub fn with_client(api_key: String, api_secret: String, client: Client) -> Self {
         Self {
             api_key,
             api_secret,
-            client: Client::new(),
+            client,
+            base_url: KRAKEN_API_URL.to_string(),
+            last_nonce: AtomicU64::new(0),
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            breaker_threshold: DEFAULT_BREAKER_THRESHOLD,
+            breaker_cooldown: DEFAULT_BREAKER_COOLDOWN,
+            breaker: Mutex::new(BreakerState::default()),
+            status_sender: None,
         }
     }
 
+    /// Overrides the REST API base URL (default: `https://api.kraken.com`).
+    ///
+    /// Mainly useful for pointing the authenticator at a mock server in tests.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Configures how token requests recover from transient failures (default: 3 retries,
+    /// starting at 500ms).
+    ///
+    /// Network errors, HTTP 5xx/429 responses and temporary Kraken errors such as
+    /// `EService:Unavailable` are retried, with the delay doubling after each attempt.
+    /// Credential errors like `EAPI:Invalid key` fail immediately. Pass `0` to disable retries.
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Configures the circuit breaker that protects the API key from being rate-limited
+    /// (default: open after 5 consecutive failures, for 60s).
+    ///
+    /// A failure is a token request that still failed after its retries, or that Kraken
+    /// rejected outright (e.g. a revoked key). Once `threshold` of them happen in a row,
+    /// every request fails immediately with [`KrakenError::Auth`] for `cooldown`, without
+    /// contacting Kraken. After the cooldown one request is let through while concurrent
+    /// ones keep failing fast; a success closes the breaker, a failure opens it again. A
+    /// probe that never completes (e.g. its future was dropped) is replaced by a new one
+    /// after another `cooldown`. Pass a `threshold` of `0` to disable it.
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.breaker_threshold = threshold;
+        self.breaker_cooldown = cooldown;
+        self
+    }
+
+    /// Sends a fatal [`KrakenError::Auth`] to `sender` every time the circuit breaker
+    /// opens, so a supervisor can alert or shut down instead of retrying forever.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::auth::Authenticator;
+    /// # async fn example() {
+    /// let (tx, mut fatal) = tokio::sync::mpsc::unbounded_channel();
+    /// let auth = Authenticator::new("key".into(), "secret".into()).with_status_channel(tx);
+    /// tokio::spawn(async move {
+    ///     while let Some(err) = fatal.recv().await {
+    ///         eprintln!("authentication disabled: {}", err);
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn with_status_channel(mut self, sender: mpsc::UnboundedSender<KrakenError>) -> Self {
+        self.status_sender = Some(sender);
+        self
+    }
+
+    /// Returns the nonce for the next signed request.
+    ///
+    /// Nonces follow the wall clock in milliseconds but are strictly increasing: two
+    /// requests in the same millisecond, or a clock that steps backwards, still get
+    /// distinct, ascending values, so Kraken never rejects them as `EAPI:Invalid nonce`.
+    pub fn next_nonce(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let prev = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .expect("closure always returns Some");
+        now.max(prev + 1)
+    }
+
+    /// Fetches a WebSocket token for the private feeds.
+    ///
+    /// Transient failures are retried as configured by [`with_retries`](Self::with_retries);
+    /// once the retries are used up the last failure is returned as [`KrakenError::Auth`].
+    /// While the [circuit breaker](Self::with_circuit_breaker) is open this fails without
+    /// contacting Kraken.
     pub async fn get_ws_token(&self) -> Result<String> {
-        let nonce = SystemTime::now()
-            .duration_since(UNIX_EPOCH)?
-            .as_millis()
-            .to_string();
+        self.check_breaker()?;
+        let outcome = self.fetch_ws_token().await;
+        self.record_outcome(&outcome);
+        outcome
+    }
+
+    async fn fetch_ws_token(&self) -> Result<String> {
+        let resp = self.request_ws_token_with_retries().await?;
+
+        if !resp.error.is_empty() {
+            return Err(KrakenError::Auth(format!(
+                "Kraken API Error: {:?}",
+                resp.error
+            )));
+        }
+
+        resp.result
+            .map(|r| r.token)
+            .ok_or_else(|| KrakenError::Auth("token missing from Kraken response".to_string()))
+    }
+
+    /// Checks that the API key and secret are accepted by Kraken.
+    ///
+    /// This makes one cheap authenticated call (`GetWebSocketsToken`) so that a typo in the
+    /// credentials fails fast at startup instead of on the first real private request.
+    ///
+    /// # Errors
+    ///
+    /// Returns a descriptive error if the secret is not valid base64, if Kraken rejects the
+    /// key (`EAPI:Invalid key`), if the signature does not match (`EAPI:Invalid signature`,
+    /// usually a wrong secret), or if the key lacks the required permission.
+    pub async fn validate(&self) -> Result<()> {
+        self.check_breaker()?;
+        let outcome = match self.request_ws_token_with_retries().await {
+            Ok(resp) => check_credential_errors(&resp.error),
+            Err(e) => Err(e),
+        };
+        self.record_outcome(&outcome);
+        outcome
+    }
+
+    /// Fails fast while the circuit breaker is open, and lets a single probe through once
+    /// the cooldown has passed.
+    fn check_breaker(&self) -> Result<()> {
+        let mut state = self.breaker.lock().unwrap();
+        let Some(until) = state.open_until else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        if now < until {
+            return Err(KrakenError::Auth(format!(
+                "circuit breaker open after {} consecutive auth failures; retrying in {:?}",
+                state.failures,
+                until - now
+            )));
+        }
+        let probing = state
+            .probe_started
+            .is_some_and(|started| now < started + self.breaker_cooldown);
+        if probing {
+            return Err(KrakenError::Auth(format!(
+                "circuit breaker half-open after {} consecutive auth failures; waiting for the probe request",
+                state.failures
+            )));
+        }
+        state.probe_started = Some(now);
+        Ok(())
+    }
+
+    /// Counts consecutive failures, opening the breaker once the threshold is reached.
+    fn record_outcome<T>(&self, outcome: &Result<T>) {
+        let mut state = self.breaker.lock().unwrap();
+        let Err(e) = outcome else {
+            *state = BreakerState::default();
+            return;
+        };
+        state.failures += 1;
+        state.probe_started = None;
+        if self.breaker_threshold == 0 || state.failures < self.breaker_threshold {
+            return;
+        }
+        state.open_until = Some(Instant::now() + self.breaker_cooldown);
+        if let Some(sender) = &self.status_sender {
+            let _ = sender.send(KrakenError::Auth(format!(
+                "giving up after {} consecutive auth failures, pausing for {:?}: {}",
+                state.failures, self.breaker_cooldown, e
+            )));
+        }
+    }
+
+    async fn request_ws_token_with_retries(&self) -> Result<TokenResponse> {
+        let mut attempt = 0;
+        loop {
+            let outcome = self.request_ws_token().await;
+            let retryable = match &outcome {
+                Ok(resp) => resp.error.iter().any(|e| is_transient_api_error(e)),
+                Err(KrakenError::Http(e)) => is_transient_http_error(e),
+                Err(_) => false,
+            };
+            if !retryable {
+                return outcome;
+            }
+            if attempt >= self.max_retries {
+                let reason = match outcome {
+                    Ok(resp) => format!("Kraken API Error: {:?}", resp.error),
+                    Err(e) => e.to_string(),
+                };
+                return Err(KrakenError::Auth(format!(
+                    "token request failed after {} attempt(s): {}",
+                    attempt + 1,
+                    reason
+                )));
+            }
+            tokio::time::sleep(self.retry_backoff.saturating_mul(1 << attempt.min(16))).await;
+            attempt += 1;
+        }
+    }
+
+    async fn request_ws_token(&self) -> Result<TokenResponse> {
+        let nonce = self.next_nonce().to_string();
 
         let path = "/0/private/GetWebSocketsToken";
-        let url = format!("https://api.kraken.com{}", path);
+        let url = format!("{}{}", self.base_url, path);
         let post_data = format!("nonce={}", nonce);
 
         let signature = sign_request(&self.api_secret, path, &nonce, &post_data)?;
@@ -54,16 +296,63 @@ impl Authenticator {
             .header("API-Sign", signature)
             .body(post_data)
             .send()
-            .await?
-            .json::<TokenResponse>()
             .await?;
 
-        if !resp.error.is_empty() {
-            return Err(eyre::eyre!("Kraken API Error: {:?}", resp.error));
+        // Kraken answers API errors with 200 and an `error` array; a 5xx or 429 has no
+        // usable body, so surface it as an HTTP error the retry loop can recognise.
+        let status = resp.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            if let Err(e) = resp.error_for_status_ref() {
+                return Err(e.into());
+            }
         }
 
-        Ok(resp.result.unwrap().token)
+        Ok(resp.json::<TokenResponse>().await?)
+    }
+}
+
+/// Kraken errors that are worth retrying: service outages, rate limits and nonce races.
+///
+/// `EGeneral:Temporary lockout` is deliberately excluded: the lockout lasts far longer than
+/// the retry backoff, and hammering the API during it only extends it.
+fn is_transient_api_error(error: &str) -> bool {
+    error.starts_with("EService:")
+        || error.contains("Rate limit exceeded")
+        || error.contains("Internal error")
+        || error.contains("Invalid nonce")
+}
+
+fn is_transient_http_error(error: &reqwest::Error) -> bool {
+    if let Some(status) = error.status() {
+        return status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+    }
+    error.is_timeout() || error.is_connect() || error.is_request()
+}
+
+/// Turns Kraken's credential-related error strings into actionable messages.
+fn check_credential_errors(errors: &[String]) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    if errors.iter().any(|e| e.contains("Invalid key")) {
+        return Err(KrakenError::Auth(format!(
+            "Invalid API key: Kraken does not recognise this key (check for typos or a revoked key): {:?}",
+            errors
+        )));
+    }
+    if errors.iter().any(|e| e.contains("Invalid signature")) {
+        return Err(KrakenError::Auth(format!(
+            "Invalid signature: the API key was accepted but the secret does not match it: {:?}",
+            errors
+        )));
+    }
+    if errors.iter().any(|e| e.contains("Permission denied")) {
+        return Err(KrakenError::Auth(format!(
+            "Permission denied: enable 'WebSocket interface' access for this API key: {:?}",
+            errors
+        )));
     }
+    Err(KrakenError::Auth(format!("Kraken API Error: {:?}", errors)))
 }
 
 pub fn sign_request(api_secret: &str, path: &str, nonce: &str, post_data: &str) -> Result<String> {
@@ -74,8 +363,14 @@ pub fn sign_request(api_secret: &str, path: &str, nonce: &str, post_data: &str)
     let sha256_digest = sha256.finalize();
 
     // 2. HMAC-SHA512(path + sha256_digest, secret)
-    let secret_bytes = general_purpose::STANDARD.decode(api_secret)?;
-    let mut mac = HmacSha512::new_from_slice(&secret_bytes)?;
+    let secret_bytes = general_purpose::STANDARD.decode(api_secret).map_err(|e| {
+        KrakenError::Auth(format!(
+            "Invalid API secret: expected the base64 string shown by Kraken ({})",
+            e
+        ))
+    })?;
+    let mut mac = HmacSha512::new_from_slice(&secret_bytes)
+        .map_err(|e| KrakenError::Auth(format!("Invalid API secret: {}", e)))?;
     mac.update(path.as_bytes());
     mac.update(&sha256_digest);
     let sig_bytes = mac.finalize().into_bytes();
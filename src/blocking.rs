@@ -0,0 +1,139 @@
+use crate::error::Result;
+use crate::models::KrakenEvent;
+use crate::KrakenClient;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
+
+/// A blocking wrapper around [`KrakenClient`] for scripts that don't want an async runtime.
+///
+/// The async client runs on a current-thread Tokio runtime owned by a background thread,
+/// so the connection keeps being serviced (heartbeats, reconnects) between calls. Events
+/// are buffered in the client's broadcast channel until [`next_event`](Self::next_event)
+/// is called; size [`event_buffer`](crate::KrakenClientBuilder::event_buffer) for how far
+/// the script may fall behind. Dropping the wrapper shuts the runtime down.
+///
+/// Do not use it from inside an async context: the blocking calls panic there.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use kraken_sdk::blocking::BlockingKrakenClient;
+///
+/// let mut client = BlockingKrakenClient::connect().unwrap();
+/// client
+///     .subscribe(vec!["XBT/USD".to_string()], "trade")
+///     .unwrap();
+/// loop {
+///     if let Some(trades) = client.next_event().try_into_trade_data() {
+///         println!("{} trades on {}", trades.data.len(), trades.pair);
+///     }
+/// }
+/// ```
+pub struct BlockingKrakenClient {
+    client: KrakenClient,
+    events: broadcast::Receiver<KrakenEvent>,
+    handle: Handle,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BlockingKrakenClient {
+    /// Connects a default client, see [`KrakenClient::new`].
+    pub fn connect() -> Result<Self> {
+        Self::connect_with(KrakenClient::new())
+    }
+
+    /// Connects a client configured through [`KrakenClient::builder`].
+    ///
+    /// The client must not have been connected yet.
+    pub fn connect_with(client: KrakenClient) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+        let (shutdown, stopped) = oneshot::channel();
+        // Drives the driver task until the wrapper is dropped
+        let thread = std::thread::Builder::new()
+            .name("kraken-blocking".to_string())
+            .spawn(move || {
+                runtime.block_on(async {
+                    let _ = stopped.await;
+                });
+            })?;
+
+        let blocking = Self {
+            events: client.subscribe_events(),
+            client,
+            handle,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        };
+        blocking.handle.block_on(blocking.client.connect())?;
+        Ok(blocking)
+    }
+
+    /// Subscribes `pairs` to channel `name`, see [`KrakenClient::subscribe`].
+    ///
+    /// Returns once the request is queued; the confirmation arrives through
+    /// [`next_event`](Self::next_event) like every other message.
+    pub fn subscribe(&self, pairs: Vec<String>, name: &str) -> Result<()> {
+        self.handle
+            .block_on(self.client.subscribe(pairs, name, None))
+            .map(drop)
+    }
+
+    /// Unsubscribes `pairs` from channel `name`, see [`KrakenClient::unsubscribe`].
+    pub fn unsubscribe(&self, pairs: Vec<String>, name: &str) -> Result<()> {
+        self.handle
+            .block_on(self.client.unsubscribe(pairs, name))
+            .map(drop)
+    }
+
+    /// Blocks until the next event arrives.
+    ///
+    /// Events missed because the caller fell more than the event buffer behind are skipped.
+    pub fn next_event(&mut self) -> KrakenEvent {
+        self.handle.block_on(recv(&mut self.events))
+    }
+
+    /// Like [`next_event`](Self::next_event), but gives up after `timeout`.
+    pub fn next_event_timeout(&mut self, timeout: Duration) -> Option<KrakenEvent> {
+        let events = &mut self.events;
+        self.handle
+            .block_on(async { tokio::time::timeout(timeout, recv(events)).await.ok() })
+    }
+
+    /// The underlying async client, e.g. for [`metrics`](KrakenClient::metrics) or
+    /// [`is_connected`](KrakenClient::is_connected).
+    pub fn client(&self) -> &KrakenClient {
+        &self.client
+    }
+}
+
+impl Drop for BlockingKrakenClient {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Receives the next event, skipping over lag.
+async fn recv(events: &mut broadcast::Receiver<KrakenEvent>) -> KrakenEvent {
+    loop {
+        match events.recv().await {
+            Ok(event) => return event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Blocking client lagged behind, skipped {} events", skipped);
+            }
+            // The wrapper holds the client, so the sender outlives the receiver
+            Err(broadcast::error::RecvError::Closed) => unreachable!("event channel closed"),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::Result;
+use crate::models::{ConnectionState, KrakenEvent, LocalOrderBook, OrderBookData};
+use crate::KrakenClient;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+/// Books maintained by the client itself, see
+/// [`KrakenClientBuilder::manage_order_books`](crate::KrakenClientBuilder::manage_order_books).
+pub type SharedBooks = Arc<RwLock<BookMaintainer>>;
+
+/// What [`BookMaintainer::update`] did with a book message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BookUpdate {
+    /// The book is consistent after the update.
+    Consistent,
+    /// The pair is waiting for a fresh snapshot; the message was ignored.
+    Resyncing,
+    /// The checksum did not match. The book was cleared and the pair must be re-subscribed.
+    Mismatch,
+}
+
+/// Maintains one [`LocalOrderBook`] per pair and recovers from checksum mismatches.
+///
+/// On a mismatch the pair's book is cleared, the `book` channel is re-subscribed for
+/// that pair so Kraken sends a fresh snapshot, and a [`KrakenEvent::ChecksumMismatch`]
+/// is broadcast. Updates for the pair are ignored until the new snapshot arrives.
+///
+/// A reconnect makes every book stale. Feed all events to [`process`](Self::process)
+/// rather than only book messages, so the books are cleared on
+/// [`ConnectionState::Reconnecting`] and rebuilt from the snapshots Kraken sends once
+/// the subscriptions are replayed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use kraken_sdk::{book::BookMaintainer, KrakenClient};
+/// # async fn example() -> kraken_sdk::error::Result<()> {
+/// let client = KrakenClient::new();
+/// let mut rx = client.subscribe_events();
+/// client.connect().await?;
+/// client.subscribe(vec!["XBT/USD".to_string()], "book", None).await?;
+///
+/// let mut books = BookMaintainer::new();
+/// while let Ok(event) = rx.recv().await {
+///     books.process(&client, &event).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct BookMaintainer {
+    books: HashMap<String, LocalOrderBook>,
+    resyncing: HashSet<String>,
+    skip_checksums: bool,
+}
+
+impl BookMaintainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A maintainer that applies updates without checking their checksums, so it never
+    /// resyncs. Used by the client when checksum validation is turned off.
+    pub(crate) fn without_checksums() -> Self {
+        Self {
+            skip_checksums: true,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the local book for `pair`, if one has been built.
+    pub fn book(&self, pair: &str) -> Option<&LocalOrderBook> {
+        self.books.get(pair)
+    }
+
+    /// Returns `true` while `pair` is waiting for a fresh snapshot after a mismatch.
+    pub fn is_resyncing(&self, pair: &str) -> bool {
+        self.resyncing.contains(pair)
+    }
+
+    /// Seeds the book for `pair` from the REST `Depth` endpoint, see
+    /// [`LocalOrderBook::apply_rest_snapshot`].
+    ///
+    /// The book is served right away and a pending resync for the pair is dropped;
+    /// WebSocket deltas then apply on top, and the WebSocket snapshot replaces it when
+    /// it arrives.
+    pub fn apply_rest_snapshot<P, V>(
+        &mut self,
+        pair: &str,
+        asks: impl IntoIterator<Item = (P, V)>,
+        bids: impl IntoIterator<Item = (P, V)>,
+    ) where
+        P: Into<String>,
+        V: Into<String>,
+    {
+        self.resyncing.remove(pair);
+        self.books
+            .entry(pair.to_string())
+            .or_default()
+            .apply_rest_snapshot(asks, bids);
+    }
+
+    /// Clears every book and ignores their updates until a fresh snapshot arrives, as after
+    /// a checksum mismatch. Books keep their depth.
+    pub fn reset(&mut self) {
+        for (pair, book) in &mut self.books {
+            book.asks.clear();
+            book.bids.clear();
+            self.resyncing.insert(pair.clone());
+        }
+    }
+
+    /// Applies book messages and resets the books when the connection drops.
+    ///
+    /// Other events are ignored. Returns `Ok(false)` while a book touched by `event` is
+    /// waiting for a snapshot, see [`apply`](Self::apply).
+    pub async fn process(&mut self, client: &KrakenClient, event: &KrakenEvent) -> Result<bool> {
+        match event {
+            KrakenEvent::ConnectionState(ConnectionState::Reconnecting) => {
+                self.reset();
+                Ok(false)
+            }
+            _ => match event.clone().try_into_orderbook_data() {
+                Some(data) => self.apply(client, &data).await,
+                None => Ok(true),
+            },
+        }
+    }
+
+    /// Applies a book message, validating its checksum if present.
+    ///
+    /// Returns `Ok(true)` if the book is consistent after the update and `Ok(false)` if it
+    /// is (or still is) waiting for a resync.
+    pub async fn apply(&mut self, client: &KrakenClient, data: &OrderBookData) -> Result<bool> {
+        match self.update(data) {
+            BookUpdate::Consistent => Ok(true),
+            BookUpdate::Resyncing => Ok(false),
+            BookUpdate::Mismatch => {
+                client.metrics.record_checksum_mismatch();
+                client.resubscribe(vec![data.pair.clone()], "book").await?;
+                client.emit(KrakenEvent::ChecksumMismatch {
+                    pair: data.pair.clone(),
+                });
+                Ok(false)
+            }
+        }
+    }
+
+    /// Applies a book message to the local state only. On a mismatch the caller is
+    /// responsible for re-subscribing the pair.
+    pub(crate) fn update(&mut self, data: &OrderBookData) -> BookUpdate {
+        if data.is_snapshot {
+            self.resyncing.remove(&data.pair);
+        } else if self.resyncing.contains(&data.pair) {
+            // Stale updates from before the resync; the snapshot will replace them.
+            return BookUpdate::Resyncing;
+        }
+
+        // Kraken keeps only the subscribed depth, named in the channel ("book-10")
+        let depth = data
+            .channel_name
+            .strip_prefix("book-")
+            .and_then(|depth| depth.parse().ok());
+        let new_book = || depth.map_or_else(LocalOrderBook::new, LocalOrderBook::with_depth);
+        if data.is_snapshot {
+            self.books.insert(data.pair.clone(), new_book());
+        }
+        let book = self.books.entry(data.pair.clone()).or_insert_with(new_book);
+        book.update(data);
+
+        let Some(checksum) = data.checksum.as_ref().filter(|_| !self.skip_checksums) else {
+            return BookUpdate::Consistent;
+        };
+        if book.validate_checksum(checksum) {
+            return BookUpdate::Consistent;
+        }
+
+        warn!(
+            "Checksum mismatch on {} (remote {}, local {}). Resyncing book...",
+            data.pair,
+            checksum,
+            book.calculate_checksum()
+        );
+        book.asks.clear();
+        book.bids.clear();
+        self.resyncing.insert(data.pair.clone());
+        BookUpdate::Mismatch
+    }
+}
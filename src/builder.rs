@@ -0,0 +1,453 @@
+use crate::aggregator::MultiPairAggregator;
+use crate::book::BookMaintainer;
+use crate::metrics::MetricsRecorder;
+use crate::{KrakenClient, SubscriptionArgs};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// Default capacity of the event broadcast channel.
+pub const DEFAULT_EVENT_BUFFER: usize = 100;
+/// Default capacity of the outgoing command channel.
+pub const DEFAULT_COMMAND_BUFFER: usize = 100;
+/// Default time without any inbound message before the connection is considered stale.
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default limit on establishing a connection (TCP, TLS and WebSocket handshake).
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default interval between client-initiated WebSocket pings.
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default maximum number of pairs sent in one subscription message.
+pub const DEFAULT_SUBSCRIPTION_BATCH_SIZE: usize = 25;
+/// Default pause between the messages of a batched subscription.
+pub const DEFAULT_SUBSCRIPTION_BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// Which Kraken WebSocket protocol the client speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolVersion {
+    /// The legacy array-based API at `wss://ws.kraken.com` (default).
+    #[default]
+    V1,
+    /// The object-based API at `wss://ws.kraken.com/v2`. See [`crate::v2`].
+    V2,
+}
+
+/// Which tracked subscriptions the driver re-sends after a reconnect.
+///
+/// Subscriptions that are not replayed are dropped from
+/// [`active_subscriptions`](crate::KrakenClient::active_subscriptions), so re-subscribing
+/// to them on [`ConnectionState::Reconnected`](crate::models::ConnectionState::Reconnected)
+/// does not track them twice. Subscriptions made before the first connection are always sent.
+#[derive(Clone, Default)]
+pub enum ReplayStrategy {
+    /// Replay every subscription (default).
+    #[default]
+    All,
+    /// Replay nothing; the caller re-subscribes, e.g. after resetting local state.
+    None,
+    /// Replay the subscriptions for which the closure returns `true`.
+    Filter(ReplayFilter),
+}
+
+/// Decides from `(pairs, subscription)` whether a subscription is replayed.
+pub type ReplayFilter = Arc<dyn Fn(&[String], &SubscriptionArgs) -> bool + Send + Sync>;
+
+impl ReplayStrategy {
+    /// Replays the subscriptions for which `filter(pairs, subscription)` returns `true`.
+    ///
+    /// ```rust
+    /// use kraken_sdk::ReplayStrategy;
+    ///
+    /// // Stateless channels come back on their own; books are re-subscribed by hand
+    /// let strategy = ReplayStrategy::filter(|_, subscription| subscription.name != "book");
+    /// ```
+    pub fn filter(
+        filter: impl Fn(&[String], &SubscriptionArgs) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        ReplayStrategy::Filter(Arc::new(filter))
+    }
+
+    pub(crate) fn replays(&self, pairs: &[String], subscription: &SubscriptionArgs) -> bool {
+        match self {
+            ReplayStrategy::All => true,
+            ReplayStrategy::None => false,
+            ReplayStrategy::Filter(filter) => filter(pairs, subscription),
+        }
+    }
+}
+
+impl std::fmt::Debug for ReplayStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayStrategy::All => f.write_str("All"),
+            ReplayStrategy::None => f.write_str("None"),
+            ReplayStrategy::Filter(_) => f.write_str("Filter(..)"),
+        }
+    }
+}
+
+/// Settings the driver task needs once `connect()` is called.
+#[derive(Debug, Clone)]
+pub(crate) struct ClientConfig {
+    pub(crate) ws_url: String,
+    pub(crate) heartbeat_timeout: Duration,
+    pub(crate) connect_timeout: Duration,
+    pub(crate) ping_interval: Option<Duration>,
+    pub(crate) protocol: ProtocolVersion,
+    pub(crate) record_path: Option<PathBuf>,
+    pub(crate) normalize_pairs: bool,
+    pub(crate) subscription_batch_size: usize,
+    pub(crate) subscription_batch_delay: Duration,
+    pub(crate) rate_limit: Option<u32>,
+    pub(crate) surface_parse_errors: bool,
+    pub(crate) client_id: Option<String>,
+    pub(crate) replay_strategy: ReplayStrategy,
+    pub(crate) max_reconnect_attempts: Option<u32>,
+    pub(crate) rate_limit_backoff: Duration,
+    pub(crate) manage_order_books: bool,
+    pub(crate) auto_validate_checksums: bool,
+    pub(crate) aggregate_interval: Option<u64>,
+    pub(crate) initial_subscriptions: Vec<(Vec<String>, SubscriptionArgs)>,
+}
+
+/// Builder for [`KrakenClient`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use kraken_sdk::KrakenClient;
+///
+/// let client = KrakenClient::builder()
+///     .event_buffer(10_000)
+///     .command_buffer(500)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct KrakenClientBuilder {
+    config: ClientConfig,
+    event_buffer: usize,
+    command_buffer: usize,
+}
+
+impl Default for KrakenClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KrakenClientBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: ClientConfig {
+                ws_url: "wss://ws.kraken.com".to_string(),
+                heartbeat_timeout: DEFAULT_HEARTBEAT_TIMEOUT,
+                connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+                ping_interval: Some(DEFAULT_PING_INTERVAL),
+                protocol: ProtocolVersion::V1,
+                record_path: None,
+                normalize_pairs: false,
+                subscription_batch_size: DEFAULT_SUBSCRIPTION_BATCH_SIZE,
+                subscription_batch_delay: DEFAULT_SUBSCRIPTION_BATCH_DELAY,
+                rate_limit: None,
+                surface_parse_errors: false,
+                client_id: None,
+                replay_strategy: ReplayStrategy::All,
+                max_reconnect_attempts: None,
+                rate_limit_backoff: Duration::from_secs(60),
+                manage_order_books: false,
+                auto_validate_checksums: true,
+                aggregate_interval: None,
+                initial_subscriptions: Vec::new(),
+            },
+            event_buffer: DEFAULT_EVENT_BUFFER,
+            command_buffer: DEFAULT_COMMAND_BUFFER,
+        }
+    }
+
+    /// Sets the WebSocket endpoint (default: `wss://ws.kraken.com`).
+    pub fn ws_url(mut self, url: impl Into<String>) -> Self {
+        self.config.ws_url = url.into();
+        self
+    }
+
+    /// Selects the WebSocket protocol version (default: [`ProtocolVersion::V1`]).
+    ///
+    /// This also points the client at that version's public endpoint, so call
+    /// [`ws_url`](Self::ws_url) *after* this if you need a different URL. With
+    /// [`ProtocolVersion::V2`], subscriptions are sent in the v2 request format and
+    /// events are broadcast as [`KrakenEvent::V2`](crate::models::KrakenEvent::V2).
+    pub fn protocol(mut self, protocol: ProtocolVersion) -> Self {
+        self.config.protocol = protocol;
+        self.config.ws_url = match protocol {
+            ProtocolVersion::V1 => "wss://ws.kraken.com".to_string(),
+            ProtocolVersion::V2 => crate::v2::WS_URL_V2.to_string(),
+        };
+        self
+    }
+
+    /// Sets how long the connection may stay silent before it is considered dead (default: 10s).
+    ///
+    /// Kraken sends a `heartbeat` roughly every second when no other data flows, so a silent
+    /// socket usually means a half-open TCP connection that would otherwise never error.
+    /// When the timeout elapses the driver drops the connection and reconnects.
+    pub fn heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.config.heartbeat_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a connection attempt may take before it is abandoned (default: 10s).
+    ///
+    /// This covers the TCP connect, the TLS handshake and the WebSocket upgrade. A stalled
+    /// attempt is treated like a failed one: the driver waits and tries again.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets how often the driver sends a WebSocket `Ping` (default: every 30s), or `None` to disable.
+    ///
+    /// Outbound pings keep NATs and load balancers from closing an idle-looking connection.
+    /// If nothing (pong, heartbeat or data) has come back by the time the next ping is due,
+    /// the connection is dropped and re-established.
+    pub fn ping_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config.ping_interval = interval;
+        self
+    }
+
+    /// Normalizes pairs passed to `subscribe`/`unsubscribe` (default: off).
+    ///
+    /// With [`ProtocolVersion::V1`] pairs go through
+    /// [`normalize_pair`](crate::pairs::normalize_pair), so `"btc-usd"` becomes `"XBT/USD"`.
+    /// With [`ProtocolVersion::V2`] they go through
+    /// [`display_pair`](crate::pairs::display_pair) instead, since v2 expects `"BTC/USD"`.
+    pub fn normalize_pairs(mut self, enabled: bool) -> Self {
+        self.config.normalize_pairs = enabled;
+        self
+    }
+
+    /// Sets the maximum number of pairs per subscription message (default: 25).
+    ///
+    /// Kraken may reject a single message that subscribes to too many pairs, so
+    /// `subscribe` and `unsubscribe` split larger lists into several messages. A size
+    /// of `0` is treated as `1`.
+    pub fn subscription_batch_size(mut self, size: usize) -> Self {
+        self.config.subscription_batch_size = size;
+        self
+    }
+
+    /// Sets the pause between the messages of a batched subscription (default: 100ms).
+    ///
+    /// The same pause separates subscriptions replayed after a reconnect.
+    pub fn subscription_batch_delay(mut self, delay: Duration) -> Self {
+        self.config.subscription_batch_delay = delay;
+        self
+    }
+
+    /// Limits outgoing commands to `per_second` messages per second (default: unlimited).
+    ///
+    /// Kraken disconnects clients that send subscriptions or orders too quickly. With a
+    /// limit set, the driver lets bursts of up to `per_second` messages through and then
+    /// spaces commands out, while inbound messages keep being processed. Queued commands
+    /// wait in the command channel (see [`command_buffer`](Self::command_buffer)).
+    pub fn rate_limit(mut self, per_second: u32) -> Self {
+        self.config.rate_limit = Some(per_second);
+        self
+    }
+
+    /// Broadcasts frames that fail to parse as [`KrakenEvent::ParseError`] (default: off).
+    ///
+    /// Useful when working with a channel this crate does not model yet: subscribers see
+    /// the raw payload instead of only a log line. Failures are counted either way.
+    ///
+    /// [`KrakenEvent::ParseError`]: crate::models::KrakenEvent::ParseError
+    pub fn surface_parse_errors(mut self, enabled: bool) -> Self {
+        self.config.surface_parse_errors = enabled;
+        self
+    }
+
+    /// Names this client in its logs.
+    ///
+    /// The driver task runs inside a `kraken_client` tracing span with `client_id` and
+    /// `ws_url` fields, so log lines from several clients in one process can be told apart.
+    pub fn client_id(mut self, id: impl Into<String>) -> Self {
+        self.config.client_id = Some(id.into());
+        self
+    }
+
+    /// Stops the driver after `max` consecutive reconnect attempts (default: `None`, retry
+    /// forever).
+    ///
+    /// Every failed connection attempt and every dropped connection uses up one attempt;
+    /// a connection that stays up for a full [`heartbeat_timeout`](Self::heartbeat_timeout)
+    /// resets the count. When the attempts are exhausted the driver broadcasts
+    /// [`ConnectionState::Disconnected { permanent: true }`](crate::models::ConnectionState::Disconnected)
+    /// and its task ends. `Some(0)` never reconnects.
+    pub fn max_reconnect_attempts(mut self, max: Option<u32>) -> Self {
+        self.config.max_reconnect_attempts = max;
+        self
+    }
+
+    /// How long to wait before reconnecting after Kraken throttled the client (default: 60s).
+    ///
+    /// Applies when the connection was closed with a rate-limit reason, an `errorMessage`
+    /// such as `"Exceeded msg rate"` arrived on it, or the handshake was answered with
+    /// HTTP 429. The driver broadcasts
+    /// [`KrakenEvent::RateLimited`](crate::models::KrakenEvent::RateLimited) and waits this
+    /// long instead of the usual short delay. See also [`rate_limit`](Self::rate_limit)
+    /// to avoid being throttled in the first place.
+    pub fn rate_limit_backoff(mut self, backoff: Duration) -> Self {
+        self.config.rate_limit_backoff = backoff;
+        self
+    }
+
+    /// Chooses which subscriptions are re-sent after a reconnect (default:
+    /// [`ReplayStrategy::All`]).
+    ///
+    /// Every reconnect is announced with
+    /// [`ConnectionState::Reconnected`](crate::models::ConnectionState::Reconnected) once the
+    /// replay has been sent, which is the cue to re-subscribe anything left out.
+    pub fn replay_strategy(mut self, strategy: ReplayStrategy) -> Self {
+        self.config.replay_strategy = strategy;
+        self
+    }
+
+    /// Subscribes `pairs` to each of `channels` as soon as the first connection opens.
+    ///
+    /// The subscriptions are tracked like ones made with
+    /// [`KrakenClient::subscribe`], so they also show up in
+    /// [`active_subscriptions`](KrakenClient::active_subscriptions) and are replayed on
+    /// reconnect. Confirmations are broadcast as events. Can be called more than once.
+    ///
+    /// ```rust,no_run
+    /// use kraken_sdk::KrakenClient;
+    ///
+    /// let client = KrakenClient::builder()
+    ///     .subscribe_on_connect(vec!["XBT/USD".to_string()], &["trade", "book"])
+    ///     .build();
+    /// ```
+    pub fn subscribe_on_connect(mut self, pairs: Vec<String>, channels: &[&str]) -> Self {
+        for name in channels {
+            self.config
+                .initial_subscriptions
+                .push((pairs.clone(), SubscriptionArgs::new(*name)));
+        }
+        self
+    }
+
+    /// Maintains a local order book for every `book` subscription (default: off).
+    ///
+    /// The driver applies each book message to a shared [`BookMaintainer`] before
+    /// broadcasting it, and re-subscribes a pair whose checksum fails, just as
+    /// [`BookMaintainer::apply`] does. Read the books through [`KrakenClient::books`],
+    /// e.g. to serve snapshots without an event loop.
+    pub fn manage_order_books(mut self, enabled: bool) -> Self {
+        self.config.manage_order_books = enabled;
+        self
+    }
+
+    /// Validates the checksum embedded in every update to a managed book (default: on).
+    ///
+    /// A failed check is counted in [`Metrics::checksum_mismatches`](crate::metrics::Metrics::checksum_mismatches),
+    /// broadcast as [`KrakenEvent::ChecksumMismatch`](crate::models::KrakenEvent::ChecksumMismatch)
+    /// and recovered by re-subscribing the pair. Turning it off applies updates as they
+    /// come, e.g. for feeds replayed without their checksums. Only takes effect together
+    /// with [`manage_order_books`](Self::manage_order_books).
+    pub fn auto_validate_checksums(mut self, enabled: bool) -> Self {
+        self.config.auto_validate_checksums = enabled;
+        self
+    }
+
+    /// Builds `interval_seconds` candles from every trade message and broadcasts each
+    /// closed one as [`KrakenEvent::CandleClosed`](crate::models::KrakenEvent::CandleClosed)
+    /// (default: off).
+    ///
+    /// Candles are cut on trade time by a [`MultiPairAggregator`], so a candle is emitted
+    /// right after the trade message that ends it, and only once a pair trades again.
+    pub fn aggregate_trades(mut self, interval_seconds: u64) -> Self {
+        self.config.aggregate_interval = Some(interval_seconds);
+        self
+    }
+
+    /// Appends every inbound text frame to `path`, one frame per line (newline-delimited JSON).
+    ///
+    /// The file is created if needed and opened when `connect()` is called. Feed it back
+    /// through [`KrakenClient::replay_from`] to reproduce a session without the network.
+    pub fn record_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.record_path = Some(path.into());
+        self
+    }
+
+    /// Sets the capacity of the event broadcast channel (default: 100).
+    ///
+    /// Every subscriber shares this ring buffer, so each slot costs one `KrakenEvent`
+    /// of memory. If a subscriber falls more than `capacity` events behind (e.g. during
+    /// a burst of book updates across many pairs), the oldest events are dropped and its
+    /// next `recv()` returns `RecvError::Lagged`. Raise this when consuming busy feeds;
+    /// keep it small when memory matters more than completeness.
+    ///
+    /// # Panics
+    ///
+    /// `build()` panics if the capacity is zero.
+    pub fn event_buffer(mut self, capacity: usize) -> Self {
+        self.event_buffer = capacity;
+        self
+    }
+
+    pub(crate) fn event_buffer_capacity(&self) -> usize {
+        self.event_buffer
+    }
+
+    /// Sets the capacity of the outgoing command channel (default: 100).
+    ///
+    /// Commands (e.g. `subscribe`) queue here until the driver writes them to the socket.
+    /// When the buffer is full, callers wait in `subscribe().await` rather than losing
+    /// commands, so this trades memory for how many commands can be queued before connect.
+    ///
+    /// # Panics
+    ///
+    /// `build()` panics if the capacity is zero.
+    pub fn command_buffer(mut self, capacity: usize) -> Self {
+        self.command_buffer = capacity;
+        self
+    }
+
+    /// Builds the client. Call `connect()` on the result to start streaming.
+    pub fn build(self) -> KrakenClient {
+        let books = self.config.manage_order_books.then(|| {
+            let books = if self.config.auto_validate_checksums {
+                BookMaintainer::new()
+            } else {
+                BookMaintainer::without_checksums()
+            };
+            Arc::new(RwLock::new(books))
+        });
+        let candles = self
+            .config
+            .aggregate_interval
+            .map(|interval| Arc::new(Mutex::new(MultiPairAggregator::new(interval))));
+        let (event_sender, _) = broadcast::channel(self.event_buffer);
+        let (command_sender, command_receiver) = mpsc::channel(self.command_buffer);
+        let client = KrakenClient {
+            config: Arc::new(self.config),
+            event_sender,
+            command_sender,
+            command_receiver: Arc::new(Mutex::new(Some(command_receiver))),
+            connected: Arc::new(AtomicBool::new(false)),
+            active_subscriptions: Arc::new(Mutex::new(Vec::new())),
+            metrics: Arc::new(MetricsRecorder::default()),
+            next_reqid: Arc::new(AtomicU64::new(1)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            system_status: Arc::new(Mutex::new(None)),
+            connection_id: Arc::new(Mutex::new(None)),
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            books,
+            candles,
+            dead_mans_switch: Arc::new(Mutex::new(None)),
+        };
+        client.track_initial_subscriptions();
+        client
+    }
+}
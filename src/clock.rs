@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A source of the current time, in Unix seconds.
+///
+/// Components that close work on wall-clock time, such as
+/// [`TradeAggregator::flush_due`](crate::aggregator::TradeAggregator::flush_due), read it
+/// through this trait so tests and replays can drive time explicitly with a [`MockClock`].
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs_f64())
+            .unwrap_or(0.0)
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so keep one to advance the clock after handing another to
+/// the component under test.
+///
+/// # Example
+///
+/// ```rust
+/// use kraken_sdk::clock::{Clock, MockClock};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new(1_700_000_000.0);
+/// let handle = clock.clone();
+/// handle.advance(Duration::from_secs(60));
+/// assert_eq!(clock.now(), 1_700_000_060.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now: Arc<Mutex<f64>>,
+}
+
+impl MockClock {
+    /// A clock stopped at `now` (Unix seconds).
+    pub fn new(now: f64) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Moves the clock to `now`, which may be in the past.
+    pub fn set(&self, now: f64) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by.as_secs_f64();
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> f64 {
+        *self.now.lock().unwrap()
+    }
+}
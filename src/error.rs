@@ -0,0 +1,42 @@
+use thiserror::Error;
+
+/// Errors returned by the public API.
+///
+/// Library callers can match on the variant (e.g. treat `Auth` as fatal but retry on
+/// `Connection`). Applications using `eyre` or `anyhow` can still propagate it with `?`,
+/// since it implements `std::error::Error`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum KrakenError {
+    /// The WebSocket connection could not be established or was lost.
+    #[error("connection error: {0}")]
+    Connection(String),
+    /// Kraken rejected a subscription request.
+    #[error("subscription error: {0}")]
+    Subscription(String),
+    /// Invalid credentials, signature or permissions.
+    #[error("authentication error: {0}")]
+    Auth(String),
+    /// A message could not be (de)serialized.
+    #[error("parse error: {0}")]
+    Parse(#[from] serde_json::Error),
+    /// A REST request failed at the HTTP level.
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    /// Reading or writing a local file (e.g. a recording) failed.
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The driver task is no longer running, so commands cannot be delivered.
+    #[error("command channel closed")]
+    ChannelClosed,
+    /// Kraken is throttling this client, e.g. a subscription was rejected with
+    /// `"Exceeded msg rate"`. Retrying right away only extends the throttle.
+    #[error("rate limited: {0}")]
+    RateLimited(String),
+    /// An awaited message did not arrive in time.
+    #[error("timed out: {0}")]
+    Timeout(String),
+}
+
+/// Shorthand for results returned by this crate.
+pub type Result<T> = std::result::Result<T, KrakenError>;
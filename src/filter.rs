@@ -0,0 +1,118 @@
+use crate::models::{Side, Trade, TradeData};
+
+/// Selects trades by notional value, volume and side.
+///
+/// Every criterion left unset matches all trades. Trades whose price or volume cannot
+/// be parsed never match a value or volume criterion.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use kraken_sdk::filter::TradeFilter;
+/// use kraken_sdk::models::{Side, TradeData};
+///
+/// # fn example(trade_data: TradeData) {
+/// let whales = TradeFilter::new().min_value(50_000.0).side(Side::Buy);
+/// for trade in trade_data.filter(whales) {
+///     println!("🐋 {} @ {}", trade.volume, trade.price);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TradeFilter {
+    min_value: Option<f64>,
+    min_volume: Option<f64>,
+    side: Option<Side>,
+}
+
+impl TradeFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only trades worth at least `value` in the quote currency (price × volume).
+    pub fn min_value(mut self, value: f64) -> Self {
+        self.min_value = Some(value);
+        self
+    }
+
+    /// Only trades of at least `volume` in the base currency.
+    pub fn min_volume(mut self, volume: f64) -> Self {
+        self.min_volume = Some(volume);
+        self
+    }
+
+    /// Only trades where the taker was on `side`.
+    pub fn side(mut self, side: Side) -> Self {
+        self.side = Some(side);
+        self
+    }
+
+    pub fn matches(&self, trade: &Trade) -> bool {
+        if let Some(side) = self.side {
+            if trade.taker_side() != Some(side) {
+                return false;
+            }
+        }
+        if self.min_value.is_none() && self.min_volume.is_none() {
+            return true;
+        }
+        let (Ok(price), Ok(volume)) = (trade.price_f64(), trade.volume_f64()) else {
+            return false;
+        };
+        self.min_volume.is_none_or(|min| volume >= min)
+            && self.min_value.is_none_or(|min| price * volume >= min)
+    }
+}
+
+impl TradeData {
+    /// The trades in this message that match `filter`.
+    pub fn filter(&self, filter: TradeFilter) -> Vec<&Trade> {
+        self.data.iter().filter(|t| filter.matches(t)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(price: &str, volume: &str, side: &str) -> Trade {
+        serde_json::from_value(serde_json::json!([
+            price,
+            volume,
+            "1700000000.0",
+            side,
+            "m",
+            ""
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_filter_by_value_volume_and_side() {
+        let data = TradeData {
+            channel_id: 0,
+            data: vec![
+                trade("50000.0", "2.0", "b"),  // 100k buy
+                trade("50000.0", "0.01", "b"), // 500 buy
+                trade("50000.0", "3.0", "s"),  // 150k sell
+                trade("bad", "3.0", "s"),
+            ],
+            channel_name: "trade".to_string(),
+            pair: "XBT/USD".to_string(),
+        };
+
+        assert_eq!(data.filter(TradeFilter::new()).len(), 4);
+
+        let whales = data.filter(TradeFilter::new().min_value(50_000.0));
+        assert_eq!(whales.len(), 2);
+
+        let buy_whales = data.filter(TradeFilter::new().min_value(50_000.0).side(Side::Buy));
+        assert_eq!(buy_whales.len(), 1);
+        assert_eq!(buy_whales[0].volume, "2.0");
+
+        let sells = data.filter(TradeFilter::new().side(Side::Sell).min_volume(2.5));
+        assert_eq!(sells.len(), 1);
+        assert_eq!(sells[0].price, "50000.0");
+    }
+}
@@ -0,0 +1,295 @@
+//! Technical indicators over closing prices.
+//!
+//! The free functions work on any slice of values (oldest first); [`CandleSeries`]
+//! applies them to the closes of accumulated candles. Every indicator returns `None`
+//! until there is enough data for the requested period.
+
+use crate::models::Candle;
+use std::io::Write;
+use std::ops::Deref;
+
+/// Simple moving average of the last `period` values.
+pub fn sma(values: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || values.len() < period {
+        return None;
+    }
+    let window = &values[values.len() - period..];
+    Some(window.iter().sum::<f64>() / period as f64)
+}
+
+/// Exponential moving average with smoothing `2 / (period + 1)`, seeded with the SMA
+/// of the first `period` values.
+pub fn ema(values: &[f64], period: usize) -> Option<f64> {
+    let seed = sma(&values[..period.min(values.len())], period)?;
+    let k = 2.0 / (period as f64 + 1.0);
+    Some(
+        values[period..]
+            .iter()
+            .fold(seed, |ema, value| value * k + ema * (1.0 - k)),
+    )
+}
+
+/// Population standard deviation of the last `period` values.
+pub fn stddev(values: &[f64], period: usize) -> Option<f64> {
+    let mean = sma(values, period)?;
+    let window = &values[values.len() - period..];
+    let variance = window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / period as f64;
+    Some(variance.sqrt())
+}
+
+/// Bollinger Bands over the last `period` values, as `(lower, middle, upper)`.
+///
+/// The middle band is the SMA; the outer bands sit `k` standard deviations away
+/// (`k = 2.0` is the usual choice).
+pub fn bollinger(values: &[f64], period: usize, k: f64) -> Option<(f64, f64, f64)> {
+    let middle = sma(values, period)?;
+    let width = k * stddev(values, period)?;
+    Some((middle - width, middle, middle + width))
+}
+
+/// Relative Strength Index with Wilder's smoothing, in `0.0..=100.0`.
+///
+/// The first average gain/loss is the simple mean of the first `period` changes; each
+/// later change is folded in as `avg = (avg * (period - 1) + change) / period`. This
+/// needs `period + 1` values, and uses the whole history, so longer input converges
+/// towards the values charting platforms show. A run with no losses returns `100.0`.
+pub fn rsi(values: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || values.len() < period + 1 {
+        return None;
+    }
+    let mut changes = values.windows(2).map(|pair| pair[1] - pair[0]);
+    let (mut gain, mut loss) = changes
+        .by_ref()
+        .take(period)
+        .fold((0.0, 0.0), |(gain, loss), change| {
+            (gain + change.max(0.0), loss + (-change).max(0.0))
+        });
+    gain /= period as f64;
+    loss /= period as f64;
+    let n = period as f64;
+    for change in changes {
+        gain = (gain * (n - 1.0) + change.max(0.0)) / n;
+        loss = (loss * (n - 1.0) + (-change).max(0.0)) / n;
+    }
+    if loss == 0.0 {
+        return Some(100.0);
+    }
+    let rs = gain / loss;
+    Some(100.0 - 100.0 / (1.0 + rs))
+}
+
+/// A growing list of candles (oldest first) with indicator methods over their closes.
+///
+/// Dereferences to `[Candle]`, so slicing and iteration work as on a `Vec`.
+///
+/// # Example
+///
+/// ```rust
+/// use kraken_sdk::indicators::CandleSeries;
+/// # use kraken_sdk::models::Candle;
+/// # let candle = |close| Candle { open: close, high: close, low: close, close, volume: 1.0, start_time: 0, interval_seconds: 60, ..Default::default() };
+///
+/// let mut series = CandleSeries::with_max_len(100);
+/// for close in [10.0, 11.0, 12.0] {
+///     series.push(candle(close));
+/// }
+/// assert_eq!(series.sma(3), Some(11.0));
+/// assert_eq!(series.sma(4), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CandleSeries {
+    candles: Vec<Candle>,
+    max_len: Option<usize>,
+}
+
+impl CandleSeries {
+    /// An unbounded series.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A series that keeps only the most recent `max_len` candles.
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self {
+            candles: Vec::new(),
+            max_len: Some(max_len),
+        }
+    }
+
+    /// Appends a candle, dropping the oldest one if the series is at its cap.
+    pub fn push(&mut self, candle: Candle) {
+        self.candles.push(candle);
+        if let Some(max_len) = self.max_len {
+            if self.candles.len() > max_len {
+                let excess = self.candles.len() - max_len;
+                self.candles.drain(..excess);
+            }
+        }
+    }
+
+    pub fn last_close(&self) -> Option<f64> {
+        self.candles.last().map(|c| c.close)
+    }
+
+    /// Closing prices, oldest first.
+    pub fn closes(&self) -> Vec<f64> {
+        self.candles.iter().map(|c| c.close).collect()
+    }
+
+    pub fn sma(&self, period: usize) -> Option<f64> {
+        sma(&self.closes(), period)
+    }
+
+    pub fn ema(&self, period: usize) -> Option<f64> {
+        ema(&self.closes(), period)
+    }
+
+    pub fn rsi(&self, period: usize) -> Option<f64> {
+        rsi(&self.closes(), period)
+    }
+
+    pub fn stddev(&self, period: usize) -> Option<f64> {
+        stddev(&self.closes(), period)
+    }
+
+    pub fn bollinger(&self, period: usize, k: f64) -> Option<(f64, f64, f64)> {
+        bollinger(&self.closes(), period, k)
+    }
+
+    /// Writes the series as CSV with a header row, oldest candle first.
+    ///
+    /// Columns are `time,open,high,low,close,volume`, with `time` in Unix seconds, which
+    /// loads directly into pandas or a spreadsheet.
+    pub fn to_csv_writer<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "{}", Candle::CSV_HEADER)?;
+        for candle in &self.candles {
+            candle.write_csv_row(&mut writer)?;
+        }
+        writer.flush()
+    }
+}
+
+impl Deref for CandleSeries {
+    type Target = [Candle];
+
+    fn deref(&self) -> &[Candle] {
+        &self.candles
+    }
+}
+
+impl From<Vec<Candle>> for CandleSeries {
+    fn from(candles: Vec<Candle>) -> Self {
+        Self {
+            candles,
+            max_len: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close: f64) -> Candle {
+        Candle {
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+            start_time: 0,
+            interval_seconds: 60,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sma_and_ema() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(sma(&values, 2), Some(4.5));
+        assert_eq!(sma(&values, 6), None);
+        assert_eq!(sma(&values, 0), None);
+
+        // Seed SMA(1,2,3) = 2, k = 0.5: 4*0.5 + 2*0.5 = 3, then 5*0.5 + 3*0.5 = 4
+        assert_eq!(ema(&values, 3), Some(4.0));
+        assert_eq!(ema(&values[..2], 3), None);
+    }
+
+    #[test]
+    fn test_stddev_and_bollinger() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        // Classic example: mean 5, population stddev 2
+        assert_eq!(stddev(&values, 8), Some(2.0));
+        assert_eq!(stddev(&values, 9), None);
+        assert_eq!(stddev(&values, 0), None);
+
+        assert_eq!(bollinger(&values, 8, 2.0), Some((1.0, 5.0, 9.0)));
+        assert_eq!(bollinger(&[3.0, 3.0, 3.0], 3, 2.0), Some((3.0, 3.0, 3.0)));
+        assert_eq!(bollinger(&values[..2], 3, 2.0), None);
+    }
+
+    #[test]
+    fn test_rsi_wilder_reference() {
+        // The 14-period example series from Wilder / StockCharts. Their table rounds the
+        // intermediate averages (first value 70.53); these are the unrounded results.
+        let closes = [
+            44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03,
+            45.61, 46.28, 46.28, 46.00, 46.03, 46.41, 46.22, 45.64, 46.21, 46.25, 45.71, 46.45,
+        ];
+        let expected = [
+            70.46, 66.25, 66.48, 69.35, 66.29, 57.92, 62.88, 63.21, 56.01, 62.34,
+        ];
+        assert_eq!(rsi(&closes[..14], 14), None);
+        for (i, want) in expected.iter().enumerate() {
+            let got = rsi(&closes[..15 + i], 14).unwrap();
+            assert!((got - want).abs() < 0.01, "RSI #{}: {} != {}", i, got, want);
+        }
+    }
+
+    #[test]
+    fn test_rsi_window() {
+        assert_eq!(rsi(&[1.0, 2.0], 2), None);
+        assert_eq!(rsi(&[1.0, 2.0, 3.0], 2), Some(100.0));
+        // Changes +2, -1: RS = 2, RSI = 100 - 100/3
+        let value = rsi(&[10.0, 12.0, 11.0], 2).unwrap();
+        assert!((value - 66.666_666).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_series_to_csv() {
+        let mut series = CandleSeries::new();
+        series.push(Candle {
+            open: 100.0,
+            high: 105.5,
+            low: 99.0,
+            close: 101.25,
+            volume: 2.5,
+            start_time: 1_700_000_000,
+            interval_seconds: 60,
+            ..Default::default()
+        });
+        series.push(candle(102.0));
+
+        let mut out = Vec::new();
+        series.to_csv_writer(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "time,open,high,low,close,volume\n\
+             1700000000,100,105.5,99,101.25,2.5\n\
+             0,102,102,102,102,1\n"
+        );
+    }
+
+    #[test]
+    fn test_series_caps_length() {
+        let mut series = CandleSeries::with_max_len(3);
+        assert_eq!(series.last_close(), None);
+        for close in 1..=5 {
+            series.push(candle(close as f64));
+        }
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.closes(), vec![3.0, 4.0, 5.0]);
+        assert_eq!(series.last_close(), Some(5.0));
+        assert_eq!(series.sma(3), Some(4.0));
+    }
+}
@@ -28,39 +28,211 @@
 //! }
 //! ```
 
-use eyre::Result;
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{SinkExt, Stream, StreamExt};
 use serde::Serialize;
-use tokio::sync::{broadcast, mpsc};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use tracing::{error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 pub mod aggregator;
 pub mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod book;
+pub mod builder;
+pub mod clock;
+pub mod error;
+pub mod filter;
+pub mod indicators;
+pub mod metrics;
 pub mod models;
-use models::KrakenEvent;
+pub mod orders;
+pub mod pairs;
+pub mod pool;
+mod rate_limit;
+pub mod sink;
+pub mod source;
+pub mod stream;
+pub mod tape;
+mod tls;
+pub mod v2;
+use aggregator::MultiPairAggregator;
+use book::{BookUpdate, SharedBooks};
+use builder::ClientConfig;
+pub use builder::{KrakenClientBuilder, ProtocolVersion, ReplayStrategy};
+pub use error::KrakenError;
+use error::Result;
+use metrics::{ClientStats, Metrics, MetricsRecorder};
+use models::{ChannelData, ConnectionState, KrakenEvent, OrderBookData, TradeData};
+use orders::AddOrderRequest;
+use rate_limit::{is_rate_limit_message, TokenBucket};
+use sink::MessageSink;
+use source::{MessageSource, RecordingSource};
+use stream::EventStream;
 
 #[derive(Debug, Clone)]
 pub enum Command {
     Subscribe {
         pairs: Vec<String>,
         subscription: SubscriptionArgs,
+        reqid: Option<u64>,
     },
+    Unsubscribe {
+        pairs: Vec<String>,
+        subscription: SubscriptionArgs,
+        reqid: Option<u64>,
+    },
+    /// A raw JSON message written to the socket as-is (see [`KrakenClient::send_raw`]).
+    Raw(serde_json::Value),
+    /// A raw text frame written to the socket as-is (see [`KrakenClient::send_raw_text`]).
+    RawText(String),
+    /// Closes the connection and stops the driver (see [`KrakenClient::disconnect`]).
+    Shutdown,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// The `subscription` object of a subscribe/unsubscribe request.
+///
+/// Only `name` is required; every other option is left out of the request when `None`,
+/// so Kraken applies its own default.
+///
+/// ```rust
+/// use kraken_sdk::SubscriptionArgs;
+///
+/// let args = SubscriptionArgs::new("book").depth(25).snapshot(false);
+/// assert_eq!(
+///     serde_json::to_string(&args).unwrap(),
+///     r#"{"name":"book","snapshot":false,"depth":25}"#
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct SubscriptionArgs {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token: Option<String>,
+    /// Whether Kraken sends an initial snapshot (`book` and `trade`; defaults to `true`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<bool>,
+    /// Candle interval in minutes for `ohlc`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u32>,
+    /// Number of price levels per side for `book` (10, 25, 100, 500 or 1000).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+    /// Whether `ownTrades` messages carry the rate counter (v1 only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ratecounter: Option<bool>,
+}
+
+impl SubscriptionArgs {
+    /// Options for channel `name` with everything else left at Kraken's defaults.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn snapshot(mut self, snapshot: bool) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    pub fn interval(mut self, minutes: u32) -> Self {
+        self.interval = Some(minutes);
+        self
+    }
+
+    pub fn depth(mut self, depth: u32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn ratecounter(mut self, ratecounter: bool) -> Self {
+        self.ratecounter = Some(ratecounter);
+        self
+    }
+}
+
+/// A reply Kraken has not sent yet, matched to its request by `reqid`.
+///
+/// The reply is also broadcast to every event subscriber as usual. Dropping this
+/// handle simply stops waiting for it.
+#[derive(Debug)]
+pub struct PendingResponse {
+    reqid: u64,
+    receiver: oneshot::Receiver<KrakenEvent>,
+}
+
+impl PendingResponse {
+    /// The `reqid` attached to the outgoing request.
+    pub fn reqid(&self) -> u64 {
+        self.reqid
+    }
+
+    /// Waits for the reply carrying this request's `reqid`
+    /// (e.g. a [`KrakenEvent::SubscriptionStatus`]).
+    ///
+    /// For a subscription to several pairs Kraken sends one status per pair; this
+    /// resolves with the first of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KrakenError::ChannelClosed`] if the client shut down before the reply arrived.
+    pub async fn response(self) -> Result<KrakenEvent> {
+        self.receiver.await.map_err(|_| KrakenError::ChannelClosed)
+    }
 }
 
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<KrakenEvent>>>>;
+type ChannelMap = Arc<Mutex<HashMap<u64, (String, String)>>>;
+
+/// Handle to a Kraken WebSocket connection.
+///
+/// The handle is cheap to clone: every clone shares the same driver task, event channel,
+/// subscriptions and metrics, so it can be handed to many tasks (e.g. web handlers) without
+/// wrapping it in an `Arc`. The driver stops once every clone has been dropped.
+#[derive(Clone)]
 pub struct KrakenClient {
-    ws_url: String,
+    config: Arc<ClientConfig>,
     event_sender: broadcast::Sender<KrakenEvent>,
     command_sender: mpsc::Sender<Command>,
     // We store the receiver in an Option so we can take it out once when connecting
-    command_receiver: std::sync::Mutex<Option<mpsc::Receiver<Command>>>,
+    command_receiver: Arc<Mutex<Option<mpsc::Receiver<Command>>>>,
+    // Shared with the driver task so callers can introspect the connection
+    connected: Arc<AtomicBool>,
+    active_subscriptions: Arc<Mutex<Vec<Command>>>,
+    metrics: Arc<MetricsRecorder>,
+    next_reqid: Arc<AtomicU64>,
+    // Requests awaiting a reply, resolved by the driver when the reqid comes back
+    pending: PendingRequests,
+    system_status: Arc<Mutex<Option<String>>>,
+    connection_id: Arc<Mutex<Option<u64>>>,
+    // channelID -> (pair, channel name), from confirmations on the current connection
+    channels: ChannelMap,
+    // Kept up to date by the dispatcher when manage_order_books is on
+    books: Option<SharedBooks>,
+    // Fed by the dispatcher when aggregate_trades is on
+    candles: Option<Arc<Mutex<MultiPairAggregator>>>,
+    // The armed cancelAllOrdersAfter timer, disabled again by disconnect()
+    dead_mans_switch: Arc<Mutex<Option<DeadMansSwitch>>>,
+}
+
+/// A `cancelAllOrdersAfter` timer set through this client.
+struct DeadMansSwitch {
+    token: String,
+    renewal: Option<JoinHandle<()>>,
 }
 
 impl Default for KrakenClient {
@@ -75,14 +247,12 @@ impl KrakenClient {
     /// This initializes the internal channels but does not connect to the WebSocket yet.
     /// Call `connect()` to establish the connection.
     pub fn new() -> Self {
-        let (event_sender, _) = broadcast::channel(100);
-        let (command_sender, command_receiver) = mpsc::channel(100);
-        Self {
-            ws_url: "wss://ws.kraken.com".to_string(),
-            event_sender,
-            command_sender,
-            command_receiver: std::sync::Mutex::new(Some(command_receiver)),
-        }
+        KrakenClientBuilder::new().build()
+    }
+
+    /// Returns a builder for configuring the client (e.g. channel capacities).
+    pub fn builder() -> KrakenClientBuilder {
+        KrakenClientBuilder::new()
     }
 
     /// Returns a broadcast receiver for Kraken events.
@@ -92,6 +262,221 @@ impl KrakenClient {
         self.event_sender.subscribe()
     }
 
+    /// Returns a `Stream` of Kraken events.
+    ///
+    /// This is an alternative to [`subscribe_events`](Self::subscribe_events) that works with
+    /// `StreamExt` combinators (`filter_map`, `take`, ...). Events missed because the consumer
+    /// lagged behind are skipped instead of ending the stream.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn example() {
+    /// let client = KrakenClient::new();
+    /// let mut trades = client
+    ///     .event_stream()
+    ///     .filter_map(|event| async move { event.try_into_trade_data() });
+    /// # }
+    /// ```
+    pub fn event_stream(&self) -> EventStream {
+        EventStream::new(self.event_sender.subscribe())
+    }
+
+    /// Returns a `Stream` of trade messages only.
+    ///
+    /// Other events are skipped, as are trades missed because the consumer lagged behind
+    /// (with a logged warning), like [`event_stream`](Self::event_stream).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn example() {
+    /// let client = KrakenClient::new();
+    /// let mut trades = client.trade_stream();
+    /// while let Some(trades) = trades.next().await {
+    ///     println!("{} trades on {}", trades.data.len(), trades.pair);
+    /// }
+    /// # }
+    /// ```
+    pub fn trade_stream(&self) -> impl Stream<Item = TradeData> + Unpin {
+        self.event_stream()
+            .filter_map(|event| futures_util::future::ready(event.try_into_trade_data()))
+    }
+
+    /// Returns a `Stream` of the events for one `pair`, as reported by
+    /// [`KrakenEvent::pair`]: market data, subscription statuses and checksum
+    /// mismatches. Everything else is skipped, see [`trade_stream`](Self::trade_stream).
+    ///
+    /// `pair` must be spelled the way Kraken reports it (`"XBT/USD"` on v1,
+    /// `"BTC/USD"` on v2).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn example(client: KrakenClient) {
+    /// let mut xbt = client.subscribe_events_for("XBT/USD");
+    /// while let Some(event) = xbt.next().await {
+    ///     if let Some(trades) = event.try_into_trade_data() {
+    ///         println!("{} XBT/USD trades", trades.data.len());
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn subscribe_events_for(&self, pair: &str) -> impl Stream<Item = KrakenEvent> + Unpin {
+        let pair = pair.to_string();
+        self.event_stream()
+            .filter(move |event| futures_util::future::ready(event.pair() == Some(pair.as_str())))
+    }
+
+    /// Returns a `Stream` of order book snapshots and updates only.
+    ///
+    /// See [`trade_stream`](Self::trade_stream) for how skipped events are handled.
+    pub fn orderbook_stream(&self) -> impl Stream<Item = OrderBookData> + Unpin {
+        self.event_stream()
+            .filter_map(|event| futures_util::future::ready(event.try_into_orderbook_data()))
+    }
+
+    /// Calls `callback` with every trade message, on a spawned task.
+    ///
+    /// A shortcut for the `subscribe_events` + `try_into_trade_data` loop. Only events
+    /// broadcast after this call are seen; trades missed because the callback fell behind
+    /// are skipped. The task ends when the client shuts down, or when the returned handle
+    /// is aborted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # async fn example() {
+    /// let client = KrakenClient::new();
+    /// client.on_trade(|trades| println!("{} trades on {}", trades.data.len(), trades.pair));
+    /// client.connect().await.unwrap();
+    /// # }
+    /// ```
+    pub fn on_trade<F>(&self, callback: F) -> JoinHandle<()>
+    where
+        F: Fn(TradeData) + Send + 'static,
+    {
+        self.on_event(move |event| {
+            if let Some(trades) = event.try_into_trade_data() {
+                callback(trades);
+            }
+        })
+    }
+
+    /// Calls `callback` with every order book snapshot and update, on a spawned task.
+    ///
+    /// See [`on_trade`](Self::on_trade) for how the task behaves.
+    pub fn on_orderbook<F>(&self, callback: F) -> JoinHandle<()>
+    where
+        F: Fn(OrderBookData) + Send + 'static,
+    {
+        self.on_event(move |event| {
+            if let Some(book) = event.try_into_orderbook_data() {
+                callback(book);
+            }
+        })
+    }
+
+    fn on_event(&self, handler: impl Fn(KrakenEvent) + Send + 'static) -> JoinHandle<()> {
+        let mut events = self.subscribe_events();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => handler(event),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Event callback lagged behind, skipped {} events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Writes every broadcast event to `sink`, on a spawned task.
+    ///
+    /// Only events broadcast after this call are written, so register sinks before
+    /// [`connect`](Self::connect). A failed write is logged and the event skipped; the
+    /// feed and later writes carry on. Events missed because the sink fell more than
+    /// [`event_buffer`](KrakenClientBuilder::event_buffer) behind are skipped with a
+    /// warning. The task ends when the client shuts down, or when the returned handle is
+    /// aborted.
+    pub fn add_sink(&self, sink: impl MessageSink + 'static) -> JoinHandle<()> {
+        let mut events = self.subscribe_events();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if let Err(e) = sink.write(&event).await {
+                            error!("Message sink failed to write an event: {}", e);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Message sink lagged behind, skipped {} events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Returns `true` while the driver holds an open WebSocket connection.
+    ///
+    /// This flips to `false` as soon as the connection drops and back to `true`
+    /// once the driver has reconnected.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Returns the subscriptions the driver has sent (and will replay on reconnect),
+    /// as `(pairs, channel name)` tuples.
+    pub fn active_subscriptions(&self) -> Vec<(Vec<String>, String)> {
+        self.active_subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Command::Subscribe {
+                    pairs,
+                    subscription,
+                    ..
+                } => Some((pairs.clone(), subscription.name.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns a snapshot of the client's counters (messages, errors, reconnects) and latency gauge.
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.snapshot()
+    }
+
+    /// Returns how many messages of each channel type the client has received.
+    ///
+    /// The counters are atomics updated by the driver as messages are parsed, so this is
+    /// cheap enough to call on every UI tick. Unlike [`metrics`](Self::metrics), they
+    /// are never reset.
+    pub fn stats(&self) -> ClientStats {
+        self.metrics.stats()
+    }
+
+    /// Returns the current metrics and resets the counters to zero.
+    ///
+    /// Call this periodically to compute per-interval rates instead of cumulative totals.
+    /// Gauges such as `latency_ms` are not reset.
+    pub fn metrics_reset(&self) -> Metrics {
+        self.metrics.reset()
+    }
+
     /// Subscribes to a list of pairs on a specific channel.
     ///
     /// # Arguments
@@ -99,13 +484,26 @@ impl KrakenClient {
     /// * `pairs` - A list of trading pairs (e.g., `vec!["XBT/USD".to_string()]`).
     /// * `name` - The channel name (e.g., `"trade"`, `"book"`, `"ticker"`).
     ///
+    /// The request carries an auto-incremented `reqid`; the returned [`PendingResponse`]
+    /// resolves with the matching `subscriptionStatus`.
+    ///
+    /// This may be called before [`connect`](Self::connect): the subscription waits in the
+    /// command channel and is sent exactly once, together with any other early
+    /// subscriptions, as soon as the first connection is up. Queue at most
+    /// [`command_buffer`](KrakenClientBuilder::command_buffer) messages this way, or the call
+    /// waits for the connection.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
     /// # use kraken_sdk::KrakenClient;
     /// # async fn example() {
     /// let client = KrakenClient::new();
-    /// client.subscribe(vec!["XBT/USD".to_string()], "trade", None).await.unwrap();
+    /// let pending = client
+    ///     .subscribe(vec!["XBT/USD".to_string()], "trade", None)
+    ///     .await
+    ///     .unwrap();
+    /// let status = pending.response().await.unwrap();
     /// # }
     /// ```
     pub async fn subscribe(
@@ -113,21 +511,706 @@ impl KrakenClient {
         pairs: Vec<String>,
         name: &str,
         token: Option<String>,
-    ) -> Result<()> {
-        let cmd = Command::Subscribe {
+    ) -> Result<PendingResponse> {
+        let subscription = SubscriptionArgs {
+            token,
+            ..SubscriptionArgs::new(name)
+        };
+        self.subscribe_with(pairs, subscription).await
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but with the full set of subscription options.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::{KrakenClient, SubscriptionArgs};
+    /// # async fn example() {
+    /// let client = KrakenClient::new();
+    /// let args = SubscriptionArgs::new("book").depth(100);
+    /// client
+    ///     .subscribe_with(vec!["XBT/USD".to_string()], args)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn subscribe_with(
+        &self,
+        pairs: Vec<String>,
+        subscription: SubscriptionArgs,
+    ) -> Result<PendingResponse> {
+        let pending = self.track_request();
+        let reqid = Some(pending.reqid);
+        self.send_batched(pairs, pending, |pairs| Command::Subscribe {
             pairs,
-            subscription: SubscriptionArgs {
-                name: name.to_string(),
-                token,
-            },
+            subscription: subscription.clone(),
+            reqid,
+        })
+        .await
+    }
+
+    /// Unsubscribes a list of pairs from a channel.
+    ///
+    /// The pairs are also removed from [`active_subscriptions`](Self::active_subscriptions),
+    /// so they are not replayed after a reconnect. The request carries the options of the
+    /// tracked subscription (token, depth, ...), so e.g. a `book` subscribed with a depth
+    /// of 25 is unsubscribed as `book-25` rather than the default depth.
+    pub async fn unsubscribe(&self, pairs: Vec<String>, name: &str) -> Result<PendingResponse> {
+        let pending = self.track_request();
+        let subscription = tracked_subscription(
+            &self.active_subscriptions.lock().unwrap(),
+            &self.normalize(pairs.clone()),
+            name,
+        );
+        let reqid = Some(pending.reqid);
+        self.send_batched(pairs, pending, |pairs| Command::Unsubscribe {
+            pairs,
+            subscription: subscription.clone(),
+            reqid,
+        })
+        .await
+    }
+
+    /// Unsubscribes and immediately re-subscribes `pairs` on a channel.
+    ///
+    /// For `book` this makes Kraken send a fresh snapshot, which is how a corrupted local
+    /// book is recovered. The options of the existing subscription (token, depth, ...) are
+    /// reused.
+    pub async fn resubscribe(&self, pairs: Vec<String>, name: &str) -> Result<PendingResponse> {
+        let subscription = tracked_subscription(
+            &self.active_subscriptions.lock().unwrap(),
+            &self.normalize(pairs.clone()),
+            name,
+        );
+        self.unsubscribe(pairs.clone(), name).await?;
+        self.subscribe_with(pairs, subscription).await
+    }
+
+    /// Replaces the subscription set with `desired`, given as `(pairs, channel name)` like
+    /// [`active_subscriptions`](Self::active_subscriptions).
+    ///
+    /// Diffs `desired` against the active subscriptions per pair and channel: pairs that
+    /// are no longer wanted are unsubscribed, new ones are subscribed with default options,
+    /// and the rest stay untouched, so their feeds keep flowing. Subscriptions without
+    /// pairs (private channels) are left alone. Returns the pending responses of the
+    /// unsubscribes, then the subscribes, one per channel.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # async fn example(client: KrakenClient) {
+    /// let watchlist = vec!["XBT/USD".to_string(), "SOL/USD".to_string()];
+    /// client
+    ///     .set_subscriptions(vec![(watchlist, "ticker".to_string())])
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn set_subscriptions(
+        &self,
+        desired: Vec<(Vec<String>, String)>,
+    ) -> Result<Vec<PendingResponse>> {
+        let desired: Vec<(Vec<String>, String)> = desired
+            .into_iter()
+            .map(|(pairs, name)| (self.normalize(pairs), name))
+            .collect();
+        let current = per_pair(&self.active_subscriptions());
+        let desired = per_pair(&desired);
+        let current_set: HashSet<_> = current.iter().collect();
+        let desired_set: HashSet<_> = desired.iter().collect();
+
+        let mut pending = Vec::new();
+        let stale = current.iter().filter(|sub| !desired_set.contains(sub));
+        for (name, pairs) in by_channel(stale) {
+            pending.push(self.unsubscribe(pairs, &name).await?);
+        }
+        let new = desired.iter().filter(|sub| !current_set.contains(sub));
+        for (name, pairs) in by_channel(new) {
+            pending.push(
+                self.subscribe_with(pairs, SubscriptionArgs::new(&name))
+                    .await?,
+            );
+        }
+        Ok(pending)
+    }
+
+    /// Subscribes `pairs` to several channels and waits until Kraken has confirmed every
+    /// pair on every channel.
+    ///
+    /// Each channel is subscribed like [`subscribe`](Self::subscribe), batching included.
+    /// Returns the confirmations (one `subscriptionStatus` per pair and channel, or the v2
+    /// acknowledgements) in arrival order. There is no built-in deadline; wrap the call in
+    /// `tokio::time::timeout` if the connection may not come up.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KrakenError::Subscription`] as soon as any pair is rejected.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # async fn example() {
+    /// let client = KrakenClient::new();
+    /// client.connect().await.unwrap();
+    /// let pairs = vec!["XBT/USD".to_string(), "ETH/USD".to_string()];
+    /// let confirmations = client
+    ///     .subscribe_many(pairs, &["trade", "book"])
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(confirmations.len(), 4);
+    /// # }
+    /// ```
+    pub async fn subscribe_many(
+        &self,
+        pairs: Vec<String>,
+        channels: &[&str],
+    ) -> Result<Vec<KrakenEvent>> {
+        // Listen before subscribing so no confirmation can slip past
+        let mut events = self.subscribe_events();
+        let mut outstanding = HashMap::new();
+        for name in channels {
+            let pending = self.subscribe(pairs.clone(), name, None).await?;
+            outstanding.insert(pending.reqid(), pairs.len().max(1));
+        }
+
+        let mut confirmations = Vec::new();
+        while !outstanding.is_empty() {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Err(KrakenError::ChannelClosed),
+            };
+            let Some(reqid) = event.reqid() else {
+                continue;
+            };
+            let Some(remaining) = outstanding.get_mut(&reqid) else {
+                continue;
+            };
+            if let Some(error) = subscription_error(&event) {
+                return Err(error);
+            }
+            *remaining -= 1;
+            if *remaining == 0 {
+                outstanding.remove(&reqid);
+            }
+            confirmations.push(event);
+        }
+        Ok(confirmations)
+    }
+
+    /// Waits for the initial `book` snapshot of `pair`, subscribing to the book first if
+    /// it is not subscribed yet.
+    ///
+    /// If the book is already subscribed, this resolves on the next snapshot (e.g. after a
+    /// reconnect or [`resubscribe`](Self::resubscribe)). Only v1 book frames are recognised.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KrakenError::Timeout`] if no snapshot arrives within `timeout`, and
+    /// [`KrakenError::Subscription`] if Kraken rejects the subscription.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # use std::time::Duration;
+    /// # async fn example() {
+    /// let client = KrakenClient::new();
+    /// client.connect().await.unwrap();
+    /// let book = client
+    ///     .await_book_snapshot("XBT/USD", Duration::from_secs(10))
+    ///     .await
+    ///     .unwrap();
+    /// println!("{} asks, {} bids", book.asks.len(), book.bids.len());
+    /// # }
+    /// ```
+    pub async fn await_book_snapshot(
+        &self,
+        pair: &str,
+        timeout: Duration,
+    ) -> Result<OrderBookData> {
+        // Listen before subscribing so the snapshot cannot slip past
+        let mut events = self.subscribe_events();
+        let subscribed = self
+            .active_subscriptions()
+            .iter()
+            .any(|(pairs, name)| name == "book" && pairs.iter().any(|p| p == pair));
+        let reqid = if subscribed {
+            None
+        } else {
+            Some(
+                self.subscribe(vec![pair.to_string()], "book", None)
+                    .await?
+                    .reqid(),
+            )
         };
+
+        let wait = async {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return Err(KrakenError::ChannelClosed)
+                    }
+                };
+                if reqid.is_some() && event.reqid() == reqid {
+                    if let Some(error) = subscription_error(&event) {
+                        return Err(error);
+                    }
+                }
+                if let Some(book) = event.try_into_orderbook_data() {
+                    if book.is_snapshot && book.pair == pair {
+                        return Ok(book);
+                    }
+                }
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.map_err(|_| {
+            KrakenError::Timeout(format!(
+                "no book snapshot for {} within {:?}",
+                pair, timeout
+            ))
+        })?
+    }
+
+    /// Normalizes `pairs` and splits them into subscription-sized batches.
+    fn batches(&self, pairs: Vec<String>) -> Vec<Vec<String>> {
+        let pairs = self.normalize(pairs);
+        // Private channels are subscribed without pairs, which is still one message
+        if pairs.is_empty() {
+            return vec![pairs];
+        }
+        pairs
+            .chunks(self.config.subscription_batch_size.max(1))
+            .map(<[String]>::to_vec)
+            .collect()
+    }
+
+    /// Tracks the builder's initial subscriptions, so the first connection sends them.
+    pub(crate) fn track_initial_subscriptions(&self) {
+        let mut commands = Vec::new();
+        for (pairs, subscription) in &self.config.initial_subscriptions {
+            for batch in self.batches(pairs.clone()) {
+                commands.push(Command::Subscribe {
+                    pairs: batch,
+                    subscription: subscription.clone(),
+                    reqid: None,
+                });
+            }
+        }
+        self.active_subscriptions.lock().unwrap().extend(commands);
+    }
+
+    /// Applies [`normalize_pairs`](KrakenClientBuilder::normalize_pairs) if enabled.
+    fn normalize(&self, pairs: Vec<String>) -> Vec<String> {
+        if !self.config.normalize_pairs {
+            return pairs;
+        }
+        let normalize = match self.config.protocol {
+            ProtocolVersion::V1 => pairs::normalize_pair,
+            ProtocolVersion::V2 => pairs::display_pair,
+        };
+        pairs.iter().map(|pair| normalize(pair)).collect()
+    }
+
+    /// Allocates the next `reqid` and registers a reply slot for it.
+    fn track_request(&self) -> PendingResponse {
+        let reqid = self.next_reqid.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        let mut pending = self.pending.lock().unwrap();
+        // Forget requests whose caller stopped waiting and never got a reply
+        pending.retain(|_, sender| !sender.is_closed());
+        pending.insert(reqid, sender);
+        PendingResponse { reqid, receiver }
+    }
+
+    /// Sends one command per batch of at most
+    /// [`subscription_batch_size`](KrakenClientBuilder::subscription_batch_size) pairs,
+    /// pausing between batches. All batches share the request's reqid.
+    async fn send_batched(
+        &self,
+        pairs: Vec<String>,
+        pending: PendingResponse,
+        command: impl Fn(Vec<String>) -> Command,
+    ) -> Result<PendingResponse> {
+        for (i, batch) in self.batches(pairs).into_iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(self.config.subscription_batch_delay).await;
+            }
+            if self.command_sender.send(command(batch)).await.is_err() {
+                self.pending.lock().unwrap().remove(&pending.reqid);
+                return Err(KrakenError::ChannelClosed);
+            }
+        }
+        Ok(pending)
+    }
+
+    async fn send_tracked(
+        &self,
+        cmd: Command,
+        pending: PendingResponse,
+    ) -> Result<PendingResponse> {
+        if self.command_sender.send(cmd).await.is_err() {
+            self.pending.lock().unwrap().remove(&pending.reqid);
+            return Err(KrakenError::ChannelClosed);
+        }
+        Ok(pending)
+    }
+
+    /// The last system status Kraken reported (e.g. `"online"`, `"maintenance"`),
+    /// or `None` before the first `systemStatus` message.
+    pub fn system_status(&self) -> Option<String> {
+        self.system_status.lock().unwrap().clone()
+    }
+
+    /// The typed form of [`system_status`](Self::system_status).
+    pub fn system_state(&self) -> Option<models::SystemState> {
+        self.system_status().map(models::SystemState::from)
+    }
+
+    /// The `connectionID` from the latest `systemStatus`, which changes on every reconnect.
+    ///
+    /// Kraken support asks for it when investigating a specific connection.
+    pub fn connection_id(&self) -> Option<u64> {
+        *self.connection_id.lock().unwrap()
+    }
+
+    /// Resolves the numeric channel id of a data frame to its `(pair, channel name)`.
+    ///
+    /// The mapping is learned from `subscriptionStatus` confirmations and forgotten on
+    /// unsubscribe and on reconnect, since Kraken may hand out the same ids again on a
+    /// new connection.
+    pub fn channel_for_id(&self, channel_id: u64) -> Option<(String, String)> {
+        self.channels.lock().unwrap().get(&channel_id).cloned()
+    }
+
+    /// Every channel id confirmed on the current connection, see
+    /// [`channel_for_id`](Self::channel_for_id).
+    pub fn channel_ids(&self) -> HashMap<u64, (String, String)> {
+        self.channels.lock().unwrap().clone()
+    }
+
+    /// Returns the order books the client maintains, or `None` unless
+    /// [`manage_order_books`](KrakenClientBuilder::manage_order_books) is enabled.
+    ///
+    /// The handle can be cloned into other tasks (e.g. HTTP handlers) and read at any time;
+    /// hold the read lock only briefly, since the driver takes the write lock for every
+    /// book message.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # async fn example() {
+    /// let client = KrakenClient::builder().manage_order_books(true).build();
+    /// client.connect().await.unwrap();
+    /// client
+    ///     .subscribe(vec!["XBT/USD".to_string()], "book", None)
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// let books = client.books().unwrap();
+    /// let best_bid = books
+    ///     .read()
+    ///     .unwrap()
+    ///     .book("XBT/USD")
+    ///     .and_then(|book| book.best_bid());
+    /// # }
+    /// ```
+    pub fn books(&self) -> Option<SharedBooks> {
+        self.books.clone()
+    }
+
+    fn dispatcher(&self) -> Dispatcher {
+        Dispatcher {
+            protocol: self.config.protocol,
+            surface_parse_errors: self.config.surface_parse_errors,
+            event_sender: self.event_sender.clone(),
+            metrics: self.metrics.clone(),
+            pending: self.pending.clone(),
+            system_status: self.system_status.clone(),
+            connection_id: self.connection_id.clone(),
+            channels: self.channels.clone(),
+            books: self.books.clone(),
+            candles: self.candles.clone(),
+            rate_limited: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Broadcasts a client-generated event to all subscribers.
+    pub(crate) fn emit(&self, event: KrakenEvent) {
+        let _ = self.event_sender.send(event);
+    }
+
+    /// Sends an arbitrary JSON message over the WebSocket.
+    ///
+    /// This is an escape hatch for events the SDK does not model yet
+    /// (e.g. `cancelAllOrdersAfter`). The message is sent once, on the current connection.
+    ///
+    /// **Warning:** raw messages bypass subscription tracking. A subscription sent this
+    /// way does not appear in [`active_subscriptions`](Self::active_subscriptions) and
+    /// is *not* replayed after a reconnect.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # async fn example(client: KrakenClient) {
+    /// client
+    ///     .send_raw(serde_json::json!({"event": "ping", "reqid": 42}))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn send_raw(&self, value: serde_json::Value) -> Result<()> {
+        self.command_sender
+            .send(Command::Raw(value))
+            .await
+            .map_err(|_| KrakenError::ChannelClosed)?;
+        Ok(())
+    }
+
+    /// Places an order over the WebSocket using an authentication `token`
+    /// (see [`Authenticator::get_ws_token`](auth::Authenticator::get_ws_token)).
+    ///
+    /// This is the lowest-latency path to trading. Kraken answers with a
+    /// [`KrakenEvent::AddOrderStatus`] carrying the `txid` or an `error_message`,
+    /// which the returned [`PendingResponse`] resolves with.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::{models::KrakenEvent, orders::AddOrderRequest, KrakenClient};
+    /// # async fn example(client: KrakenClient, token: String) {
+    /// let order = AddOrderRequest::limit("buy", "XBT/USD", "0.01", "30000.0");
+    /// let pending = client.add_order(order, &token).await.unwrap();
+    /// if let KrakenEvent::AddOrderStatus(status) = pending.response().await.unwrap() {
+    ///     println!("{} {:?}", status.status, status.txid);
+    /// }
+    /// # }
+    /// ```
+    pub async fn add_order(&self, order: AddOrderRequest, token: &str) -> Result<PendingResponse> {
+        let pending = self.track_request();
+        let cmd = Command::Raw(order.to_message(token, pending.reqid));
+        self.send_tracked(cmd, pending).await
+    }
+
+    /// Cancels the open order `txid` over the WebSocket, authenticated with `token`.
+    ///
+    /// Kraken answers with a [`KrakenEvent::CancelOrderStatus`], which the returned
+    /// [`PendingResponse`] resolves with.
+    pub async fn cancel_order(&self, txid: &str, token: &str) -> Result<PendingResponse> {
+        let pending = self.track_request();
+        let cmd = Command::Raw(orders::cancel_order_message(txid, token, pending.reqid));
+        self.send_tracked(cmd, pending).await
+    }
+
+    /// Cancels every open order over the WebSocket, authenticated with `token`.
+    ///
+    /// Kraken answers with a [`KrakenEvent::CancelAllStatus`] carrying the number of
+    /// cancelled orders, which the returned [`PendingResponse`] resolves with.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::{models::KrakenEvent, KrakenClient};
+    /// # async fn example(client: KrakenClient, token: String) {
+    /// let pending = client.cancel_all(&token).await.unwrap();
+    /// if let KrakenEvent::CancelAllStatus(status) = pending.response().await.unwrap() {
+    ///     println!("cancelled {:?} orders", status.count);
+    /// }
+    /// # }
+    /// ```
+    pub async fn cancel_all(&self, token: &str) -> Result<PendingResponse> {
+        let pending = self.track_request();
+        let cmd = Command::Raw(orders::cancel_all_message(token, pending.reqid));
+        self.send_tracked(cmd, pending).await
+    }
+
+    /// Arms Kraken's dead man's switch: unless this is sent again within `seconds`, all
+    /// open orders are cancelled. A `seconds` of 0 disables the timer.
+    ///
+    /// Kraken answers with a [`KrakenEvent::CancelAllOrdersAfterStatus`] carrying the
+    /// trigger time. Kraken recommends a timeout of 60 seconds renewed every 15 to 30
+    /// seconds; [`cancel_all_after_renewing`](Self::cancel_all_after_renewing) does the
+    /// renewing. An armed timer is disabled by [`disconnect`](Self::disconnect).
+    pub async fn cancel_all_after(&self, seconds: u64, token: &str) -> Result<PendingResponse> {
+        let pending = self.track_request();
+        let cmd = Command::Raw(orders::cancel_all_after_message(
+            seconds,
+            token,
+            Some(pending.reqid),
+        ));
+        let pending = self.send_tracked(cmd, pending).await?;
+        let switch = (seconds > 0).then(|| DeadMansSwitch {
+            token: token.to_string(),
+            renewal: None,
+        });
+        self.replace_dead_mans_switch(switch);
+        Ok(pending)
+    }
+
+    /// Like [`cancel_all_after`](Self::cancel_all_after), then re-sends the timer every
+    /// `every` on a spawned task until [`disconnect`](Self::disconnect), a new timer is
+    /// set, or the client is dropped.
+    ///
+    /// Renewals are not tied to a reqid; their replies are broadcast as events. If the
+    /// process dies, renewals stop and Kraken cancels the open orders after `seconds`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # use std::time::Duration;
+    /// # async fn example(client: KrakenClient, token: String) {
+    /// client
+    ///     .cancel_all_after_renewing(60, &token, Duration::from_secs(20))
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn cancel_all_after_renewing(
+        &self,
+        seconds: u64,
+        token: &str,
+        every: Duration,
+    ) -> Result<PendingResponse> {
+        let pending = self.cancel_all_after(seconds, token).await?;
+        if seconds == 0 {
+            return Ok(pending);
+        }
+        // A weak sender, so the renewals do not keep the driver alive on their own
+        let commands = self.command_sender.downgrade();
+        let message = orders::cancel_all_after_message(seconds, token, None);
+        let renewal = tokio::spawn(async move {
+            let start = tokio::time::Instant::now() + every;
+            let mut ticks = tokio::time::interval_at(start, every);
+            loop {
+                ticks.tick().await;
+                let Some(sender) = commands.upgrade() else {
+                    break;
+                };
+                if sender.send(Command::Raw(message.clone())).await.is_err() {
+                    break;
+                }
+            }
+        });
+        self.replace_dead_mans_switch(Some(DeadMansSwitch {
+            token: token.to_string(),
+            renewal: Some(renewal),
+        }));
+        Ok(pending)
+    }
+
+    /// Records the current timer, stopping the renewals of the previous one.
+    fn replace_dead_mans_switch(&self, switch: Option<DeadMansSwitch>) {
+        let previous = std::mem::replace(&mut *self.dead_mans_switch.lock().unwrap(), switch);
+        if let Some(renewal) = previous.and_then(|previous| previous.renewal) {
+            renewal.abort();
+        }
+    }
+
+    /// Closes the connection and stops the driver for good.
+    ///
+    /// A dead man's switch armed with [`cancel_all_after`](Self::cancel_all_after) is
+    /// disabled first (`timeout: 0`), so a deliberate shutdown does not cancel resting
+    /// orders. Returns once the driver has stopped and broadcast
+    /// [`ConnectionState::Disconnected { permanent: true }`](ConnectionState::Disconnected).
+    /// A client that never connected can no longer connect afterwards.
+    pub async fn disconnect(&self) -> Result<()> {
+        let switch = self.dead_mans_switch.lock().unwrap().take();
+        if let Some(switch) = switch {
+            if let Some(renewal) = switch.renewal {
+                renewal.abort();
+            }
+            let disarm = orders::cancel_all_after_message(0, &switch.token, None);
+            self.send_raw(disarm).await?;
+        }
+        // Nothing to close; dropping the receiver keeps a later connect() from starting
+        if self.command_receiver.lock().unwrap().take().is_some() {
+            return Ok(());
+        }
+        let mut events = self.subscribe_events();
         self.command_sender
-            .send(cmd)
+            .send(Command::Shutdown)
             .await
-            .map_err(|e| eyre::eyre!("Failed to send command: {}", e))?;
+            .map_err(|_| KrakenError::ChannelClosed)?;
+        loop {
+            match events.recv().await {
+                Ok(KrakenEvent::ConnectionState(ConnectionState::Disconnected {
+                    permanent: true,
+                }))
+                | Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends a raw text frame over the WebSocket, written exactly as given.
+    ///
+    /// Like [`send_raw`](Self::send_raw), this bypasses subscription tracking.
+    pub async fn send_raw_text(&self, text: String) -> Result<()> {
+        self.command_sender
+            .send(Command::RawText(text))
+            .await
+            .map_err(|_| KrakenError::ChannelClosed)?;
         Ok(())
     }
 
+    /// Feeds a recording made with [`record_to`](KrakenClientBuilder::record_to) through the
+    /// normal parse and broadcast pipeline, as if each line had arrived on the socket.
+    ///
+    /// Subscribe to events *before* calling this. Lines are replayed as fast as the
+    /// subscribers keep up; size [`event_buffer`](KrakenClientBuilder::event_buffer) for
+    /// the recording if a consumer only starts reading afterwards. Returns the number of
+    /// frames replayed. No connection is needed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use kraken_sdk::KrakenClient;
+    /// # async fn example() {
+    /// let client = KrakenClient::new();
+    /// let mut rx = client.subscribe_events();
+    /// tokio::spawn(async move {
+    ///     while let Ok(event) = rx.recv().await {
+    ///         println!("{:?}", event);
+    ///     }
+    /// });
+    /// client.replay_from("session.jsonl").await.unwrap();
+    /// # }
+    /// ```
+    pub async fn replay_from(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let file = tokio::fs::File::open(path).await?;
+        let mut source = RecordingSource::new(file);
+        let count = self.run_source(&mut source).await;
+        match source.take_error() {
+            Some(e) => Err(e.into()),
+            None => Ok(count),
+        }
+    }
+
+    /// Feeds every frame from `source` through the parse and broadcast pipeline until the
+    /// source is exhausted, and returns the number of frames processed.
+    ///
+    /// This is what the live driver does with each text frame, minus the connection
+    /// management, so event handling can be exercised with a
+    /// [`VecSource`](source::VecSource) or [`ChannelSource`](source::ChannelSource).
+    pub async fn run_source(&self, mut source: impl MessageSource) -> usize {
+        let dispatcher = self.dispatcher();
+        let mut count = 0;
+        while let Some(frame) = source.next_frame().await {
+            dispatcher.dispatch(&frame);
+            count += 1;
+            // Give subscribers on other tasks a chance to drain the channel
+            tokio::task::yield_now().await;
+        }
+        count
+    }
+
     /// Connects to the Kraken WebSocket API and starts the event loop.
     ///
     /// This spawns a background task that handles:
@@ -136,81 +1219,287 @@ impl KrakenClient {
     /// - Sending outgoing commands.
     /// - Broadcasting events to subscribers.
     ///
+    /// Returns the driver task's handle, so a supervisor can await it (it finishes when
+    /// the client is dropped, or with a `JoinError` if it panicked) or `abort()` it.
+    /// Dropping the handle leaves the driver running; see also
+    /// [`connect_detached`](Self::connect_detached).
+    ///
     /// # Errors
     ///
-    /// Returns an error if the client has already connected (the command receiver is taken).
-    pub async fn connect(&self) -> Result<()> {
+    /// Returns an error if the client has already connected (the command receiver is taken),
+    /// or if the [`record_to`](KrakenClientBuilder::record_to) file cannot be opened.
+    pub async fn connect(&self) -> Result<JoinHandle<()>> {
         info!("Starting Kraken Client...");
 
         // Take the command receiver
-        let mut command_receiver = self
-            .command_receiver
-            .lock()
-            .unwrap()
-            .take()
-            .ok_or_else(|| eyre::eyre!("Client already connected (receiver taken)"))?;
+        let mut command_receiver =
+            self.command_receiver
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| {
+                    KrakenError::Connection("client already connected (receiver taken)".to_string())
+                })?;
+
+        // Commands sent before connect() are folded into the first subscription batch
+        let mut queued_before_connect = command_receiver.len();
 
-        let ws_url = self.ws_url.clone();
-        let event_sender = self.event_sender.clone();
+        let ClientConfig {
+            ws_url,
+            heartbeat_timeout,
+            connect_timeout,
+            ping_interval,
+            protocol,
+            record_path,
+            subscription_batch_delay,
+            rate_limit,
+            client_id,
+            replay_strategy,
+            max_reconnect_attempts,
+            rate_limit_backoff,
+            ..
+        } = (*self.config).clone();
+        let mut recorder = match record_path {
+            Some(path) => Some(
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?,
+            ),
+            None => None,
+        };
+        let connected = self.connected.clone();
+        let metrics = self.metrics.clone();
+        let dispatcher = self.dispatcher();
 
         // State to track active subscriptions for re-subscribing
         // We use a simple list of commands that we've sent.
         // In a real app, we might want to be smarter (e.g. remove unsubscribes),
         // but for now, replaying the "Subscribe" commands is sufficient.
-        let mut active_subscriptions: Vec<Command> = Vec::new();
+        let active_subscriptions = self.active_subscriptions.clone();
+
+        // Every log line from the driver carries the endpoint and, if set, the client id
+        let span = info_span!(
+            "kraken_client",
+            client_id = client_id.as_deref().unwrap_or_default(),
+            ws_url = %ws_url
+        );
 
         // Spawn the driver task
-        tokio::spawn(async move {
+        let driver = tokio::spawn(async move {
+            let mut has_connected = false;
+            // Attempts since the last stable connection, checked against max_reconnect_attempts
+            let mut reconnect_attempts = 0;
+            let mut throttle = rate_limit
+                .map(|per_second| TokenBucket::new(per_second, tokio::time::Instant::now()));
             loop {
                 info!("Connecting to {}...", ws_url);
-                let ws_stream = match connect_async(&ws_url).await {
-                    Ok((stream, _)) => {
-                        info!("Connected to Kraken WebSocket API");
-                        stream
-                    }
-                    Err(e) => {
-                        error!("Connection failed: {}. Retrying in 5s...", e);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                let attempt = tokio::time::timeout(connect_timeout, tls::connect(&ws_url)).await;
+                let ws_stream = match attempt {
+                    Ok(Ok((stream, _))) => stream,
+                    failed => {
+                        let throttled = matches!(
+                            &failed,
+                            Ok(Err(tokio_tungstenite::tungstenite::Error::Http(response)))
+                                if response.status().as_u16() == 429
+                        );
+                        let failure = match failed {
+                            Ok(Err(e)) => format!("Connection failed: {}", e),
+                            _ => format!(
+                                "Connection attempt timed out after {:?}",
+                                connect_timeout
+                            ),
+                        };
+                        reconnect_attempts += 1;
+                        if max_reconnect_attempts.is_some_and(|max| reconnect_attempts > max) {
+                            error!("{}. Giving up after {} attempt(s)", failure, reconnect_attempts);
+                            dispatcher.emit(KrakenEvent::ConnectionState(
+                                ConnectionState::Disconnected { permanent: true },
+                            ));
+                            return;
+                        }
+                        dispatcher.emit(KrakenEvent::ConnectionState(
+                            ConnectionState::Disconnected { permanent: false },
+                        ));
+                        if throttled {
+                            error!("{}. Rate limited, retrying in {:?}...", failure, rate_limit_backoff);
+                            dispatcher.emit(KrakenEvent::RateLimited {
+                                reason: failure,
+                                retry_in: rate_limit_backoff,
+                            });
+                            tokio::time::sleep(rate_limit_backoff).await;
+                        } else {
+                            error!("{}. Retrying in 5s...", failure);
+                            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                        }
                         continue;
                     }
                 };
+                info!("Connected to Kraken WebSocket API");
+                connected.store(true, Ordering::Relaxed);
+                // Channel ids are per connection; fresh confirmations follow the replay
+                dispatcher.channels.lock().unwrap().clear();
+                // So is throttling: only errors on this connection justify a long backoff
+                dispatcher.take_rate_limit();
+                let mut rate_limited = None;
+                if has_connected {
+                    metrics.record_reconnect();
+                    active_subscriptions.lock().unwrap().retain(|cmd| match cmd {
+                        Command::Subscribe {
+                            pairs,
+                            subscription,
+                            ..
+                        } => replay_strategy.replays(pairs, subscription),
+                        _ => true,
+                    });
+                }
+                let connected_at = tokio::time::Instant::now();
 
                 let (mut write, mut read) = ws_stream.split();
 
+                // Subscriptions queued before connect() join the initial batch. Folding stops
+                // at the first other command, so everything after it keeps its order.
+                let mut queued = None;
+                while queued_before_connect > 0 {
+                    queued_before_connect -= 1;
+                    match command_receiver.try_recv() {
+                        Ok(cmd @ Command::Subscribe { .. }) => {
+                            active_subscriptions.lock().unwrap().push(cmd)
+                        }
+                        Ok(cmd) => {
+                            queued = Some(cmd);
+                            queued_before_connect = 0;
+                        }
+                        Err(_) => break,
+                    }
+                }
+
                 // Re-send active subscriptions
-                for cmd in &active_subscriptions {
+                let replay = active_subscriptions.lock().unwrap().clone();
+                for (i, cmd) in replay.iter().enumerate() {
                     let Command::Subscribe {
                         pairs,
                         subscription,
-                    } = cmd;
-                    let msg = serde_json::json!({
-                        "event": "subscribe",
-                        "pair": pairs,
-                        "subscription": subscription
-                    });
-                    if let Err(e) = write.send(Message::Text(msg.to_string())).await {
+                        reqid,
+                    } = cmd
+                    else {
+                        continue;
+                    };
+                    if i > 0 {
+                        // Same spacing as the original batched subscribe
+                        tokio::time::sleep(subscription_batch_delay).await;
+                    }
+                    let msg =
+                        subscription_message(protocol, "subscribe", pairs, subscription, *reqid);
+                    if let Err(e) = write.send(Message::Text(msg)).await {
                         error!("Failed to resubscribe: {}", e);
                         // If we can't send, the connection is likely dead, break to outer loop
                         break;
                     }
                     info!("Resubscribed to {:?}", pairs);
                 }
+                if let Some(cmd) = queued {
+                    if let Err(e) =
+                        send_command(&mut write, protocol, &active_subscriptions, &cmd).await
+                    {
+                        error!("Failed to send queued command: {}", e);
+                    }
+                }
+                dispatcher.emit(KrakenEvent::ConnectionState(if has_connected {
+                    ConnectionState::Reconnected
+                } else {
+                    ConnectionState::Connected
+                }));
+                has_connected = true;
+
+                // Watchdog: if nothing arrives (not even a heartbeat) within the timeout,
+                // assume the connection is half-open and reconnect.
+                let watchdog = tokio::time::sleep(heartbeat_timeout);
+                tokio::pin!(watchdog);
+
+                // Keepalive: ping periodically and expect something back before the next ping.
+                let mut ping_timer = ping_interval.map(|interval| {
+                    tokio::time::interval_at(tokio::time::Instant::now() + interval, interval)
+                });
+                let mut awaiting_pong = false;
 
                 loop {
+                    // While throttled, leave commands queued but keep reading the socket
+                    let throttle_wait = throttle
+                        .as_mut()
+                        .map(|bucket| bucket.wait_time(tokio::time::Instant::now()))
+                        .unwrap_or_default();
+
                     tokio::select! {
                         // 1. Handle incoming WS messages
                         msg_opt = read.next() => {
+                            watchdog
+                                .as_mut()
+                                .reset(tokio::time::Instant::now() + heartbeat_timeout);
+                            awaiting_pong = false;
                             match msg_opt {
-                                Some(Ok(Message::Text(text))) => {
-                                    match serde_json::from_str::<KrakenEvent>(&text) {
-                                        Ok(event) => {
-                                            let _ = event_sender.send(event);
+                                // Binary frames are JSON too (e.g. behind a proxy), so share the text path
+                                Some(Ok(frame @ (Message::Text(_) | Message::Binary(_)))) => {
+                                    let text = match frame.into_text() {
+                                        Ok(text) => text,
+                                        Err(e) => {
+                                            metrics.record_parse_error();
+                                            error!("Dropping binary frame that is not UTF-8: {}", e);
+                                            continue;
+                                        }
+                                    };
+                                    if let Some(file) = recorder.as_mut() {
+                                        if let Err(e) = record_frame(file, &text).await {
+                                            error!("Failed to record frame, recording stopped: {}", e);
+                                            recorder = None;
+                                        }
+                                    }
+                                    // A managed book failed its checksum: fetch a fresh snapshot
+                                    if let Some(pair) = dispatcher.dispatch(&text) {
+                                        let pairs = vec![pair];
+                                        let subscription = tracked_subscription(
+                                            &active_subscriptions.lock().unwrap(),
+                                            &pairs,
+                                            "book",
+                                        );
+                                        let resync = [
+                                            Command::Unsubscribe {
+                                                pairs: pairs.clone(),
+                                                subscription: subscription.clone(),
+                                                reqid: None,
+                                            },
+                                            Command::Subscribe {
+                                                pairs,
+                                                subscription,
+                                                reqid: None,
+                                            },
+                                        ];
+                                        for cmd in &resync {
+                                            if let Err(e) = send_command(&mut write, protocol, &active_subscriptions, cmd).await {
+                                                error!("Failed to resubscribe book: {}", e);
+                                            }
                                         }
-                                        Err(e) => error!("Parse error: {}", e),
                                     }
                                 }
                                 Some(Ok(Message::Ping(_))) => {}
+                                Some(Ok(Message::Close(frame))) => {
+                                    match frame {
+                                        Some(frame) => {
+                                            warn!(
+                                                "Server closed the connection ({}: {}). Reconnecting...",
+                                                frame.code, frame.reason
+                                            );
+                                            if is_rate_limit_message(&frame.reason) {
+                                                rate_limited = Some(frame.reason.to_string());
+                                            }
+                                        }
+                                        None => warn!("Server closed the connection. Reconnecting..."),
+                                    }
+                                    break; // Break inner loop to reconnect
+                                }
                                 Some(Err(e)) => {
+                                    metrics.record_error();
                                     error!("WS Error: {}. Reconnecting...", e);
                                     break; // Break inner loop to reconnect
                                 }
@@ -222,41 +1511,481 @@ impl KrakenClient {
                             }
                         }
                         // 2. Handle outgoing commands
-                        cmd_opt = command_receiver.recv() => {
+                        cmd_opt = command_receiver.recv(), if throttle_wait.is_zero() => {
                             match cmd_opt {
                                 Some(cmd) => {
-                                    match &cmd {
-                                        Command::Subscribe { pairs, subscription } => {
-                                            let msg = serde_json::json!({
-                                                "event": "subscribe",
-                                                "pair": pairs,
-                                                "subscription": subscription
-                                            });
-                                            if let Err(e) = write.send(Message::Text(msg.to_string())).await {
-                                                error!("Failed to send subscription: {}", e);
-                                                break; // Connection likely dead
-                                            }
-                                            info!("Sent subscription for {:?}", pairs);
-
-                                            // Add to active subscriptions
-                                            active_subscriptions.push(cmd);
-                                        }
+                                    if let Some(bucket) = throttle.as_mut() {
+                                        bucket.take();
+                                    }
+                                    let sent = send_command(&mut write, protocol, &active_subscriptions, &cmd).await;
+                                    if matches!(cmd, Command::Shutdown) {
+                                        info!("Disconnected on request. Shutting down client.");
+                                        connected.store(false, Ordering::Relaxed);
+                                        dispatcher.emit(KrakenEvent::ConnectionState(
+                                            ConnectionState::Disconnected { permanent: true },
+                                        ));
+                                        return;
+                                    }
+                                    if let Err(e) = sent {
+                                        error!("Failed to send command: {}", e);
+                                        break; // Connection likely dead
                                     }
                                 }
                                 None => {
                                     warn!("Command channel closed. Shutting down client.");
+                                    connected.store(false, Ordering::Relaxed);
+                                    dispatcher.emit(KrakenEvent::ConnectionState(
+                                        ConnectionState::Disconnected { permanent: true },
+                                    ));
                                     return; // Exit the task entirely
                                 }
                             }
                         }
+                        // Wake up once the rate limiter has a token again
+                        _ = tokio::time::sleep(throttle_wait), if !throttle_wait.is_zero() => {}
+                        // 3. Send keepalive pings
+                        _ = async {
+                            match ping_timer.as_mut() {
+                                Some(timer) => {
+                                    timer.tick().await;
+                                }
+                                None => std::future::pending::<()>().await,
+                            }
+                        } => {
+                            if awaiting_pong {
+                                warn!("No reply to ping within {:?}. Reconnecting...", ping_interval);
+                                break; // Break inner loop to reconnect
+                            }
+                            if let Err(e) = write.send(Message::Ping(Vec::new())).await {
+                                error!("Failed to send ping: {}", e);
+                                break; // Connection likely dead
+                            }
+                            awaiting_pong = true;
+                        }
+                        // 4. Stale connection watchdog
+                        _ = &mut watchdog => {
+                            warn!(
+                                "No message received for {:?}, connection looks stale. Reconnecting...",
+                                heartbeat_timeout
+                            );
+                            break; // Break inner loop to reconnect
+                        }
                     }
                 }
 
                 // If we broke the inner loop, wait a bit before reconnecting
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                connected.store(false, Ordering::Relaxed);
+                // A connection that outlived one heartbeat window was healthy
+                if connected_at.elapsed() >= heartbeat_timeout {
+                    reconnect_attempts = 0;
+                }
+                reconnect_attempts += 1;
+                if max_reconnect_attempts.is_some_and(|max| reconnect_attempts > max) {
+                    error!("Giving up after {} reconnect attempt(s)", reconnect_attempts - 1);
+                    dispatcher.emit(KrakenEvent::ConnectionState(ConnectionState::Disconnected {
+                        permanent: true,
+                    }));
+                    return;
+                }
+                // Managed books are stale until the replayed subscriptions send new snapshots
+                if let Some(books) = &dispatcher.books {
+                    books.write().unwrap().reset();
+                }
+                dispatcher.emit(KrakenEvent::ConnectionState(ConnectionState::Reconnecting));
+                match rate_limited.or_else(|| dispatcher.take_rate_limit()) {
+                    // Reconnecting right away would run into the same limit
+                    Some(reason) => {
+                        warn!("Rate limited ({}). Reconnecting in {:?}...", reason, rate_limit_backoff);
+                        dispatcher.emit(KrakenEvent::RateLimited {
+                            reason,
+                            retry_in: rate_limit_backoff,
+                        });
+                        tokio::time::sleep(rate_limit_backoff).await;
+                    }
+                    None => tokio::time::sleep(tokio::time::Duration::from_secs(1)).await,
+                }
             }
-        });
+        }.instrument(span));
 
-        Ok(())
+        Ok(driver)
+    }
+
+    /// Like [`connect`](Self::connect), but detaches the driver task instead of
+    /// returning its handle.
+    pub async fn connect_detached(&self) -> Result<()> {
+        self.connect().await.map(drop)
+    }
+}
+
+/// Splits `(pairs, channel)` subscriptions into `(pair, channel)` entries, in order and
+/// without duplicates.
+fn per_pair(subscriptions: &[(Vec<String>, String)]) -> Vec<(String, String)> {
+    let mut seen = HashSet::new();
+    subscriptions
+        .iter()
+        .flat_map(|(pairs, name)| pairs.iter().map(move |pair| (pair.clone(), name.clone())))
+        .filter(|sub| seen.insert(sub.clone()))
+        .collect()
+}
+
+/// Groups `(pair, channel)` entries by channel, keeping the order channels first appear in.
+fn by_channel<'a>(
+    subscriptions: impl Iterator<Item = &'a (String, String)>,
+) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for (pair, name) in subscriptions {
+        match groups.iter_mut().find(|(channel, _)| channel == name) {
+            Some((_, pairs)) => pairs.push(pair.clone()),
+            None => groups.push((name.clone(), vec![pair.clone()])),
+        }
+    }
+    groups
+}
+
+/// Returns the options of the tracked subscription covering any of `pairs` on channel
+/// `name`, or the defaults if there is none. With no `pairs`, matches the pair-less
+/// subscription of a private channel.
+fn tracked_subscription(active: &[Command], pairs: &[String], name: &str) -> SubscriptionArgs {
+    active
+        .iter()
+        .find_map(|cmd| match cmd {
+            Command::Subscribe {
+                pairs: active,
+                subscription,
+                ..
+            } if subscription.name == name
+                && (pairs.iter().any(|p| active.contains(p))
+                    || pairs.is_empty() && active.is_empty()) =>
+            {
+                Some(subscription.clone())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| SubscriptionArgs::new(name))
+}
+
+/// Removes `pairs` from the tracked subscriptions on channel `name`,
+/// dropping entries that end up with no pairs.
+fn remove_subscription(active: &mut Vec<Command>, pairs: &[String], name: &str) {
+    active.retain_mut(|cmd| match cmd {
+        Command::Subscribe {
+            pairs: active_pairs,
+            subscription,
+            ..
+        } if subscription.name == name => {
+            active_pairs.retain(|p| !pairs.contains(p));
+            !active_pairs.is_empty()
+        }
+        _ => true,
+    });
+}
+
+/// Builds a `subscribe`/`unsubscribe` message in the wire format of `protocol`.
+fn subscription_message(
+    protocol: ProtocolVersion,
+    event: &str,
+    pairs: &[String],
+    subscription: &SubscriptionArgs,
+    reqid: Option<u64>,
+) -> String {
+    match protocol {
+        ProtocolVersion::V1 => {
+            let mut msg = serde_json::json!({
+                "event": event,
+                "subscription": subscription
+            });
+            // Private channels such as ownTrades take no pairs; Kraken may reject "pair": []
+            if !pairs.is_empty() {
+                msg["pair"] = serde_json::json!(pairs);
+            }
+            if let Some(reqid) = reqid {
+                msg["reqid"] = serde_json::json!(reqid);
+            }
+            msg.to_string()
+        }
+        ProtocolVersion::V2 => v2::request_message(event, pairs, subscription, reqid).to_string(),
+    }
+}
+
+/// The error for a rejected subscription, or `None` if `event` is not a rejection.
+///
+/// Rejections for exceeding Kraken's limits become [`KrakenError::RateLimited`].
+fn subscription_error(event: &KrakenEvent) -> Option<KrakenError> {
+    let message = match event {
+        KrakenEvent::SubscriptionStatus(status) if status.status.as_deref() == Some("error") => {
+            let message = status.error_message.as_deref().unwrap_or("unknown error");
+            Some(match &status.pair {
+                Some(pair) => format!("{}: {}", pair, message),
+                None => message.to_string(),
+            })
+        }
+        KrakenEvent::V2(v2::KrakenEventV2::Method(response)) if response.success == Some(false) => {
+            Some(
+                response
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "unknown error".to_string()),
+            )
+        }
+        _ => None,
+    }?;
+    Some(if is_rate_limit_message(&message) {
+        KrakenError::RateLimited(message)
+    } else {
+        KrakenError::Subscription(message)
+    })
+}
+
+/// The error message of `event` if it says Kraken is throttling this client.
+fn rate_limit_reason(event: &KrakenEvent) -> Option<String> {
+    let message = match event {
+        KrakenEvent::SubscriptionStatus(status) => status.error_message.as_deref(),
+        KrakenEvent::AddOrderStatus(status) => status.error_message.as_deref(),
+        KrakenEvent::CancelOrderStatus(status) => status.error_message.as_deref(),
+        KrakenEvent::CancelAllStatus(status) => status.error_message.as_deref(),
+        KrakenEvent::CancelAllOrdersAfterStatus(status) => status.error_message.as_deref(),
+        KrakenEvent::V2(v2::KrakenEventV2::Method(response)) => response.error.as_deref(),
+        // e.g. {"event":"error","errorMessage":"Exceeded msg rate"}
+        KrakenEvent::Unknown(value) => value.get("errorMessage").and_then(|m| m.as_str()),
+        _ => None,
+    }?;
+    is_rate_limit_message(message).then(|| message.to_string())
+}
+
+/// Writes one command to the socket, keeping the tracked subscriptions in sync.
+async fn send_command<S>(
+    write: &mut S,
+    protocol: ProtocolVersion,
+    active_subscriptions: &Mutex<Vec<Command>>,
+    cmd: &Command,
+) -> std::result::Result<(), tokio_tungstenite::tungstenite::Error>
+where
+    S: futures_util::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    match cmd {
+        Command::Subscribe {
+            pairs,
+            subscription,
+            reqid,
+        } => {
+            // Track it first so it is replayed even if this send fails
+            active_subscriptions.lock().unwrap().push(cmd.clone());
+            let msg = subscription_message(protocol, "subscribe", pairs, subscription, *reqid);
+            write.send(Message::Text(msg)).await?;
+            info!("Sent subscription for {:?}", pairs);
+        }
+        Command::Unsubscribe {
+            pairs,
+            subscription,
+            reqid,
+        } => {
+            // Stop tracking first so a reconnect does not replay it
+            remove_subscription(
+                &mut active_subscriptions.lock().unwrap(),
+                pairs,
+                &subscription.name,
+            );
+            let msg = subscription_message(protocol, "unsubscribe", pairs, subscription, *reqid);
+            write.send(Message::Text(msg)).await?;
+            info!("Sent unsubscription for {:?}", pairs);
+        }
+        Command::Raw(value) => {
+            write.send(Message::Text(value.to_string())).await?;
+            info!("Sent raw message");
+        }
+        Command::RawText(text) => {
+            write.send(Message::Text(text.clone())).await?;
+            info!("Sent raw message");
+        }
+        Command::Shutdown => {
+            write.send(Message::Close(None)).await?;
+            info!("Sent close frame");
+        }
+    }
+    Ok(())
+}
+
+/// Appends one frame to the recording. Flushing hands the write to the OS before the
+/// frame is broadcast, so the file is complete up to the last event a subscriber saw.
+async fn record_frame(file: &mut tokio::fs::File, text: &str) -> std::io::Result<()> {
+    file.write_all(format!("{}\n", text).as_bytes()).await?;
+    file.flush().await
+}
+
+/// Turns inbound text frames into broadcast events. Shared by the live driver,
+/// [`KrakenClient::replay_from`] and [`KrakenClient::run_source`].
+#[derive(Clone)]
+struct Dispatcher {
+    protocol: ProtocolVersion,
+    surface_parse_errors: bool,
+    event_sender: broadcast::Sender<KrakenEvent>,
+    metrics: Arc<MetricsRecorder>,
+    pending: PendingRequests,
+    system_status: Arc<Mutex<Option<String>>>,
+    connection_id: Arc<Mutex<Option<u64>>>,
+    channels: ChannelMap,
+    books: Option<SharedBooks>,
+    candles: Option<Arc<Mutex<MultiPairAggregator>>>,
+    // Reason of the last rate-limit error seen on the current connection
+    rate_limited: Arc<Mutex<Option<String>>>,
+}
+
+impl Dispatcher {
+    /// Parses a frame, resolves any request waiting on its reqid and broadcasts it.
+    ///
+    /// Returns the pair whose managed book failed its checksum and must be re-subscribed.
+    fn dispatch(&self, text: &str) -> Option<String> {
+        self.metrics.record_message();
+        let event = match parse_event(self.protocol, text) {
+            Ok(event) => event,
+            Err(e) => {
+                self.metrics.record_parse_error();
+                error!("Parse error: {}", e);
+                if self.surface_parse_errors {
+                    let _ = self.event_sender.send(KrakenEvent::ParseError {
+                        raw: text.to_string(),
+                        error: e.to_string(),
+                    });
+                }
+                return None;
+            }
+        };
+        self.metrics.record_event(&event);
+        if let Some(reqid) = event.reqid() {
+            if let Some(reply) = self.pending.lock().unwrap().remove(&reqid) {
+                let _ = reply.send(event.clone());
+            }
+        }
+        let status_change = match &event {
+            KrakenEvent::SystemStatus(status) => {
+                if status.connection_id.is_some() {
+                    *self.connection_id.lock().unwrap() = status.connection_id;
+                }
+                self.track_system_status(&status.status)
+            }
+            KrakenEvent::SubscriptionStatus(status)
+                if status.status.as_deref() == Some("error") =>
+            {
+                warn!(
+                    "Subscription to {:?} failed: {}",
+                    status.pair,
+                    status.error_message.as_deref().unwrap_or("unknown error")
+                );
+                None
+            }
+            KrakenEvent::SubscriptionStatus(status) => {
+                self.track_channel_id(status);
+                None
+            }
+            KrakenEvent::Unknown(value) => {
+                debug!("Unrecognized message: {}", value);
+                None
+            }
+            _ => None,
+        };
+        if let Some(reason) = rate_limit_reason(&event) {
+            warn!("Kraken is throttling this client: {}", reason);
+            *self.rate_limited.lock().unwrap() = Some(reason);
+        }
+        let resync = self.track_book(&event);
+        let closed = self.aggregate(&event);
+        let _ = self.event_sender.send(event);
+        for candle in closed {
+            let _ = self.event_sender.send(candle);
+        }
+        if let Some(change) = status_change {
+            let _ = self.event_sender.send(change);
+        }
+        if let Some(pair) = &resync {
+            let _ = self
+                .event_sender
+                .send(KrakenEvent::ChecksumMismatch { pair: pair.clone() });
+        }
+        resync
+    }
+
+    /// Applies book messages to the managed books, returning the pair on a checksum mismatch.
+    fn track_book(&self, event: &KrakenEvent) -> Option<String> {
+        let books = self.books.as_ref()?;
+        let data = event.clone().try_into_orderbook_data()?;
+        if books.write().unwrap().update(&data) != BookUpdate::Mismatch {
+            return None;
+        }
+        warn!(
+            "Managed book for {} is out of sync, resubscribing",
+            data.pair
+        );
+        self.metrics.record_checksum_mismatch();
+        Some(data.pair)
+    }
+
+    /// Feeds trade messages to the candle aggregator, returning the candles they closed.
+    fn aggregate(&self, event: &KrakenEvent) -> Vec<KrakenEvent> {
+        let (Some(candles), KrakenEvent::Channel(ChannelData::Trade(data))) =
+            (&self.candles, event)
+        else {
+            return Vec::new();
+        };
+        candles
+            .lock()
+            .unwrap()
+            .process(data)
+            .into_iter()
+            .map(|candle| KrakenEvent::CandleClosed {
+                pair: data.pair.clone(),
+                candle,
+            })
+            .collect()
+    }
+
+    /// Broadcasts a client-generated event.
+    fn emit(&self, event: KrakenEvent) {
+        let _ = self.event_sender.send(event);
+    }
+
+    /// Takes the reason of a rate-limit error seen since the last call, if any.
+    fn take_rate_limit(&self) -> Option<String> {
+        self.rate_limited.lock().unwrap().take()
+    }
+
+    /// Keeps the `channelID -> (pair, channel name)` map in sync with confirmations.
+    fn track_channel_id(&self, status: &models::SubscriptionStatus) {
+        let Some(channel_id) = status.channel_id else {
+            return;
+        };
+        let mut channels = self.channels.lock().unwrap();
+        match (status.status.as_deref(), &status.pair, &status.channel_name) {
+            (Some("subscribed"), Some(pair), Some(name)) => {
+                channels.insert(channel_id, (pair.clone(), name.clone()));
+            }
+            (Some("unsubscribed"), _, _) => {
+                channels.remove(&channel_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Records the latest system status, returning a change event if it differs from the last one.
+    fn track_system_status(&self, status: &str) -> Option<KrakenEvent> {
+        let mut current = self.system_status.lock().unwrap();
+        if current.as_deref() == Some(status) {
+            return None;
+        }
+        let from = current.replace(status.to_string());
+        if from.is_some() {
+            warn!("System status changed: {:?} -> {}", from, status);
+        }
+        Some(KrakenEvent::SystemStatusChanged {
+            from,
+            to: status.to_string(),
+        })
+    }
+}
+
+/// Parses an inbound text frame according to `protocol`.
+fn parse_event(protocol: ProtocolVersion, text: &str) -> serde_json::Result<KrakenEvent> {
+    match protocol {
+        ProtocolVersion::V1 => serde_json::from_str::<KrakenEvent>(text),
+        ProtocolVersion::V2 => serde_json::from_str::<v2::KrakenEventV2>(text).map(KrakenEvent::V2),
     }
 }
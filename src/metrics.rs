@@ -0,0 +1,445 @@
+use crate::models::{ChannelData, KrakenEvent};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A point-in-time copy of the client's counters and gauges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Text frames received from the WebSocket.
+    pub messages: u64,
+    /// Parse failures and WebSocket errors.
+    pub errors: u64,
+    /// Frames that could not be parsed (also included in `errors`).
+    pub parse_errors: u64,
+    /// Successful connections after the first one.
+    pub reconnects: u64,
+    /// Gauge: delay between the latest trade's timestamp and its arrival, in milliseconds.
+    pub latency_ms: u64,
+    /// Managed order books that failed checksum validation.
+    pub checksum_mismatches: u64,
+}
+
+/// Messages received per channel type, see [`KrakenClient::stats`](crate::KrakenClient::stats).
+///
+/// Counts are per message, not per item: a trade message carrying five trades counts
+/// once. They cover both protocol versions and are cumulative for the client's lifetime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    pub trades: u64,
+    pub books: u64,
+    pub tickers: u64,
+    /// Heartbeats and pongs.
+    pub heartbeats: u64,
+    /// Parse failures and WebSocket errors, as in [`Metrics::errors`].
+    pub errors: u64,
+    /// Every other parsed message: status replies, private feeds, untyped channels.
+    pub other: u64,
+}
+
+impl ClientStats {
+    /// All parsed messages, i.e. every count except `errors`.
+    pub fn total(&self) -> u64 {
+        self.trades + self.books + self.tickers + self.heartbeats + self.other
+    }
+}
+
+/// Lock-free counters updated by the driver task.
+///
+/// With the `metrics` cargo feature, every recording is also forwarded to the
+/// [`metrics`](https://docs.rs/metrics) facade, so any installed recorder (such as a
+/// Prometheus exporter) sees:
+///
+/// - `kraken_messages_received_total{channel}`: parsed messages per channel or event type
+/// - `kraken_reconnects_total`
+/// - `kraken_parse_errors_total`
+/// - `kraken_checksum_mismatches_total`
+/// - `kraken_feed_latency_seconds`: histogram of exchange timestamp to arrival
+#[derive(Debug, Default)]
+pub(crate) struct MetricsRecorder {
+    messages: AtomicU64,
+    errors: AtomicU64,
+    parse_errors: AtomicU64,
+    reconnects: AtomicU64,
+    latency_ms: AtomicU64,
+    checksum_mismatches: AtomicU64,
+    trades: AtomicU64,
+    books: AtomicU64,
+    tickers: AtomicU64,
+    heartbeats: AtomicU64,
+    other: AtomicU64,
+    // Like `errors`, but never reset, for ClientStats
+    lifetime_errors: AtomicU64,
+}
+
+impl MetricsRecorder {
+    pub(crate) fn record_message(&self) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        self.lifetime_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A frame that could not be parsed; counted as an error too.
+    pub(crate) fn record_parse_error(&self) {
+        self.record_error();
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!("kraken_parse_errors_total").increment(1);
+    }
+
+    pub(crate) fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!("kraken_reconnects_total").increment(1);
+    }
+
+    pub(crate) fn record_checksum_mismatch(&self) {
+        self.checksum_mismatches.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!("kraken_checksum_mismatches_total").increment(1);
+    }
+
+    /// Records a successfully parsed event: the per-channel counter and the latency gauge.
+    pub(crate) fn record_event(&self, event: &KrakenEvent) {
+        self.channel_counter(event).fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        {
+            ::metrics::counter!("kraken_messages_received_total", "channel" => channel_label(event))
+                .increment(1);
+            if let Some(sent) = event.timestamp() {
+                if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+                    ::metrics::histogram!("kraken_feed_latency_seconds")
+                        .record((now.as_secs_f64() - sent).max(0.0));
+                }
+            }
+        }
+        self.record_latency(event);
+    }
+
+    /// The [`ClientStats`] counter `event` belongs to.
+    fn channel_counter(&self, event: &KrakenEvent) -> &AtomicU64 {
+        use crate::v2::{ChannelMessage, KrakenEventV2};
+        match event {
+            KrakenEvent::Channel(ChannelData::Trade(_))
+            | KrakenEvent::V2(KrakenEventV2::Channel(ChannelMessage::Trade { .. })) => &self.trades,
+            KrakenEvent::Channel(ChannelData::Book(_))
+            | KrakenEvent::V2(KrakenEventV2::Channel(ChannelMessage::Book { .. })) => &self.books,
+            KrakenEvent::Channel(ChannelData::Ticker(_))
+            | KrakenEvent::V2(KrakenEventV2::Channel(ChannelMessage::Ticker { .. })) => {
+                &self.tickers
+            }
+            KrakenEvent::Heartbeat(_)
+            | KrakenEvent::V2(KrakenEventV2::Channel(ChannelMessage::Heartbeat)) => {
+                &self.heartbeats
+            }
+            _ => &self.other,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> ClientStats {
+        ClientStats {
+            trades: self.trades.load(Ordering::Relaxed),
+            books: self.books.load(Ordering::Relaxed),
+            tickers: self.tickers.load(Ordering::Relaxed),
+            heartbeats: self.heartbeats.load(Ordering::Relaxed),
+            errors: self.lifetime_errors.load(Ordering::Relaxed),
+            other: self.other.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Updates the latency gauge from a trade event; other events are ignored.
+    fn record_latency(&self, event: &KrakenEvent) {
+        if let Some(latency) = trade_latency_ms(event) {
+            self.latency_ms.store(latency, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> Metrics {
+        Metrics {
+            messages: self.messages.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            latency_ms: self.latency_ms.load(Ordering::Relaxed),
+            checksum_mismatches: self.checksum_mismatches.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the current values and zeroes the counters. Gauges are left untouched.
+    ///
+    /// Each counter is swapped atomically, so no increment is ever lost between the
+    /// returned snapshot and the next one.
+    pub(crate) fn reset(&self) -> Metrics {
+        Metrics {
+            messages: self.messages.swap(0, Ordering::Relaxed),
+            errors: self.errors.swap(0, Ordering::Relaxed),
+            parse_errors: self.parse_errors.swap(0, Ordering::Relaxed),
+            reconnects: self.reconnects.swap(0, Ordering::Relaxed),
+            latency_ms: self.latency_ms.load(Ordering::Relaxed),
+            checksum_mismatches: self.checksum_mismatches.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Rolling feed latency: the delay between a message's exchange timestamp and its arrival.
+///
+/// Only trade and book messages carry a timestamp (see [`KrakenEvent::timestamp`]);
+/// everything else is ignored. The statistics cover the last `window` samples. Latency
+/// includes any clock offset between this machine and Kraken, so keep the clock synced.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use kraken_sdk::metrics::LatencyTracker;
+/// # async fn example(client: kraken_sdk::KrakenClient) {
+/// let mut rx = client.subscribe_events();
+/// let mut latency = LatencyTracker::new(1_000);
+/// while let Ok(event) = rx.recv().await {
+///     latency.record(&event);
+///     if let (Some(avg), Some(p99)) = (latency.average(), latency.percentile(99.0)) {
+///         println!("latency avg {:.1}ms p99 {:.1}ms", avg, p99);
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    samples: VecDeque<f64>,
+    window: usize,
+}
+
+impl LatencyTracker {
+    /// A tracker over the most recent `window` samples (at least one).
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window,
+        }
+    }
+
+    /// Records the latency of `event` and returns it in milliseconds, or `None` if the
+    /// event has no timestamp.
+    pub fn record(&mut self, event: &KrakenEvent) -> Option<f64> {
+        let sent = event.timestamp()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+        let latency_ms = ((now.as_secs_f64() - sent) * 1000.0).max(0.0);
+        self.record_ms(latency_ms);
+        Some(latency_ms)
+    }
+
+    /// Records a latency measured elsewhere, in milliseconds.
+    pub fn record_ms(&mut self, latency_ms: f64) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency_ms);
+    }
+
+    /// The most recent sample, in milliseconds.
+    pub fn last(&self) -> Option<f64> {
+        self.samples.back().copied()
+    }
+
+    /// Mean latency over the window, in milliseconds.
+    pub fn average(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f64>() / self.samples.len() as f64)
+    }
+
+    /// The `pct`-th percentile (nearest rank, `0.0..=100.0`) over the window, in milliseconds.
+    pub fn percentile(&self, pct: f64) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (pct.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Label for the per-channel message counter.
+#[cfg(feature = "metrics")]
+fn channel_label(event: &KrakenEvent) -> String {
+    use crate::v2::{ChannelMessage, KrakenEventV2};
+    let label = match event {
+        KrakenEvent::Heartbeat(heartbeat) => return heartbeat.event.clone(),
+        KrakenEvent::SystemStatus(_) => "systemStatus",
+        KrakenEvent::SubscriptionStatus(_) => "subscriptionStatus",
+        KrakenEvent::AddOrderStatus(_) => "addOrderStatus",
+        KrakenEvent::CancelOrderStatus(_) => "cancelOrderStatus",
+        KrakenEvent::CancelAllStatus(_) => "cancelAllStatus",
+        KrakenEvent::CancelAllOrdersAfterStatus(_) => "cancelAllOrdersAfterStatus",
+        KrakenEvent::Channel(data) => match data {
+            ChannelData::Trade(_) => "trade",
+            ChannelData::Book(_) => "book",
+            ChannelData::Ticker(_) => "ticker",
+            ChannelData::OwnTrades(_) => "ownTrades",
+            ChannelData::OpenOrders(_) => "openOrders",
+        },
+        // [channel_id, data..., channel_name, pair]; private feeds end in the channel name
+        KrakenEvent::Data(vec) => {
+            let name = vec
+                .iter()
+                .rev()
+                .take(2)
+                .filter_map(|v| v.as_str())
+                .find(|s| !s.contains('/'));
+            // Strip the depth so "book-10" and "book-25" share a series
+            return name
+                .map(|n| n.split('-').next().unwrap_or(n).to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+        }
+        KrakenEvent::Unknown(_) => "unknown",
+        KrakenEvent::V2(KrakenEventV2::Channel(message)) => match message {
+            ChannelMessage::Heartbeat => "heartbeat",
+            ChannelMessage::Status { .. } => "status",
+            ChannelMessage::Trade { .. } => "trade",
+            ChannelMessage::Book { .. } => "book",
+            ChannelMessage::Ticker { .. } => "ticker",
+            ChannelMessage::Unknown => "unknown",
+        },
+        KrakenEvent::V2(KrakenEventV2::Method(_)) => "method",
+        _ => "other",
+    };
+    label.to_string()
+}
+
+// Measured against the newest trade in the message
+fn trade_latency_ms(event: &KrakenEvent) -> Option<u64> {
+    let KrakenEvent::Channel(ChannelData::Trade(data)) = event else {
+        return None;
+    };
+    let time = data.data.last()?.time_f64().ok()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    Some(now.saturating_sub((time * 1000.0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_count_messages_per_channel() {
+        let recorder = MetricsRecorder::default();
+        for frame in [
+            r#"{"event":"heartbeat"}"#,
+            r#"{"event":"pong"}"#,
+            r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","XBT/USD"]"#,
+            r#"[1,{"a":[["5541.3","2.5","1534614248.4"]]},"book-10","XBT/USD"]"#,
+            r#"[0,[["5698.4","5700.0","1542057299.5","1.0","0.9"]],"spread","XBT/USD"]"#,
+        ] {
+            recorder.record_event(&serde_json::from_str(frame).unwrap());
+        }
+        recorder.record_parse_error();
+
+        let stats = recorder.stats();
+        assert_eq!(
+            stats,
+            ClientStats {
+                trades: 1,
+                books: 1,
+                tickers: 0,
+                heartbeats: 2,
+                errors: 1,
+                other: 1,
+            }
+        );
+        assert_eq!(stats.total(), 5);
+        assert_eq!(recorder.reset().errors, 1);
+        assert_eq!(recorder.stats(), stats);
+    }
+
+    #[test]
+    fn test_latency_tracker_ignores_untimestamped_events() {
+        let mut tracker = LatencyTracker::new(3);
+        let heartbeat: KrakenEvent = serde_json::from_str(r#"{"event":"heartbeat"}"#).unwrap();
+        assert_eq!(tracker.record(&heartbeat), None);
+        assert!(tracker.is_empty());
+
+        let book: KrakenEvent = serde_json::from_str(
+            r#"[336, {"a": [["5541.3", "2.5", "1534614248.456738"]]}, {"b": [["5541.2", "1.5", "1534614248.123678"]]}, "book-10", "XBT/USD"]"#,
+        )
+        .unwrap();
+        assert_eq!(book.timestamp(), Some(1534614248.456738));
+        assert!(tracker.record(&book).unwrap() > 0.0);
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn test_latency_tracker_statistics() {
+        let mut tracker = LatencyTracker::new(4);
+        assert_eq!(tracker.average(), None);
+        assert_eq!(tracker.percentile(50.0), None);
+
+        for ms in [100.0, 10.0, 20.0, 30.0, 40.0] {
+            tracker.record_ms(ms);
+        }
+        // The 100ms sample has left the window
+        assert_eq!(tracker.len(), 4);
+        assert_eq!(tracker.last(), Some(40.0));
+        assert_eq!(tracker.average(), Some(25.0));
+        assert_eq!(tracker.percentile(50.0), Some(20.0));
+        assert_eq!(tracker.percentile(99.0), Some(40.0));
+        assert_eq!(tracker.percentile(0.0), Some(10.0));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_channel_labels() {
+        let label = |raw: &str| channel_label(&serde_json::from_str(raw).unwrap());
+        assert_eq!(label(r#"{"event":"heartbeat"}"#), "heartbeat");
+        assert_eq!(label(r#"[0, [], "trade", "XBT/USD"]"#), "trade");
+        assert_eq!(
+            label(r#"[0, {"as": [], "bs": []}, "book-25", "XBT/USD"]"#),
+            "book"
+        );
+        assert_eq!(label(r#"[[], "ownTrades", {"sequence": 1}]"#), "ownTrades");
+    }
+
+    #[test]
+    fn test_reset_returns_counts_and_zeroes_counters() {
+        let recorder = MetricsRecorder::default();
+        for _ in 0..3 {
+            recorder.record_message();
+        }
+        recorder.record_error();
+        recorder.record_reconnect();
+
+        let trade: KrakenEvent = serde_json::from_str(
+            r#"[0, [["50000.0", "1.0", "1.0", "b", "m", ""]], "trade", "XBT/USD"]"#,
+        )
+        .unwrap();
+        recorder.record_event(&trade);
+        let latency = recorder.snapshot().latency_ms;
+        assert!(latency > 0);
+
+        let snapshot = recorder.reset();
+        assert_eq!(snapshot.messages, 3);
+        assert_eq!(snapshot.errors, 1);
+        assert_eq!(snapshot.reconnects, 1);
+
+        let after = recorder.snapshot();
+        assert_eq!(after.messages, 0);
+        assert_eq!(after.errors, 0);
+        assert_eq!(after.reconnects, 0);
+        assert_eq!(after.latency_ms, latency);
+    }
+}
@@ -1,48 +1,383 @@
+use crate::v2::KrakenEventV2;
 use crc32fast::Hasher;
-use serde::{Deserialize, Deserializer};
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use std::collections::BTreeMap;
+use std::num::ParseFloatError;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(untagged)]
+#[derive(Debug, Clone, Serialize)]
 pub enum KrakenEvent {
     Heartbeat(Heartbeat),
     SystemStatus(SystemStatus),
     SubscriptionStatus(SubscriptionStatus),
-    Data(Vec<Value>), // Fallback for data arrays: [channelID, data, channelName, pair]
+    AddOrderStatus(AddOrderStatus),
+    CancelOrderStatus(CancelOrderStatus),
+    CancelAllStatus(CancelAllStatus),
+    CancelAllOrdersAfterStatus(CancelAllOrdersAfterStatus),
+    /// A data frame, already parsed according to its channel name.
+    Channel(ChannelData),
+    /// Raw fallback for data frames of channels without a typed form (e.g. `spread`,
+    /// `ohlc-1`) and for frames that do not match their channel's expected shape.
+    Data(Vec<Value>),
+    /// A valid JSON message this crate does not recognize, e.g. a new event type or a
+    /// frame from an unknown channel, kept whole for forward compatibility.
+    ///
+    /// Unlike [`ParseError`](Self::ParseError), the message was well-formed; it just
+    /// has no variant yet. The client logs these at debug level.
+    Unknown(Value),
+    /// Emitted by the client after a [`SystemStatus`] whose status differs from the
+    /// previous one (`from` is `None` for the first status of the session). Trading
+    /// logic should halt when `to` is `"maintenance"` or `"cancel_only"`; parse it with
+    /// [`SystemState::from`] to match exhaustively.
+    SystemStatusChanged {
+        from: Option<String>,
+        to: String,
+    },
+    /// Emitted when a local book failed checksum validation and a fresh snapshot has been
+    /// requested, either by [`BookMaintainer`](crate::book::BookMaintainer) or by the client
+    /// itself with [`manage_order_books`](crate::KrakenClientBuilder::manage_order_books)
+    /// and [`auto_validate_checksums`](crate::KrakenClientBuilder::auto_validate_checksums)
+    /// enabled.
+    ChecksumMismatch {
+        pair: String,
+    },
+    /// Emitted by the client when a trade closes a candle, with
+    /// [`aggregate_trades`](crate::KrakenClientBuilder::aggregate_trades) enabled.
+    CandleClosed {
+        pair: String,
+        candle: Candle,
+    },
+    /// Emitted by the client when the connection dropped because Kraken throttled it
+    /// (a rate-limit close reason or `errorMessage`, or an HTTP 429 on connect). The
+    /// driver waits `retry_in`, see
+    /// [`rate_limit_backoff`](crate::KrakenClientBuilder::rate_limit_backoff), instead of
+    /// the usual short delay before reconnecting.
+    RateLimited {
+        reason: String,
+        retry_in: Duration,
+    },
+    /// A frame that could not be parsed, with the raw text and the parser's message.
+    ///
+    /// Only broadcast when [`surface_parse_errors`](crate::KrakenClientBuilder::surface_parse_errors)
+    /// is enabled; otherwise such frames are logged and counted in
+    /// [`Metrics::parse_errors`](crate::metrics::Metrics::parse_errors).
+    ParseError {
+        raw: String,
+        error: String,
+    },
+    /// Emitted by the client when its connection comes up, drops or comes back.
+    ConnectionState(ConnectionState),
+    /// A message from the v2 API, when the client uses [`ProtocolVersion::V2`](crate::builder::ProtocolVersion::V2).
+    V2(KrakenEventV2),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Connection lifecycle, broadcast as [`KrakenEvent::ConnectionState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ConnectionState {
+    /// The first connection is up and subscriptions made before it have been sent.
+    Connected,
+    /// The connection dropped; the driver is about to reconnect. Local state derived from
+    /// the feed (e.g. order books) is stale from here on; clear it or let
+    /// [`BookMaintainer::process`](crate::book::BookMaintainer::process) do so. Managed
+    /// books are reset by the client before this is broadcast.
+    Reconnecting,
+    /// A new connection is up and subscriptions have been replayed according to the
+    /// [`ReplayStrategy`](crate::ReplayStrategy).
+    Reconnected,
+    /// A connection attempt failed and will be retried (`permanent: false`), or the driver
+    /// has stopped for good (`permanent: true`), e.g. after
+    /// [`max_reconnect_attempts`](crate::KrakenClientBuilder::max_reconnect_attempts).
+    Disconnected { permanent: bool },
+}
+
+// Objects are routed on their "event" field. An untagged enum cannot do this: every
+// status object also matches `Heartbeat { event }`, so the first variant always won.
+// The visitor also avoids buffering the whole frame into a `Value` first: arrays are
+// read straight into their elements, and heartbeats are never materialized. Any other
+// well-formed message ends up in `Unknown`.
+impl<'de> Deserialize<'de> for KrakenEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(KrakenEventVisitor)
+    }
+}
+
+struct KrakenEventVisitor;
+
+impl<'de> de::Visitor<'de> for KrakenEventVisitor {
+    type Value = KrakenEvent;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a JSON message")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<KrakenEvent, E> {
+        Ok(KrakenEvent::Unknown(Value::Bool(v)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<KrakenEvent, E> {
+        Ok(KrakenEvent::Unknown(Value::from(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<KrakenEvent, E> {
+        Ok(KrakenEvent::Unknown(Value::from(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<KrakenEvent, E> {
+        Ok(KrakenEvent::Unknown(Value::from(v)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<KrakenEvent, E> {
+        Ok(KrakenEvent::Unknown(Value::from(v)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<KrakenEvent, E> {
+        Ok(KrakenEvent::Unknown(Value::Null))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<KrakenEvent, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(4));
+        while let Some(element) = seq.next_element::<Value>()? {
+            vec.push(element);
+        }
+        Ok(match parse_channel_data(&vec) {
+            Some(data) => KrakenEvent::Channel(data),
+            None if is_known_channel(&vec) => KrakenEvent::Data(vec),
+            None => KrakenEvent::Unknown(Value::Array(vec)),
+        })
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<KrakenEvent, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        // Fields are only kept while the event might need them; "event" is usually first
+        let mut fields = serde_json::Map::new();
+        let mut event: Option<String> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "event" {
+                event = Some(map.next_value()?);
+            } else if event.as_deref().is_some_and(is_heartbeat) {
+                map.next_value::<de::IgnoredAny>()?;
+            } else {
+                fields.insert(key, map.next_value()?);
+            }
+        }
+        let Some(name) = event else {
+            return Ok(KrakenEvent::Unknown(Value::Object(fields)));
+        };
+        if is_heartbeat(&name) {
+            return Ok(KrakenEvent::Heartbeat(Heartbeat { event: name }));
+        }
+        fields.insert("event".to_string(), Value::String(name.clone()));
+        let value = Value::Object(fields);
+        if !has_event_variant(&name) {
+            return Ok(KrakenEvent::Unknown(value));
+        }
+        match name.as_str() {
+            "systemStatus" => serde_json::from_value(value).map(KrakenEvent::SystemStatus),
+            "subscriptionStatus" => {
+                serde_json::from_value(value).map(KrakenEvent::SubscriptionStatus)
+            }
+            "cancelOrderStatus" => {
+                serde_json::from_value(value).map(KrakenEvent::CancelOrderStatus)
+            }
+            "cancelAllStatus" => serde_json::from_value(value).map(KrakenEvent::CancelAllStatus),
+            "cancelAllOrdersAfterStatus" => {
+                serde_json::from_value(value).map(KrakenEvent::CancelAllOrdersAfterStatus)
+            }
+            _ => serde_json::from_value(value).map(KrakenEvent::AddOrderStatus),
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+/// Keep-alive events, parsed as [`Heartbeat`] without their other fields.
+fn is_heartbeat(name: &str) -> bool {
+    matches!(name, "heartbeat" | "pong")
+}
+
+/// Whether an object with this `event` name has its own [`KrakenEvent`] variant.
+fn has_event_variant(name: &str) -> bool {
+    matches!(
+        name,
+        "systemStatus"
+            | "subscriptionStatus"
+            | "addOrderStatus"
+            | "cancelOrderStatus"
+            | "cancelAllStatus"
+            | "cancelAllOrdersAfterStatus"
+    )
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Heartbeat {
     pub event: String, // "heartbeat"
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SystemStatus {
     pub event: String, // "systemStatus"
+    #[serde(rename = "connectionID")]
     pub connection_id: Option<u64>,
     pub status: String, // "online"
     pub version: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl SystemStatus {
+    /// The typed form of [`status`](Self::status).
+    pub fn state(&self) -> SystemState {
+        SystemState::from(self.status.as_str())
+    }
+}
+
+/// The trading state Kraken reports in a `systemStatus` message.
+///
+/// Statuses this crate does not know yet are kept as `Unknown` rather than failing to
+/// parse, so match on the states you care about and treat the rest conservatively.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "String")]
+pub enum SystemState {
+    Online,
+    Maintenance,
+    CancelOnly,
+    PostOnly,
+    LimitOnly,
+    Unknown(String),
+}
+
+impl SystemState {
+    /// Kraken's spelling, e.g. `"cancel_only"`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            SystemState::Online => "online",
+            SystemState::Maintenance => "maintenance",
+            SystemState::CancelOnly => "cancel_only",
+            SystemState::PostOnly => "post_only",
+            SystemState::LimitOnly => "limit_only",
+            SystemState::Unknown(status) => status,
+        }
+    }
+
+    /// Whether new orders are accepted at all. `PostOnly` and `LimitOnly` still restrict
+    /// which orders, so only `Online` means business as usual.
+    pub fn accepts_orders(&self) -> bool {
+        matches!(
+            self,
+            SystemState::Online | SystemState::PostOnly | SystemState::LimitOnly
+        )
+    }
+}
+
+impl From<&str> for SystemState {
+    fn from(status: &str) -> Self {
+        match status {
+            "online" => SystemState::Online,
+            "maintenance" => SystemState::Maintenance,
+            "cancel_only" => SystemState::CancelOnly,
+            "post_only" => SystemState::PostOnly,
+            "limit_only" => SystemState::LimitOnly,
+            other => SystemState::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for SystemState {
+    fn from(status: String) -> Self {
+        SystemState::from(status.as_str())
+    }
+}
+
+impl std::fmt::Display for SystemState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SubscriptionStatus {
     pub event: String,          // "subscriptionStatus"
     pub status: Option<String>, // "subscribed" or "error"
     pub pair: Option<String>,
+    #[serde(rename = "channelName")]
     pub channel_name: Option<String>,
+    /// The id data frames of this subscription are keyed by (absent on errors).
+    #[serde(rename = "channelID")]
+    pub channel_id: Option<u64>,
     pub subscription: Option<SubscriptionInfo>,
+    #[serde(rename = "errorMessage")]
     pub error_message: Option<String>,
+    pub reqid: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Reply to an `addOrder` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AddOrderStatus {
+    pub event: String,  // "addOrderStatus"
+    pub status: String, // "ok" or "error"
+    pub txid: Option<String>,
+    pub descr: Option<String>,
+    #[serde(rename = "errorMessage")]
+    pub error_message: Option<String>,
+    pub reqid: Option<u64>,
+}
+
+/// Reply to a `cancelOrder` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CancelOrderStatus {
+    pub event: String,  // "cancelOrderStatus"
+    pub status: String, // "ok" or "error"
+    #[serde(rename = "errorMessage")]
+    pub error_message: Option<String>,
+    pub reqid: Option<u64>,
+}
+
+/// Reply to a `cancelAll` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CancelAllStatus {
+    pub event: String,  // "cancelAllStatus"
+    pub status: String, // "ok" or "error"
+    /// Number of orders cancelled.
+    pub count: Option<u64>,
+    #[serde(rename = "errorMessage")]
+    pub error_message: Option<String>,
+    pub reqid: Option<u64>,
+}
+
+/// Reply to a `cancelAllOrdersAfter` request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CancelAllOrdersAfterStatus {
+    pub event: String,  // "cancelAllOrdersAfterStatus"
+    pub status: String, // "ok" or "error"
+    #[serde(rename = "currentTime")]
+    pub current_time: Option<String>,
+    /// When open orders will be cancelled unless the timer is renewed; `"0"` once disabled.
+    #[serde(rename = "triggerTime")]
+    pub trigger_time: Option<String>,
+    #[serde(rename = "errorMessage")]
+    pub error_message: Option<String>,
+    pub reqid: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SubscriptionInfo {
     pub name: String,
 }
 
 // --- Typed Data Structures ---
+//
+// These serialize as plain objects named after their fields, not back into
+// Kraken's positional arrays, so they can be logged or forwarded as-is.
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TradeData {
     pub channel_id: u64,
     pub data: Vec<Trade>,
@@ -50,17 +385,166 @@ pub struct TradeData {
     pub pair: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Trade {
     pub price: String,
     pub volume: String,
     pub time: String,
     pub side: String,       // "b" or "s"
     pub order_type: String, // "m" or "l"
-    pub misc: String,
+    /// Kraken's "miscellaneous" field, kept verbatim. Kraken documents no flags for it and
+    /// sends an empty string for regular trades, which becomes `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub misc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trade_id: Option<u64>, // Sequential per pair, only present in the extended array
 }
 
-#[derive(Debug, Clone)]
+/// A trade frame whose strings borrow from the frame text, see [`parse_borrowed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeDataRef<'a> {
+    pub channel_id: u64,
+    pub data: Vec<TradeRef<'a>>,
+    pub channel_name: &'a str,
+    pub pair: &'a str,
+}
+
+/// A [`Trade`] that borrows its fields instead of owning them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TradeRef<'a> {
+    pub price: &'a str,
+    pub volume: &'a str,
+    pub time: &'a str,
+    pub side: &'a str,
+    pub order_type: &'a str,
+    /// `None` when Kraken sent an empty string, as in [`Trade::misc`].
+    pub misc: Option<&'a str>,
+    pub trade_id: Option<u64>,
+}
+
+impl TradeRef<'_> {
+    /// The taker side, parsed from the `"b"`/`"s"` code in [`side`](Self::side).
+    pub fn taker_side(&self) -> Option<Side> {
+        match self.side {
+            "b" => Some(Side::Buy),
+            "s" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    /// Copies the fields into an owned [`Trade`], e.g. to keep a trade past the frame.
+    pub fn to_trade(&self) -> Trade {
+        Trade {
+            price: self.price.to_string(),
+            volume: self.volume.to_string(),
+            time: self.time.to_string(),
+            side: self.side.to_string(),
+            order_type: self.order_type.to_string(),
+            misc: self.misc.map(str::to_string),
+            trade_id: self.trade_id,
+        }
+    }
+}
+
+/// Parses a v1 `trade` frame without allocating a `String` per field.
+///
+/// A faster alternative to going through [`KrakenEvent`] for consumers that only look at
+/// each trade once. Anything other than a trade frame is an error, so fall back to the
+/// owned path for the rest of the feed. Unlike [`Trade`], fields must be JSON strings
+/// without escape sequences (which Kraken never sends in trades), since those cannot be
+/// borrowed.
+///
+/// ```rust
+/// use kraken_sdk::models::parse_borrowed;
+///
+/// let frame = r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","XBT/USD"]"#;
+/// let trades = parse_borrowed(frame).unwrap();
+/// assert_eq!(trades.pair, "XBT/USD");
+/// assert_eq!(trades.data[0].price, "5541.2");
+/// ```
+pub fn parse_borrowed(text: &str) -> serde_json::Result<TradeDataRef<'_>> {
+    let (channel_id, data, channel_name, pair): (u64, Vec<TradeRef>, &str, &str) =
+        serde_json::from_str(text)?;
+    if channel_name != "trade" {
+        return Err(de::Error::custom(format!(
+            "expected a trade frame, got channel {:?}",
+            channel_name
+        )));
+    }
+    Ok(TradeDataRef {
+        channel_id,
+        data,
+        channel_name,
+        pair,
+    })
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for TradeRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(TradeRefVisitor(std::marker::PhantomData))
+    }
+}
+
+struct TradeRefVisitor<'a>(std::marker::PhantomData<&'a ()>);
+
+impl<'de: 'a, 'a> de::Visitor<'de> for TradeRefVisitor<'a> {
+    type Value = TradeRef<'a>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a trade array")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<TradeRef<'a>, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut fields = [""; 6];
+        for (i, field) in fields.iter_mut().enumerate() {
+            *field = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        // The trade id may arrive as a number or a numeric string
+        let trade_id = seq
+            .next_element::<Value>()?
+            .and_then(|x| x.as_u64().or_else(|| x.as_str()?.parse().ok()));
+        while seq.next_element::<de::IgnoredAny>()?.is_some() {}
+        let [price, volume, time, side, order_type, misc] = fields;
+        Ok(TradeRef {
+            price,
+            volume,
+            time,
+            side,
+            order_type,
+            misc: Some(misc).filter(|misc| !misc.is_empty()),
+            trade_id,
+        })
+    }
+}
+
+/// Which side of the book the taker of a trade was on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Trade {
+    /// The taker side, parsed from the `"b"`/`"s"` code in [`side`](Self::side).
+    pub fn taker_side(&self) -> Option<Side> {
+        match self.side.as_str() {
+            "b" => Some(Side::Buy),
+            "s" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct OrderBookData {
     pub channel_id: u64,
     pub asks: Vec<OrderBookEntry>,
@@ -71,26 +555,392 @@ pub struct OrderBookData {
     pub checksum: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// A one-line summary such as `SNAPSHOT XBT/USD: 10 asks / 10 bids`.
+impl std::fmt::Display for OrderBookData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.is_snapshot {
+            "SNAPSHOT"
+        } else {
+            "UPDATE"
+        };
+        write!(
+            f,
+            "{} {}: {} asks / {} bids",
+            kind,
+            self.pair,
+            self.asks.len(),
+            self.bids.len()
+        )
+    }
+}
+
+impl OrderBookData {
+    /// The `n` lowest asks as `(price, volume)`, best first.
+    ///
+    /// Most useful on a snapshot: an update only carries the levels that changed
+    /// (including deletions with volume `0`), so for the current top of book apply updates
+    /// to a [`LocalOrderBook`] instead. Levels that fail to parse are skipped.
+    pub fn sorted_asks(&self, n: usize) -> Vec<(f64, f64)> {
+        let mut levels = parse_levels(&self.asks);
+        levels.sort_by(|a, b| a.0.total_cmp(&b.0));
+        levels.truncate(n);
+        levels
+    }
+
+    /// The `n` highest bids as `(price, volume)`, best first. See
+    /// [`sorted_asks`](Self::sorted_asks) for when this is meaningful.
+    pub fn sorted_bids(&self, n: usize) -> Vec<(f64, f64)> {
+        let mut levels = parse_levels(&self.bids);
+        levels.sort_by(|a, b| b.0.total_cmp(&a.0));
+        levels.truncate(n);
+        levels
+    }
+}
+
+fn parse_levels(entries: &[OrderBookEntry]) -> Vec<(f64, f64)> {
+    entries
+        .iter()
+        .filter_map(|e| Some((e.price_f64().ok()?, e.volume_f64().ok()?)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct OrderBookEntry {
     pub price: String,
     pub volume: String,
     pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_type: Option<String>, // "r" for a republished level (updates only)
 }
 
-// Custom deserializer for OrderBookEntry: ["price", "volume", "timestamp"]
+impl OrderBookEntry {
+    /// Parses the price level. The raw string is kept for checksum calculation.
+    pub fn price_f64(&self) -> Result<f64, ParseFloatError> {
+        self.price.parse()
+    }
+
+    /// Parses the volume at this level (`0` means the level was removed).
+    pub fn volume_f64(&self) -> Result<f64, ParseFloatError> {
+        self.volume.parse()
+    }
+
+    /// Whether this update removes the level, i.e. its volume is zero in any spelling
+    /// (`"0"`, `"0.0"`, `"0.000000"`, ...).
+    pub fn is_deletion(&self) -> bool {
+        matches!(self.volume_f64(), Ok(volume) if volume == 0.0)
+    }
+
+    /// Parses the price level as an exact decimal.
+    #[cfg(feature = "decimal")]
+    pub fn price_decimal(&self) -> Result<Decimal, rust_decimal::Error> {
+        self.price.parse()
+    }
+
+    /// Parses the volume at this level as an exact decimal.
+    #[cfg(feature = "decimal")]
+    pub fn volume_decimal(&self) -> Result<Decimal, rust_decimal::Error> {
+        self.volume.parse()
+    }
+}
+
+// Custom deserializer for OrderBookEntry: ["price", "volume", "timestamp", "update_type"?]
 impl<'de> Deserialize<'de> for OrderBookEntry {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
         let v: Vec<String> = Deserialize::deserialize(deserializer)?;
+        if v.len() < 3 {
+            return Err(de::Error::invalid_length(
+                v.len(),
+                &"a price, volume and timestamp",
+            ));
+        }
+        let mut fields = v.into_iter();
         Ok(OrderBookEntry {
-            price: v.first().cloned().unwrap_or_default(),
-            volume: v.get(1).cloned().unwrap_or_default(),
-            timestamp: v.get(2).cloned().unwrap_or_default(),
+            price: fields.next().unwrap_or_default(),
+            volume: fields.next().unwrap_or_default(),
+            timestamp: fields.next().unwrap_or_default(),
+            update_type: fields.next(),
+        })
+    }
+}
+
+/// Ticker fields as Kraken sends them. Each is a list of decimal strings, e.g. `ask` is
+/// `[price, whole lot volume, lot volume]` and `volume` is `[today, last 24 hours]`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Ticker {
+    #[serde(rename = "a", deserialize_with = "lenient_strings")]
+    pub ask: Vec<String>,
+    #[serde(rename = "b", deserialize_with = "lenient_strings")]
+    pub bid: Vec<String>,
+    /// Last trade: `[price, lot volume]`.
+    #[serde(rename = "c")]
+    pub close: Vec<String>,
+    #[serde(rename = "v")]
+    pub volume: Vec<String>,
+    #[serde(rename = "p")]
+    pub vwap: Vec<String>,
+    /// Number of trades: `[today, last 24 hours]`.
+    #[serde(rename = "t")]
+    pub trades: Vec<u64>,
+    #[serde(rename = "l")]
+    pub low: Vec<String>,
+    #[serde(rename = "h")]
+    pub high: Vec<String>,
+    #[serde(rename = "o")]
+    pub open: Vec<String>,
+}
+
+impl Ticker {
+    pub fn ask_price(&self) -> Option<f64> {
+        self.ask.first()?.parse().ok()
+    }
+
+    pub fn bid_price(&self) -> Option<f64> {
+        self.bid.first()?.parse().ok()
+    }
+
+    /// Price of the last trade.
+    pub fn last_price(&self) -> Option<f64> {
+        self.close.first()?.parse().ok()
+    }
+}
+
+// The whole lot volume in "a"/"b" is sent as a bare integer
+fn lenient_strings<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|value| match value {
+            Value::String(s) => Ok(s),
+            Value::Number(n) => Ok(n.to_string()),
+            other => Err(de::Error::custom(format!(
+                "expected a string or number, got {}",
+                other
+            ))),
         })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TickerData {
+    pub channel_id: u64,
+    pub ticker: Ticker,
+    pub channel_name: String,
+    pub pair: String,
+}
+
+// --- Private Channels ---
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnTradesData {
+    pub trades: Vec<OwnTrade>,
+    pub sequence: Option<u64>,
+}
+
+/// One of your own fills, from the `ownTrades` channel.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OwnTrade {
+    #[serde(skip_deserializing)]
+    pub trade_id: String, // Key of the trade object
+    #[serde(rename = "ordertxid")]
+    pub order_id: String,
+    pub pair: String,
+    pub price: String,
+    pub vol: String,
+    pub cost: String,
+    pub fee: String,
+    pub time: String,
+    #[serde(rename = "type")]
+    pub side: String, // "buy" or "sell"
+    #[serde(rename = "ordertype")]
+    pub order_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenOrdersData {
+    pub orders: Vec<OpenOrder>,
+    pub sequence: Option<u64>,
+}
+
+/// An order from the `openOrders` channel. The snapshot carries every field; later
+/// updates only carry what changed (usually `status`, or `vol_exec` and `cost`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OpenOrder {
+    #[serde(skip_deserializing)]
+    pub order_id: String, // Key of the order object
+    pub status: Option<String>, // "pending", "open", "closed", "canceled", "expired"
+    pub vol: Option<String>,
+    pub vol_exec: Option<String>,
+    pub cost: Option<String>,
+    pub fee: Option<String>,
+    pub descr: Option<OpenOrderDescription>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OpenOrderDescription {
+    pub pair: String,
+    #[serde(rename = "type")]
+    pub side: String,
+    #[serde(rename = "ordertype")]
+    pub order_type: String,
+    pub price: String,
+}
+
+/// A data frame, classified by channel when it is parsed.
+///
+/// Match on this instead of trying each `try_into_*` conversion in turn.
+#[derive(Debug, Clone, Serialize)]
+pub enum ChannelData {
+    Trade(TradeData),
+    Book(OrderBookData),
+    Ticker(TickerData),
+    OwnTrades(OwnTradesData),
+    OpenOrders(OpenOrdersData),
+}
+
+/// Whether a data frame belongs to a channel Kraken documents, typed or not.
+fn is_known_channel(vec: &[Value]) -> bool {
+    let name = vec
+        .len()
+        .checked_sub(2)
+        .and_then(|i| vec[i].as_str())
+        .unwrap_or_default();
+    matches!(
+        name,
+        "trade" | "ticker" | "spread" | "ownTrades" | "openOrders"
+    ) || name.starts_with("book")
+        || name.starts_with("ohlc")
+}
+
+// Public frames are [channel_id, data..., channel_name, pair] and private ones
+// [data, channel_name, {"sequence": n}], so the channel name is second to last in both.
+fn parse_channel_data(vec: &[Value]) -> Option<ChannelData> {
+    let name = vec.get(vec.len().checked_sub(2)?)?.as_str()?;
+    match name {
+        "trade" => parse_trade_frame(vec).map(ChannelData::Trade),
+        "ticker" => parse_ticker_frame(vec).map(ChannelData::Ticker),
+        "ownTrades" => parse_own_trades_frame(vec).map(ChannelData::OwnTrades),
+        "openOrders" => parse_open_orders_frame(vec).map(ChannelData::OpenOrders),
+        name if name.starts_with("book") => parse_book_frame(vec).map(ChannelData::Book),
+        _ => None,
+    }
+}
+
+// [channel_id, [[trade...], ...], "trade", pair]
+fn parse_trade_frame(vec: &[Value]) -> Option<TradeData> {
+    let [channel_id, trades, channel_name, pair] = vec else {
+        return None;
+    };
+    if channel_name.as_str()? != "trade" {
+        return None;
+    }
+    Some(TradeData {
+        channel_id: channel_id.as_u64()?,
+        data: Vec::<Trade>::deserialize(trades).ok()?,
+        channel_name: "trade".to_string(),
+        pair: pair.as_str()?.to_string(),
+    })
+}
+
+// [channel_id, {"a": [...], "b": [...], ...}, "ticker", pair]
+fn parse_ticker_frame(vec: &[Value]) -> Option<TickerData> {
+    let [channel_id, ticker, channel_name, pair] = vec else {
+        return None;
+    };
+    Some(TickerData {
+        channel_id: channel_id.as_u64()?,
+        ticker: Ticker::deserialize(ticker).ok()?,
+        channel_name: channel_name.as_str()?.to_string(),
+        pair: pair.as_str()?.to_string(),
+    })
+}
+
+// [channel_id, one or two data objects, "book-N", pair]
+fn parse_book_frame(vec: &[Value]) -> Option<OrderBookData> {
+    if !(4..=5).contains(&vec.len()) {
+        return None;
+    }
+    let (pair, rest) = vec.split_last()?;
+    let (channel_name, rest) = rest.split_last()?;
+    let (channel_id, sides) = rest.split_first()?;
+    let channel_name = channel_name.as_str()?;
+    if !channel_name.starts_with("book") {
+        return None;
+    }
+
+    let mut book = OrderBookData {
+        channel_id: channel_id.as_u64()?,
+        asks: Vec::new(),
+        bids: Vec::new(),
+        is_snapshot: false,
+        channel_name: channel_name.to_string(),
+        pair: pair.as_str()?.to_string(),
+        checksum: None,
+    };
+    for side in sides {
+        for (key, value) in side.as_object()? {
+            match key.as_str() {
+                "as" | "a" => {
+                    book.is_snapshot |= key == "as";
+                    book.asks
+                        .extend(Vec::<OrderBookEntry>::deserialize(value).ok()?);
+                }
+                "bs" | "b" => {
+                    book.is_snapshot |= key == "bs";
+                    book.bids
+                        .extend(Vec::<OrderBookEntry>::deserialize(value).ok()?);
+                }
+                "c" => book.checksum = Some(value.as_str()?.to_string()),
+                _ => {}
+            }
+        }
+    }
+    Some(book)
+}
+
+// [[{trade_id: {...}}, ...], "ownTrades", {"sequence": n}]
+fn parse_own_trades_frame(vec: &[Value]) -> Option<OwnTradesData> {
+    let (payload, sequence) = private_payload(vec, "ownTrades")?;
+    let trades = parse_keyed(payload, |trade: &mut OwnTrade, id| trade.trade_id = id)?;
+    Some(OwnTradesData { trades, sequence })
+}
+
+// [[{order_id: {...}}, ...], "openOrders", {"sequence": n}]
+fn parse_open_orders_frame(vec: &[Value]) -> Option<OpenOrdersData> {
+    let (payload, sequence) = private_payload(vec, "openOrders")?;
+    let orders = parse_keyed(payload, |order: &mut OpenOrder, id| order.order_id = id)?;
+    Some(OpenOrdersData { orders, sequence })
+}
+
+fn private_payload<'a>(vec: &'a [Value], channel: &str) -> Option<(&'a Value, Option<u64>)> {
+    if vec.len() >= 2 && vec[1].as_str() == Some(channel) {
+        let sequence = vec
+            .get(2)
+            .and_then(|meta| meta.get("sequence"))
+            .and_then(Value::as_u64);
+        return Some((&vec[0], sequence));
+    }
+    None
+}
+
+// Private payloads are lists of single-key objects: [{"<id>": {...}}, ...]
+fn parse_keyed<T, F>(value: &Value, mut set_id: F) -> Option<Vec<T>>
+where
+    T: serde::de::DeserializeOwned,
+    F: FnMut(&mut T, String),
+{
+    let entries = Vec::<serde_json::Map<String, Value>>::deserialize(value).ok()?;
+    let mut items = Vec::new();
+    for (id, fields) in entries.into_iter().flatten() {
+        let mut item: T = serde_json::from_value(fields).ok()?;
+        set_id(&mut item, id);
+        items.push(item);
     }
+    Some(items)
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -106,129 +956,223 @@ pub struct OrderBookRegion {
 }
 
 impl KrakenEvent {
+    /// The `reqid` echoed back by Kraken, if this is a reply to a request that carried one.
+    pub fn reqid(&self) -> Option<u64> {
+        match self {
+            KrakenEvent::SubscriptionStatus(status) => status.reqid,
+            KrakenEvent::AddOrderStatus(status) => status.reqid,
+            KrakenEvent::CancelOrderStatus(status) => status.reqid,
+            KrakenEvent::CancelAllStatus(status) => status.reqid,
+            KrakenEvent::CancelAllOrdersAfterStatus(status) => status.reqid,
+            KrakenEvent::V2(KrakenEventV2::Method(response)) => response.req_id,
+            _ => None,
+        }
+    }
+
+    /// The pair a market data message or pair-level status refers to.
+    ///
+    /// For v2 messages this is the symbol of the first entry. Private feeds, order
+    /// replies and connection-level events return `None`.
+    pub fn pair(&self) -> Option<&str> {
+        use crate::v2::ChannelMessage;
+        match self {
+            KrakenEvent::Channel(ChannelData::Trade(data)) => Some(&data.pair),
+            KrakenEvent::Channel(ChannelData::Book(data)) => Some(&data.pair),
+            KrakenEvent::Channel(ChannelData::Ticker(data)) => Some(&data.pair),
+            KrakenEvent::SubscriptionStatus(status) => status.pair.as_deref(),
+            KrakenEvent::ChecksumMismatch { pair } => Some(pair),
+            KrakenEvent::CandleClosed { pair, .. } => Some(pair),
+            // Public frames end in the pair; private ones in a sequence object
+            KrakenEvent::Data(vec) => vec.last()?.as_str(),
+            KrakenEvent::V2(KrakenEventV2::Channel(message)) => match message {
+                ChannelMessage::Trade { data, .. } => data.first().map(|t| t.symbol.as_str()),
+                ChannelMessage::Book { data, .. } => data.first().map(|b| b.symbol.as_str()),
+                ChannelMessage::Ticker { data, .. } => data.first().map(|t| t.symbol.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The exchange timestamp (Unix seconds) carried by a trade or book message.
+    ///
+    /// For a message with several trades or levels this is the latest of their
+    /// timestamps. Events that carry no time of their own (heartbeats, status
+    /// messages, ticker updates) return `None`.
+    pub fn timestamp(&self) -> Option<f64> {
+        match self {
+            KrakenEvent::Channel(ChannelData::Trade(data)) => data
+                .data
+                .iter()
+                .filter_map(|trade| trade.time_f64().ok())
+                .reduce(f64::max),
+            KrakenEvent::Channel(ChannelData::Book(data)) => data
+                .asks
+                .iter()
+                .chain(&data.bids)
+                .filter_map(|entry| entry.timestamp.parse::<f64>().ok())
+                .reduce(f64::max),
+            _ => None,
+        }
+    }
+
+    /// The parsed data of a trade frame, or `None` for any other event.
     pub fn try_into_trade_data(self) -> Option<TradeData> {
-        if let KrakenEvent::Data(mut vec) = self {
-            // Check if it's a trade event (has "trade" string)
-            // Format: [channel_id, [[trade...], ...], "trade", pair]
-            if vec.len() >= 4 && vec[2].as_str() == Some("trade") {
-                let pair = vec.pop()?.as_str()?.to_string();
-                let _channel_name = vec.pop()?.as_str()?.to_string();
-                let trades_value = vec.pop()?;
-                let channel_id = vec.pop()?.as_u64()?;
-
-                let trades: Vec<Trade> = serde_json::from_value(trades_value).ok()?;
-
-                return Some(TradeData {
-                    channel_id,
-                    data: trades,
-                    channel_name: "trade".to_string(),
-                    pair,
-                });
-            }
+        match self {
+            KrakenEvent::Channel(ChannelData::Trade(data)) => Some(data),
+            KrakenEvent::Data(vec) => parse_trade_frame(&vec),
+            _ => None,
+        }
+    }
+
+    /// The parsed data of a ticker frame, or `None` for any other event.
+    pub fn try_into_ticker_data(self) -> Option<TickerData> {
+        match self {
+            KrakenEvent::Channel(ChannelData::Ticker(data)) => Some(data),
+            KrakenEvent::Data(vec) => parse_ticker_frame(&vec),
+            _ => None,
+        }
+    }
+
+    pub fn try_into_own_trades(self) -> Option<OwnTradesData> {
+        match self {
+            KrakenEvent::Channel(ChannelData::OwnTrades(data)) => Some(data),
+            KrakenEvent::Data(vec) => parse_own_trades_frame(&vec),
+            _ => None,
         }
-        None
     }
 
+    pub fn try_into_open_orders(self) -> Option<OpenOrdersData> {
+        match self {
+            KrakenEvent::Channel(ChannelData::OpenOrders(data)) => Some(data),
+            KrakenEvent::Data(vec) => parse_open_orders_frame(&vec),
+            _ => None,
+        }
+    }
+
+    /// Parses a book snapshot or update.
+    ///
+    /// Kraken uses three shapes, all handled here:
+    ///
+    /// - snapshot: `[channel_id, {"as": [...], "bs": [...]}, "book-N", pair]`
+    /// - update with one side or both in one object:
+    ///   `[channel_id, {"a": [...], "b": [...], "c": "checksum"}, "book-N", pair]`
+    /// - update with both sides in separate objects, checksum on the second:
+    ///   `[channel_id, {"a": [...]}, {"b": [...], "c": "checksum"}, "book-N", pair]`
+    ///
+    /// Returns `None` for other channels and for frames that do not match one of these
+    /// shapes, including levels that fail to parse, so a malformed update is never
+    /// applied halfway.
     pub fn try_into_orderbook_data(self) -> Option<OrderBookData> {
-        if let KrakenEvent::Data(mut vec) = self {
-            // Format: [channel_id, { "as": ... } OR { "a": ... }, "book-N", pair]
-            // Sometimes updates have two objects: [channel_id, {"a":...}, {"b":...}, "book-N", pair]
+        match self {
+            KrakenEvent::Channel(ChannelData::Book(data)) => Some(data),
+            KrakenEvent::Data(vec) => parse_book_frame(&vec),
+            _ => None,
+        }
+    }
+}
 
-            // Check for "book-" in the channel name
-            // The channel name is usually the second to last element, but if there are 2 objects, it shifts.
-            // Let's look from the end.
+/// An order book price that sorts numerically but keeps Kraken's exact string.
+///
+/// Ordering compares the decimal digits, so `"99.5" < "100.0"` and `"100.0" == "100.00"`,
+/// without a lossy float conversion. The original string is what goes into the checksum.
+#[derive(Debug, Clone)]
+pub struct PriceKey(String);
 
-            let pair = vec.pop()?.as_str()?.to_string();
-            let channel_name = vec.pop()?.as_str()?.to_string();
+impl PriceKey {
+    pub fn new(price: impl Into<String>) -> Self {
+        Self(price.into())
+    }
 
-            if !channel_name.starts_with("book") {
-                return None;
-            }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 
-            let channel_id = vec.remove(0).as_u64()?;
-
-            // Remaining elements in vec are the data objects (1 or 2)
-            let mut asks = Vec::new();
-            let mut bids = Vec::new();
-            let mut is_snapshot = false;
-            let mut checksum: Option<String> = None;
-
-            for value in vec {
-                // Check if it's the checksum string (only present in updates sometimes, or as a separate field?)
-                // Actually, Kraken sends checksum as a field "c" inside the object usually, OR as a separate string at the end?
-                // Let's check the docs/logs.
-                // Usually: [channelID, {"a": [], "b": [], "c": "1234"}, "book-10", "XBT/USD"]
-
-                if let Ok(obj) =
-                    serde_json::from_value::<serde_json::Map<String, Value>>(value.clone())
-                {
-                    if let Some(a_val) = obj.get("a") {
-                        if let Ok(mut list) =
-                            serde_json::from_value::<Vec<OrderBookEntry>>(a_val.clone())
-                        {
-                            asks.append(&mut list);
-                        }
-                    }
-                    if let Some(b_val) = obj.get("b") {
-                        if let Ok(mut list) =
-                            serde_json::from_value::<Vec<OrderBookEntry>>(b_val.clone())
-                        {
-                            bids.append(&mut list);
-                        }
-                    }
-                    if let Some(c_val) = obj.get("c") {
-                        // Checksum is usually a string in the JSON
-                        if let Some(s) = c_val.as_str() {
-                            checksum = Some(s.to_string());
-                        }
-                    }
-                    if let Some(as_val) = obj.get("as") {
-                        is_snapshot = true;
-                        if let Ok(mut list) =
-                            serde_json::from_value::<Vec<OrderBookEntry>>(as_val.clone())
-                        {
-                            asks.append(&mut list);
-                        }
-                    }
-                    if let Some(bs_val) = obj.get("bs") {
-                        is_snapshot = true;
-                        if let Ok(mut list) =
-                            serde_json::from_value::<Vec<OrderBookEntry>>(bs_val.clone())
-                        {
-                            bids.append(&mut list);
-                        }
-                    }
-                }
-            }
+    /// Integer digits without leading zeros, fraction digits without trailing zeros.
+    fn digits(&self) -> (&str, &str) {
+        let (int, frac) = self.0.split_once('.').unwrap_or((&self.0, ""));
+        (int.trim_start_matches('0'), frac.trim_end_matches('0'))
+    }
+}
 
-            return Some(OrderBookData {
-                channel_id,
-                asks,
-                bids,
-                is_snapshot,
-                channel_name,
-                pair,
-                checksum,
-            });
-        }
-        None
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let (int, frac) = self.digits();
+        let (other_int, other_frac) = other.digits();
+        int.len()
+            .cmp(&other_int.len())
+            .then_with(|| int.cmp(other_int))
+            .then_with(|| frac.cmp(other_frac))
     }
 }
 
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PriceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for PriceKey {}
+
+impl std::ops::Deref for PriceKey {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for PriceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for PriceKey {
+    fn from(price: &str) -> Self {
+        Self(price.to_string())
+    }
+}
+
+impl From<String> for PriceKey {
+    fn from(price: String) -> Self {
+        Self(price)
+    }
+}
+
+/// One price level of a [`BookSnapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Level {
+    pub price: f64,
+    pub volume: f64,
+}
+
+/// The best levels of a [`LocalOrderBook`], see [`LocalOrderBook::top`].
+///
+/// Serializes as `{"asks": [{"price": .., "volume": ..}, ...], "bids": [...]}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BookSnapshot {
+    /// Best (lowest) first.
+    pub asks: Vec<Level>,
+    /// Best (highest) first.
+    pub bids: Vec<Level>,
+}
+
+/// A local copy of one pair's order book, kept in price order.
+///
+/// `asks` iterate from the best (lowest) price, `bids` from the worst, so the best bid
+/// is `bids.iter().next_back()`.
 #[derive(Debug, Default)]
 pub struct LocalOrderBook {
-    // Price -> Volume
-    // We use String for precision, but for sorting we might need f64 or custom comparator.
-    // Kraken prices are strings. BTreeMap sorts Strings lexicographically, which IS NOT CORRECT for numbers ("10" < "2").
-    // We must parse to f64 for sorting keys, or use a custom wrapper.
-    // For simplicity in this hackathon, let's assume standard float parsing is fine for keys,
-    // but we keep the original string for the checksum to avoid float formatting issues.
-    // Actually, using a wrapper `OrderedFloat` is best, but we don't want another dep.
-    // Let's use a helper to parse key as f64 for the map.
-    // Wait, if we use f64 as key, we can't get the original string back easily unless we store it as value.
-    // Value: (OriginalPriceString, VolumeString)
-    pub asks: BTreeMap<String, String>, // Key: Price (padded/normalized?), Value: Volume
-    pub bids: BTreeMap<String, String>,
+    pub asks: BTreeMap<PriceKey, String>, // Price -> Volume
+    pub bids: BTreeMap<PriceKey, String>,
+    depth: Option<usize>,
 }
 
 impl LocalOrderBook {
@@ -236,6 +1180,37 @@ impl LocalOrderBook {
         Self::default()
     }
 
+    /// A book that keeps only the best `depth` levels per side, like Kraken does for a
+    /// `book` subscription of that depth.
+    ///
+    /// Kraken does not send deletions for levels pushed out of range by better ones, so
+    /// an untruncated book accumulates stale levels that resurface (and break the
+    /// checksum) once the levels above them are removed.
+    pub fn with_depth(depth: usize) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// The number of levels kept per side, if limited.
+    pub fn depth(&self) -> Option<usize> {
+        self.depth
+    }
+
+    /// Drops the levels beyond the depth: the highest asks and the lowest bids.
+    fn truncate(&mut self) {
+        let Some(depth) = self.depth else {
+            return;
+        };
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+        while self.bids.len() > depth {
+            self.bids.pop_first();
+        }
+    }
+
     pub fn update(&mut self, data: &OrderBookData) {
         if data.is_snapshot {
             self.asks.clear();
@@ -243,24 +1218,67 @@ impl LocalOrderBook {
         }
 
         for entry in &data.asks {
-            let price = &entry.price;
-            let volume = &entry.volume;
-            if volume == "0.00000000" || volume == "0.0" || volume == "0" {
-                self.asks.remove(price);
+            if entry.is_deletion() {
+                self.asks.remove(&PriceKey::from(entry.price.as_str()));
             } else {
-                self.asks.insert(price.clone(), volume.clone());
+                self.asks
+                    .insert(entry.price.clone().into(), entry.volume.clone());
             }
         }
 
         for entry in &data.bids {
-            let price = &entry.price;
-            let volume = &entry.volume;
-            if volume == "0.00000000" || volume == "0.0" || volume == "0" {
-                self.bids.remove(price);
+            if entry.is_deletion() {
+                self.bids.remove(&PriceKey::from(entry.price.as_str()));
             } else {
-                self.bids.insert(price.clone(), volume.clone());
+                self.bids
+                    .insert(entry.price.clone().into(), entry.volume.clone());
+            }
+        }
+        self.truncate();
+    }
+
+    /// Replaces the book with levels from the REST `Depth` endpoint, so a book can be
+    /// served before the WebSocket snapshot arrives. Deltas are applied on top with
+    /// [`update`](Self::update) as usual.
+    ///
+    /// Levels are `(price, volume)` as the strings Kraken returns them (the REST
+    /// timestamp is not needed), in any order. Zero-volume levels are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use kraken_sdk::models::LocalOrderBook;
+    ///
+    /// let mut book = LocalOrderBook::new();
+    /// book.apply_rest_snapshot(
+    ///     [("5541.30000", "2.50700000")],
+    ///     [("5541.20000", "1.52900000")],
+    /// );
+    /// assert_eq!(book.mid(), Some(5541.25));
+    /// ```
+    pub fn apply_rest_snapshot<P, V>(
+        &mut self,
+        asks: impl IntoIterator<Item = (P, V)>,
+        bids: impl IntoIterator<Item = (P, V)>,
+    ) where
+        P: Into<String>,
+        V: Into<String>,
+    {
+        fn fill<P: Into<String>, V: Into<String>>(
+            side: &mut BTreeMap<PriceKey, String>,
+            levels: impl IntoIterator<Item = (P, V)>,
+        ) {
+            side.clear();
+            for (price, volume) in levels {
+                let volume = volume.into();
+                if !matches!(volume.parse::<f64>(), Ok(v) if v == 0.0) {
+                    side.insert(PriceKey::from(price.into()), volume);
+                }
             }
         }
+        fill(&mut self.asks, asks);
+        fill(&mut self.bids, bids);
+        self.truncate();
     }
 
     /// Calculates the Kraken CRC32 checksum.
@@ -268,48 +1286,146 @@ impl LocalOrderBook {
     /// 1. Top 10 Asks (lowest price)
     /// 2. Top 10 Bids (highest price)
     /// 3. String = price + volume (decimal points removed)
+    ///
+    /// The maps are already in price order, so this only walks the top levels:
+    /// no allocation and no sort per call.
     pub fn calculate_checksum(&self) -> u32 {
-        let mut hasher = Hasher::new();
+        let top_asks = self.asks.iter().map(|(p, v)| (p.as_str(), v.as_str()));
+        let top_bids = self
+            .bids
+            .iter()
+            .rev()
+            .map(|(p, v)| (p.as_str(), v.as_str()));
+        checksum_levels(top_asks, top_bids)
+    }
 
-        // Asks: Sorted Low to High.
-        // BTreeMap sorts Strings lexicographically. This is a BUG if prices have different integer lengths (e.g. "100" vs "99").
-        // However, for a single pair like XBT/USD, prices are usually same length (5 digits).
-        // To be safe, we should really sort by float value.
-        // Let's collect and sort properly.
+    /// Fraction of volume on the bid side over the top `levels` of each side, in `0.0..=1.0`.
+    ///
+    /// Above `0.5` means more resting bids than asks. An empty book returns `0.5`.
+    pub fn imbalance(&self, levels: usize) -> f64 {
+        let bid_vol: f64 = parsed(self.bids.iter().rev())
+            .take(levels)
+            .map(|(_, v)| v)
+            .sum();
+        let ask_vol: f64 = parsed(self.asks.iter()).take(levels).map(|(_, v)| v).sum();
+        let total = bid_vol + ask_vol;
+        if total > 0.0 {
+            bid_vol / total
+        } else {
+            0.5
+        }
+    }
 
-        let mut asks: Vec<(&String, &String)> = self.asks.iter().collect();
-        asks.sort_by(|a, b| {
-            let p1 = a.0.parse::<f64>().unwrap_or(0.0);
-            let p2 = b.0.parse::<f64>().unwrap_or(0.0);
-            p1.partial_cmp(&p2).unwrap()
-        });
+    /// The best `n` levels per side as parsed numbers, best first on both sides.
+    ///
+    /// Levels that fail to parse are skipped. For Kraken's exact strings use
+    /// [`to_json`](Self::to_json).
+    pub fn top(&self, n: usize) -> BookSnapshot {
+        let level = |(price, volume)| Level { price, volume };
+        BookSnapshot {
+            asks: parsed(self.asks.iter()).take(n).map(level).collect(),
+            bids: parsed(self.bids.iter().rev()).take(n).map(level).collect(),
+        }
+    }
 
-        let mut bids: Vec<(&String, &String)> = self.bids.iter().collect();
-        bids.sort_by(|a, b| {
-            let p1 = a.0.parse::<f64>().unwrap_or(0.0);
-            let p2 = b.0.parse::<f64>().unwrap_or(0.0);
-            p2.partial_cmp(&p1).unwrap() // Reverse for Bids (High to Low)
-        });
+    /// Best (highest) bid as `(price, volume)`.
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        parsed(self.bids.iter().rev()).next()
+    }
 
-        for (price, volume) in asks.iter().take(10) {
-            let p = price.replace(".", "");
-            let p = p.trim_start_matches('0');
-            let v = volume.replace(".", "");
-            let v = v.trim_start_matches('0');
-            hasher.update(p.as_bytes());
-            hasher.update(v.as_bytes());
-        }
+    /// Best (lowest) ask as `(price, volume)`.
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        parsed(self.asks.iter()).next()
+    }
 
-        for (price, volume) in bids.iter().take(10) {
-            let p = price.replace(".", "");
-            let p = p.trim_start_matches('0');
-            let v = volume.replace(".", "");
-            let v = v.trim_start_matches('0');
-            hasher.update(p.as_bytes());
-            hasher.update(v.as_bytes());
-        }
+    /// Midpoint between the best bid and best ask.
+    pub fn mid(&self) -> Option<f64> {
+        let ((bid, _), (ask, _)) = (self.best_bid()?, self.best_ask()?);
+        Some((bid + ask) / 2.0)
+    }
+
+    /// Size-weighted fair price at the touch:
+    /// `(best_ask * bid_vol + best_bid * ask_vol) / (bid_vol + ask_vol)`.
+    ///
+    /// Leans towards the ask when the bid is heavier (buy pressure) and vice versa.
+    /// `None` if either side is empty.
+    pub fn microprice(&self) -> Option<f64> {
+        let ((bid, bid_vol), (ask, ask_vol)) = (self.best_bid()?, self.best_ask()?);
+        weighted_fair_price(bid, bid_vol, ask, ask_vol)
+    }
+
+    /// [`microprice`](Self::microprice) over the top `levels` of each side: each side's
+    /// volume-weighted price, weighted by the opposite side's total volume.
+    ///
+    /// With `levels == 1` this equals the microprice. `None` if either side is empty.
+    pub fn weighted_mid(&self, levels: usize) -> Option<f64> {
+        let (bid, bid_vol) = side_vwap(parsed(self.bids.iter().rev()).take(levels))?;
+        let (ask, ask_vol) = side_vwap(parsed(self.asks.iter()).take(levels))?;
+        weighted_fair_price(bid, bid_vol, ask, ask_vol)
+    }
 
-        hasher.finalize()
+    /// Cumulative `(bid, ask)` volume resting within `pct` percent of the mid price.
+    ///
+    /// For example `volume_within(0.5)` sums levels priced within ±0.5% of mid.
+    /// Returns `(0.0, 0.0)` unless both sides have at least one level.
+    pub fn volume_within(&self, pct: f64) -> (f64, f64) {
+        let Some(mid) = self.mid() else {
+            return (0.0, 0.0);
+        };
+        let band = mid * pct / 100.0;
+        let bid_vol = parsed(self.bids.iter().rev())
+            .take_while(|(p, _)| *p >= mid - band)
+            .map(|(_, v)| v)
+            .sum();
+        let ask_vol = parsed(self.asks.iter())
+            .take_while(|(p, _)| *p <= mid + band)
+            .map(|(_, v)| v)
+            .sum();
+        (bid_vol, ask_vol)
+    }
+
+    /// Volume resting at exactly `price` on either side, if that level exists.
+    pub fn depth_at(&self, price: f64) -> Option<f64> {
+        parsed(self.asks.iter().chain(self.bids.iter()))
+            .find(|(p, _)| *p == price)
+            .map(|(_, v)| v)
+    }
+
+    /// The book as `{"asks": [[price, volume], ...], "bids": [...]}`, in Kraken's order:
+    /// asks ascending, bids descending.
+    ///
+    /// Prices and volumes keep Kraken's exact strings, so a dump taken after a checksum
+    /// mismatch can be compared level by level with a fresh snapshot, and
+    /// [`from_json`](Self::from_json) restores the same checksum.
+    pub fn to_json(&self) -> Value {
+        let level = |(price, volume): (&PriceKey, &String)| {
+            Value::Array(vec![price.as_str().into(), volume.as_str().into()])
+        };
+        serde_json::json!({
+            "asks": self.asks.iter().map(level).collect::<Vec<_>>(),
+            "bids": self.bids.iter().rev().map(level).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Restores a book dumped by [`to_json`](Self::to_json). Levels may come in any order.
+    pub fn from_json(value: &Value) -> serde_json::Result<Self> {
+        #[derive(Deserialize)]
+        struct Levels {
+            asks: Vec<(String, String)>,
+            bids: Vec<(String, String)>,
+        }
+        let levels = Levels::deserialize(value)?;
+        let side = |levels: Vec<(String, String)>| {
+            levels
+                .into_iter()
+                .map(|(price, volume)| (PriceKey::from(price), volume))
+                .collect()
+        };
+        Ok(Self {
+            asks: side(levels.asks),
+            bids: side(levels.bids),
+            depth: None,
+        })
     }
 
     pub fn validate_checksum(&self, remote_checksum: &str) -> bool {
@@ -323,25 +1439,137 @@ impl LocalOrderBook {
     }
 }
 
-// Custom deserializer for Trade array: ["price", "volume", "time", "side", "type", "misc"]
+impl Trade {
+    pub fn price_f64(&self) -> Result<f64, ParseFloatError> {
+        self.price.parse()
+    }
+
+    pub fn volume_f64(&self) -> Result<f64, ParseFloatError> {
+        self.volume.parse()
+    }
+
+    /// Parses the trade time (seconds since the UNIX epoch, with fractional part).
+    pub fn time_f64(&self) -> Result<f64, ParseFloatError> {
+        self.time.parse()
+    }
+
+    /// Parses the price as an exact decimal. Unlike `f64`, this is lossless
+    /// because Kraken sends prices as decimal strings.
+    #[cfg(feature = "decimal")]
+    pub fn price_decimal(&self) -> Result<Decimal, rust_decimal::Error> {
+        self.price.parse()
+    }
+
+    #[cfg(feature = "decimal")]
+    pub fn volume_decimal(&self) -> Result<Decimal, rust_decimal::Error> {
+        self.volume.parse()
+    }
+}
+
+// Custom deserializer for Trade array: ["price", "volume", "time", "side", "type", "misc", trade_id?]
 impl<'de> Deserialize<'de> for Trade {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let v: Vec<String> = Deserialize::deserialize(deserializer)?;
+        let v: Vec<Value> = Deserialize::deserialize(deserializer)?;
+        let field = |i: usize| {
+            v.get(i)
+                .and_then(|x| x.as_str())
+                .map(str::to_string)
+                .unwrap_or_default()
+        };
+        // The trade id may arrive as a number or a numeric string
+        let trade_id = v
+            .get(6)
+            .and_then(|x| x.as_u64().or_else(|| x.as_str()?.parse().ok()));
         Ok(Trade {
-            price: v.first().cloned().unwrap_or_default(),
-            volume: v.get(1).cloned().unwrap_or_default(),
-            time: v.get(2).cloned().unwrap_or_default(),
-            side: v.get(3).cloned().unwrap_or_default(),
-            order_type: v.get(4).cloned().unwrap_or_default(),
-            misc: v.get(5).cloned().unwrap_or_default(),
+            price: field(0),
+            volume: field(1),
+            time: field(2),
+            side: field(3),
+            order_type: field(4),
+            misc: Some(field(5)).filter(|misc| !misc.is_empty()),
+            trade_id,
         })
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Kraken's CRC32 book checksum over raw `(price, volume)` levels.
+///
+/// `asks` must be sorted best (lowest) first and `bids` best (highest) first; only the
+/// top 10 of each are used. Prices and volumes must be the exact strings Kraken sent,
+/// since trailing zeros are part of the checksum input. Useful for validating a REST
+/// snapshot without building a [`LocalOrderBook`].
+///
+/// # Example
+///
+/// A ten-level book in the style of Kraken's checksum guide (expected value computed
+/// independently with zlib's CRC32 over the concatenated digits):
+///
+/// ```rust
+/// use kraken_sdk::models::kraken_checksum;
+///
+/// let asks = [
+///     ("0.05005", "0.00000500"), ("0.05010", "0.00000500"), ("0.05015", "0.00000500"),
+///     ("0.05020", "0.00000500"), ("0.05025", "0.00000500"), ("0.05030", "0.00000500"),
+///     ("0.05035", "0.00000500"), ("0.05040", "0.00000500"), ("0.05045", "0.00000500"),
+///     ("0.05050", "0.00000500"),
+/// ];
+/// let bids = [
+///     ("0.05000", "0.00000500"), ("0.04995", "0.00000500"), ("0.04990", "0.00000500"),
+///     ("0.04980", "0.00000500"), ("0.04975", "0.00000500"), ("0.04970", "0.00000500"),
+///     ("0.04965", "0.00000500"), ("0.04960", "0.00000500"), ("0.04955", "0.00000500"),
+///     ("0.04950", "0.00000500"),
+/// ];
+/// assert_eq!(kraken_checksum(&asks, &bids), 974947235);
+/// ```
+pub fn kraken_checksum(asks: &[(&str, &str)], bids: &[(&str, &str)]) -> u32 {
+    checksum_levels(asks.iter().copied(), bids.iter().copied())
+}
+
+/// Hashes the top 10 levels of each side, asks first; both iterators go best price first.
+fn checksum_levels<'a>(
+    asks: impl Iterator<Item = (&'a str, &'a str)>,
+    bids: impl Iterator<Item = (&'a str, &'a str)>,
+) -> u32 {
+    let mut hasher = Hasher::new();
+    for (price, volume) in asks.take(10).chain(bids.take(10)) {
+        hash_checksum_field(&mut hasher, price);
+        hash_checksum_field(&mut hasher, volume);
+    }
+    hasher.finalize()
+}
+
+/// Checksum input for a price or volume: decimal point removed, leading zeros trimmed.
+fn hash_checksum_field(hasher: &mut Hasher, value: &str) {
+    for digits in value.trim_start_matches(['0', '.']).split('.') {
+        hasher.update(digits.as_bytes());
+    }
+}
+
+/// Volume-weighted price and total volume of some levels, `None` if they hold no volume.
+fn side_vwap(levels: impl Iterator<Item = (f64, f64)>) -> Option<(f64, f64)> {
+    let (notional, volume) = levels.fold((0.0, 0.0), |(n, v), (price, vol)| {
+        (n + price * vol, v + vol)
+    });
+    (volume > 0.0).then(|| (notional / volume, volume))
+}
+
+fn weighted_fair_price(bid: f64, bid_vol: f64, ask: f64, ask_vol: f64) -> Option<f64> {
+    let total = bid_vol + ask_vol;
+    (total > 0.0).then(|| (ask * bid_vol + bid * ask_vol) / total)
+}
+
+/// Book levels as `(price, volume)` floats, skipping levels that don't parse.
+fn parsed<'a, I>(levels: I) -> impl Iterator<Item = (f64, f64)> + 'a
+where
+    I: Iterator<Item = (&'a PriceKey, &'a String)> + 'a,
+{
+    levels.filter_map(|(p, v)| Some((p.parse().ok()?, v.parse().ok()?)))
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
 pub struct Candle {
     pub open: f64,
     pub high: f64,
@@ -350,6 +1578,51 @@ pub struct Candle {
     pub volume: f64,
     pub start_time: u64, // Unix timestamp (seconds)
     pub interval_seconds: u64,
+    /// Volume of trades where the taker bought (lifted the offer).
+    pub buy_volume: f64,
+    /// Volume of trades where the taker sold (hit the bid).
+    pub sell_volume: f64,
+    /// Number of trades in the candle.
+    pub count: u64,
+}
+
+/// Formats as `O: 100.00 H: 105.00 L: 99.50 C: 104.00 V: 12.3456`.
+///
+/// Prices use the formatter's precision (default 2 decimals), so `{:.8}` suits
+/// low-priced assets; the volume always has 4.
+impl std::fmt::Display for Candle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let precision = f.precision().unwrap_or(2);
+        write!(
+            f,
+            "O: {:.p$} H: {:.p$} L: {:.p$} C: {:.p$} V: {:.4}",
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume,
+            p = precision
+        )
+    }
+}
+
+impl Candle {
+    /// Aggressor volume delta: `buy_volume - sell_volume`. Positive when buyers dominated.
+    pub fn delta(&self) -> f64 {
+        self.buy_volume - self.sell_volume
+    }
+
+    /// Header row matching [`write_csv_row`](Self::write_csv_row).
+    pub const CSV_HEADER: &'static str = "time,open,high,low,close,volume";
+
+    /// Writes the candle as one CSV line: start time (Unix seconds), then OHLCV.
+    pub fn write_csv_row<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            self.start_time, self.open, self.high, self.low, self.close, self.volume
+        )
+    }
 }
 
 #[cfg(test)]
@@ -360,10 +1633,53 @@ mod tests {
     fn test_parse_heartbeat() {
         let data = r#"{"event":"heartbeat"}"#;
         let event: KrakenEvent = serde_json::from_str(data).unwrap();
-        match event {
-            KrakenEvent::Heartbeat(_) => assert!(true),
-            _ => assert!(false, "Expected Heartbeat"),
+        assert!(
+            matches!(event, KrakenEvent::Heartbeat(_)),
+            "Expected Heartbeat"
+        );
+    }
+
+    #[test]
+    fn test_parse_borrowed_matches_owned_trades() {
+        let frame = r#"[0,[["5541.2","0.1","1534614057.3","b","m","",42],["5541.3","0.2","1534614058.1","s","l",""]],"trade","XBT/USD"]"#;
+        let borrowed = parse_borrowed(frame).unwrap();
+        let owned = serde_json::from_str::<KrakenEvent>(frame)
+            .unwrap()
+            .try_into_trade_data()
+            .unwrap();
+        assert_eq!((borrowed.channel_name, borrowed.pair), ("trade", "XBT/USD"));
+        assert_eq!(borrowed.data.len(), owned.data.len());
+        for (b, o) in borrowed.data.iter().zip(&owned.data) {
+            assert_eq!(b.to_trade().price, o.price);
+            assert_eq!(b.misc, o.misc.as_deref());
+            assert_eq!((b.time, b.trade_id), (o.time.as_str(), o.trade_id));
+            assert_eq!(b.taker_side(), o.taker_side());
         }
+
+        let book = r#"[1,{"a":[["5541.3","2.5","1.0"]]},"book-10","XBT/USD"]"#;
+        assert!(parse_borrowed(book).is_err());
+        assert!(parse_borrowed(r#"[0,[],"spread","XBT/USD"]"#).is_err());
+        assert!(parse_borrowed(r#"{"event":"heartbeat"}"#).is_err());
+    }
+
+    #[test]
+    fn test_trade_misc_is_none_when_empty() {
+        let frame = r#"[0,[["5541.2","0.1","1534614057.3","b","m",""],["5541.3","0.2","1534614058.1","s","l","x"]],"trade","XBT/USD"]"#;
+        let trades = serde_json::from_str::<KrakenEvent>(frame)
+            .unwrap()
+            .try_into_trade_data()
+            .unwrap();
+        assert_eq!(trades.data[0].misc, None);
+        assert_eq!(trades.data[1].misc.as_deref(), Some("x"));
+        let borrowed = parse_borrowed(frame).unwrap();
+        assert_eq!(
+            (borrowed.data[0].misc, borrowed.data[1].misc),
+            (None, Some("x"))
+        );
+        assert!(serde_json::to_value(&trades.data[0])
+            .unwrap()
+            .get("misc")
+            .is_none());
     }
 
     #[test]
@@ -371,11 +1687,633 @@ mod tests {
         let data = r#"[123, [["50000.0", "1.0", "123456.789", "b", "m", ""]], "trade", "XBT/USD"]"#;
         let event: KrakenEvent = serde_json::from_str(data).unwrap();
         match event {
-            KrakenEvent::Data(vec) => {
-                assert_eq!(vec.len(), 4);
-                // Further parsing logic would go here or in a conversion function
+            KrakenEvent::Channel(ChannelData::Trade(trades)) => {
+                assert_eq!(trades.channel_id, 123);
+                assert_eq!(trades.pair, "XBT/USD");
+                assert_eq!(trades.data[0].price, "50000.0");
             }
-            _ => assert!(false, "Expected Data"),
+            other => panic!("Expected a trade, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_ticker_data() {
+        let data = r#"[0,{"a":["5525.40000",1,"1.000"],"b":["5525.10000",1,"1.000"],"c":["5525.10000","0.00398963"],"v":["2634.11501494","3591.17907851"],"p":["5631.44067","5653.78939"],"t":[11493,16267],"l":["5505.00000","5505.00000"],"h":["5783.00000","5783.00000"],"o":["5760.70000","5763.40000"]},"ticker","XBT/USD"]"#;
+        let ticker = serde_json::from_str::<KrakenEvent>(data)
+            .unwrap()
+            .try_into_ticker_data()
+            .unwrap();
+        assert_eq!(ticker.pair, "XBT/USD");
+        assert_eq!(ticker.ticker.ask_price(), Some(5525.4));
+        assert_eq!(ticker.ticker.bid_price(), Some(5525.1));
+        assert_eq!(ticker.ticker.last_price(), Some(5525.1));
+        assert_eq!(ticker.ticker.trades, vec![11493, 16267]);
+    }
+
+    #[test]
+    fn test_unknown_and_malformed_frames_stay_raw() {
+        let spread = r#"[0,[["5698.40000","5700.00000","1542057299.545897","1.01234567","0.98765432"]],"spread","XBT/USD"]"#;
+        let malformed = r#"[0, {"as": [["oops"]]}, "book-10", "XBT/USD"]"#;
+        for raw in [spread, malformed] {
+            let event: KrakenEvent = serde_json::from_str(raw).unwrap();
+            assert!(matches!(event, KrakenEvent::Data(_)), "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn test_unrecognized_messages_are_unknown() {
+        let new_channel = r#"[0,{"x":"1"},"auction","XBT/USD"]"#;
+        let new_event = r#"{"event":"editOrderStatus","status":"ok","txid":"OTX"}"#;
+        for raw in [
+            new_channel,
+            new_event,
+            "[]",
+            r#"{"status":"online"}"#,
+            r#""text""#,
+        ] {
+            let event: KrakenEvent = serde_json::from_str(raw).unwrap();
+            match event {
+                KrakenEvent::Unknown(value) => {
+                    assert_eq!(value, serde_json::from_str::<Value>(raw).unwrap())
+                }
+                other => panic!("Expected Unknown, got {:?}", other),
+            }
+        }
+        assert!(serde_json::from_str::<KrakenEvent>(r#"{"event":"#).is_err());
+    }
+
+    #[test]
+    fn test_numeric_accessors() {
+        let trade: Trade =
+            serde_json::from_str(r#"["50000.5", "0.25", "1700000000.123", "b", "m", ""]"#).unwrap();
+        assert_eq!(trade.price_f64(), Ok(50000.5));
+        assert_eq!(trade.volume_f64(), Ok(0.25));
+        assert_eq!(trade.time_f64(), Ok(1700000000.123));
+
+        let entry: OrderBookEntry = serde_json::from_str(r#"["abc", "1.5", "1.0"]"#).unwrap();
+        assert!(entry.price_f64().is_err());
+        assert_eq!(entry.volume_f64(), Ok(1.5));
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal_accessors_are_exact() {
+        let trade: Trade =
+            serde_json::from_str(r#"["0.10000000", "0.20000000", "1.0", "b", "m", ""]"#).unwrap();
+        let sum = trade.price_decimal().unwrap() + trade.volume_decimal().unwrap();
+        assert_eq!(sum, "0.3".parse::<Decimal>().unwrap());
+    }
+
+    #[test]
+    fn test_orderbook_entry_update_type() {
+        let snapshot: OrderBookEntry =
+            serde_json::from_str(r#"["5541.30000", "2.50700000", "1534614248.123678"]"#).unwrap();
+        assert_eq!(snapshot.update_type, None);
+
+        let republish: OrderBookEntry =
+            serde_json::from_str(r#"["5541.30000", "2.50700000", "1534614248.456738", "r"]"#)
+                .unwrap();
+        assert_eq!(republish.update_type.as_deref(), Some("r"));
+        assert_eq!(republish.price, "5541.30000");
+    }
+
+    fn parse_book(raw: &str) -> Option<OrderBookData> {
+        serde_json::from_str::<KrakenEvent>(raw)
+            .unwrap()
+            .try_into_orderbook_data()
+    }
+
+    #[test]
+    fn test_parse_book_snapshot() {
+        let book = parse_book(
+            r#"[0, {"as": [["5541.30000", "2.50700000", "1534614248.123678"]], "bs": [["5541.20000", "1.52900000", "1534614248.765567"], ["5539.90000", "0.30000000", "1534614241.769870"]]}, "book-100", "XBT/USD"]"#,
+        )
+        .unwrap();
+        assert!(book.is_snapshot);
+        assert_eq!(book.channel_id, 0);
+        assert_eq!(book.channel_name, "book-100");
+        assert_eq!(book.pair, "XBT/USD");
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.bids.len(), 2);
+        assert_eq!(book.checksum, None);
+    }
+
+    #[test]
+    fn test_parse_book_update_single_object() {
+        let book = parse_book(
+            r#"[1234, {"a": [["5541.30000", "2.50700000", "1534614248.456738"], ["5542.50000", "0.40100000", "1534614248.456738"]], "c": "974942666"}, "book-10", "XBT/USD"]"#,
+        )
+        .unwrap();
+        assert!(!book.is_snapshot);
+        assert_eq!(book.asks.len(), 2);
+        assert!(book.bids.is_empty());
+        assert_eq!(book.checksum.as_deref(), Some("974942666"));
+    }
+
+    #[test]
+    fn test_parse_book_update_two_objects_checksum_on_second() {
+        let book = parse_book(
+            r#"[1234, {"a": [["5541.30000", "2.50700000", "1534614248.456738"]]}, {"b": [["5541.20000", "1.52900000", "1534614248.765567", "r"]], "c": "974942666"}, "book-10", "XBT/USD"]"#,
+        )
+        .unwrap();
+        assert!(!book.is_snapshot);
+        assert_eq!(book.channel_id, 1234);
+        assert_eq!(book.asks[0].price, "5541.30000");
+        assert_eq!(book.bids[0].price, "5541.20000");
+        assert_eq!(book.bids[0].update_type.as_deref(), Some("r"));
+        assert_eq!(book.checksum.as_deref(), Some("974942666"));
+        assert_eq!(book.pair, "XBT/USD");
+    }
+
+    #[test]
+    fn test_parse_book_rejects_malformed_frames() {
+        // Not a book channel
+        assert!(parse_book(r#"[0, [], "trade", "XBT/USD"]"#).is_none());
+        // A level that does not parse must not yield a partial update
+        assert!(parse_book(
+            r#"[1, {"a": [["5541.3", "1.0", "1534614248.4"]]}, {"b": [["oops"]], "c": "1"}, "book-10", "XBT/USD"]"#
+        )
+        .is_none());
+        // Data that is not an object
+        assert!(parse_book(r#"[1, "a", "book-10", "XBT/USD"]"#).is_none());
+        // Too many data objects
+        assert!(parse_book(r#"[1, {}, {}, {}, "book-10", "XBT/USD"]"#).is_none());
+    }
+
+    #[test]
+    fn test_parse_routes_on_event_field() {
+        let status = r#"{"connectionID":8628615390848610000,"event":"systemStatus","status":"online","version":"1.0.0"}"#;
+        assert!(matches!(
+            serde_json::from_str::<KrakenEvent>(status).unwrap(),
+            KrakenEvent::SystemStatus(s)
+                if s.status == "online" && s.connection_id == Some(8628615390848610000)
+        ));
+
+        let subscription = r#"{"channelID":10001,"event":"subscriptionStatus","pair":"XBT/USD","status":"subscribed","subscription":{"name":"trade"}}"#;
+        assert!(matches!(
+            serde_json::from_str::<KrakenEvent>(subscription).unwrap(),
+            KrakenEvent::SubscriptionStatus(_)
+        ));
+
+        let rejected = r#"{"errorMessage":"Currency pair not supported BTC/USD","event":"subscriptionStatus","pair":"BTC/USD","status":"error","subscription":{"name":"trade"}}"#;
+        match serde_json::from_str::<KrakenEvent>(rejected).unwrap() {
+            KrakenEvent::SubscriptionStatus(status) => assert_eq!(
+                status.error_message.as_deref(),
+                Some("Currency pair not supported BTC/USD")
+            ),
+            other => panic!("Expected SubscriptionStatus, got {:?}", other),
+        }
+
+        // "event" first: later fields are still kept for typed events, skipped otherwise
+        let leading = r#"{"event":"subscriptionStatus","channelID":10001,"status":"subscribed","subscription":{"name":"trade"}}"#;
+        assert!(matches!(
+            serde_json::from_str::<KrakenEvent>(leading).unwrap(),
+            KrakenEvent::SubscriptionStatus(s) if s.channel_id == Some(10001)
+        ));
+        let pong = r#"{"event":"pong","reqid":42,"extra":{"nested":[1,2]}}"#;
+        assert!(matches!(
+            serde_json::from_str::<KrakenEvent>(pong).unwrap(),
+            KrakenEvent::Heartbeat(h) if h.event == "pong"
+        ));
+        assert!(matches!(
+            serde_json::from_value::<KrakenEvent>(serde_json::json!({"event": "heartbeat"}))
+                .unwrap(),
+            KrakenEvent::Heartbeat(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_add_order_status() {
+        let ok = r#"{"descr":"buy 0.01000000 XBTUSD @ limit 9000.0","event":"addOrderStatus","status":"ok","txid":"ONPNXH-KMKMU-F4MR5V","reqid":7}"#;
+        match serde_json::from_str::<KrakenEvent>(ok).unwrap() {
+            KrakenEvent::AddOrderStatus(status) => {
+                assert_eq!(status.status, "ok");
+                assert_eq!(status.txid.as_deref(), Some("ONPNXH-KMKMU-F4MR5V"));
+                assert_eq!(status.reqid, Some(7));
+            }
+            other => panic!("Expected AddOrderStatus, got {:?}", other),
+        }
+
+        let err = r#"{"errorMessage":"EOrder:Order minimum not met","event":"addOrderStatus","status":"error"}"#;
+        match serde_json::from_str::<KrakenEvent>(err).unwrap() {
+            KrakenEvent::AddOrderStatus(status) => {
+                assert_eq!(status.status, "error");
+                assert_eq!(
+                    status.error_message.as_deref(),
+                    Some("EOrder:Order minimum not met")
+                );
+            }
+            other => panic!("Expected AddOrderStatus, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_cancel_statuses() {
+        let cancel = r#"{"event":"cancelOrderStatus","status":"ok","reqid":3}"#;
+        assert!(matches!(
+            serde_json::from_str::<KrakenEvent>(cancel).unwrap(),
+            KrakenEvent::CancelOrderStatus(s) if s.status == "ok" && s.reqid == Some(3)
+        ));
+
+        let all = r#"{"count":2,"event":"cancelAllStatus","status":"ok","reqid":4}"#;
+        let event = serde_json::from_str::<KrakenEvent>(all).unwrap();
+        assert_eq!(event.reqid(), Some(4));
+        assert!(matches!(event, KrakenEvent::CancelAllStatus(s) if s.count == Some(2)));
+
+        let after = r#"{"currentTime":"2020-12-21T09:37:09Z","event":"cancelAllOrdersAfterStatus","reqid":5,"status":"ok","triggerTime":"2020-12-21T09:38:09Z"}"#;
+        assert!(matches!(
+            serde_json::from_str::<KrakenEvent>(after).unwrap(),
+            KrakenEvent::CancelAllOrdersAfterStatus(s)
+                if s.trigger_time.as_deref() == Some("2020-12-21T09:38:09Z") && s.reqid == Some(5)
+        ));
+
+        let err = r#"{"errorMessage":"EOrder:Unknown order","event":"cancelOrderStatus","status":"error"}"#;
+        match serde_json::from_str::<KrakenEvent>(err).unwrap() {
+            KrakenEvent::CancelOrderStatus(status) => {
+                assert_eq!(
+                    status.error_message.as_deref(),
+                    Some("EOrder:Unknown order")
+                )
+            }
+            other => panic!("Expected CancelOrderStatus, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_own_trades() {
+        let data = r#"[[{"TDLH43-DVQXD-2KHVYY":{"cost":"1000000.00000","fee":"1600.00000","margin":"0.00000","ordertxid":"TDLH43-DVQXD-2KHVYY","ordertype":"limit","pair":"XBT/EUR","postxid":"OGTT3Y-C6I3P-XRI6HX","price":"100000.00000","time":"1560516023.070651","type":"sell","vol":"1000000000.00000000"}}],"ownTrades",{"sequence":2948}]"#;
+        let event: KrakenEvent = serde_json::from_str(data).unwrap();
+        let own = event.try_into_own_trades().expect("Should parse ownTrades");
+
+        assert_eq!(own.sequence, Some(2948));
+        assert_eq!(own.trades.len(), 1);
+        let trade = &own.trades[0];
+        assert_eq!(trade.trade_id, "TDLH43-DVQXD-2KHVYY");
+        assert_eq!(trade.pair, "XBT/EUR");
+        assert_eq!(trade.fee, "1600.00000");
+        assert_eq!(trade.side, "sell");
+        assert_eq!(trade.order_type, "limit");
+    }
+
+    #[test]
+    fn test_parse_open_orders() {
+        let data = r#"[[{"OGTT3Y-C6I3P-XRI6HX":{"status":"open","vol":"0.5","vol_exec":"0.1","cost":"3000.0","fee":"4.8","descr":{"pair":"XBT/USD","type":"buy","ordertype":"limit","price":"30000.0"}}},{"OKAGX3-XXXXX-ZZZZZZ":{"status":"closed"}}],"openOrders",{"sequence":5}]"#;
+        let event: KrakenEvent = serde_json::from_str(data).unwrap();
+        let open = event
+            .try_into_open_orders()
+            .expect("Should parse openOrders");
+
+        assert_eq!(open.sequence, Some(5));
+        assert_eq!(open.orders.len(), 2);
+        assert_eq!(open.orders[0].order_id, "OGTT3Y-C6I3P-XRI6HX");
+        assert_eq!(open.orders[0].vol_exec.as_deref(), Some("0.1"));
+        assert_eq!(open.orders[0].descr.as_ref().unwrap().side, "buy");
+        assert_eq!(open.orders[1].status.as_deref(), Some("closed"));
+        assert!(open.orders[1].vol.is_none());
+
+        let trades = serde_json::from_str::<KrakenEvent>(data)
+            .unwrap()
+            .try_into_own_trades();
+        assert!(trades.is_none());
+    }
+
+    #[test]
+    fn test_serialize_typed_data() {
+        let data = r#"[0,[["5541.20000","0.15850568","1534614057.321597","s","l","",42]],"trade","XBT/USD"]"#;
+        let trade_data = serde_json::from_str::<KrakenEvent>(data)
+            .unwrap()
+            .try_into_trade_data()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&trade_data).unwrap(),
+            serde_json::json!({
+                "channel_id": 0,
+                "data": [{
+                    "price": "5541.20000",
+                    "volume": "0.15850568",
+                    "time": "1534614057.321597",
+                    "side": "s",
+                    "order_type": "l",
+                    "trade_id": 42
+                }],
+                "channel_name": "trade",
+                "pair": "XBT/USD"
+            })
+        );
+
+        let book = r#"[1234,{"a":[["5541.30000","2.50700000","1534614248.456738"]],"c":"974942666"},"book-10","XBT/USD"]"#;
+        let book_data = serde_json::from_str::<KrakenEvent>(book)
+            .unwrap()
+            .try_into_orderbook_data()
+            .unwrap();
+        let json = serde_json::to_value(&book_data).unwrap();
+        assert_eq!(json["asks"][0]["price"], "5541.30000");
+        assert!(json["asks"][0].get("update_type").is_none());
+        assert_eq!(json["is_snapshot"], false);
+        assert_eq!(json["checksum"], "974942666");
+
+        let candle = Candle {
+            open: 1.0,
+            high: 2.0,
+            low: 0.5,
+            close: 1.5,
+            volume: 10.0,
+            start_time: 60,
+            interval_seconds: 60,
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(candle).unwrap()["close"], 1.5);
+    }
+
+    #[test]
+    fn test_system_state_parses_known_and_unknown_statuses() {
+        let status: SystemStatus = serde_json::from_str(
+            r#"{"event":"systemStatus","status":"cancel_only","version":"1.9.0"}"#,
+        )
+        .unwrap();
+        assert_eq!(status.state(), SystemState::CancelOnly);
+        assert!(!status.state().accepts_orders());
+
+        let state: SystemState = serde_json::from_str(r#""post_only""#).unwrap();
+        assert_eq!(state, SystemState::PostOnly);
+        assert!(state.accepts_orders());
+
+        let unknown = SystemState::from("degraded");
+        assert_eq!(unknown, SystemState::Unknown("degraded".to_string()));
+        assert_eq!(unknown.to_string(), "degraded");
+        assert_eq!(SystemState::Maintenance.as_str(), "maintenance");
+    }
+
+    #[test]
+    fn test_sorted_top_levels() {
+        let snapshot = r#"[1, {"as": [["102.0", "2", "0"], ["100.5", "1", "0"], ["oops", "1", "0"], ["101.0", "3", "0"]], "bs": [["99.0", "1", "0"], ["100.0", "4", "0"], ["98.5", "2", "0"]]}, "book-10", "XBT/USD"]"#;
+        let book = parse_book(snapshot).unwrap();
+        assert_eq!(book.sorted_asks(2), vec![(100.5, 1.0), (101.0, 3.0)]);
+        assert_eq!(
+            book.sorted_bids(10),
+            vec![(100.0, 4.0), (99.0, 1.0), (98.5, 2.0)]
+        );
+        assert!(book.sorted_asks(0).is_empty());
+    }
+
+    #[test]
+    fn test_book_update_removes_levels_for_any_zero_spelling() {
+        let snapshot = r#"[1, {"as": [["1.0", "1", "0"], ["2.0", "1", "0"], ["3.0", "1", "0"], ["4.0", "1", "0"], ["5.0", "1", "0"]], "bs": [["0.5", "1", "0"]]}, "book-10", "XBT/USD"]"#;
+        let mut book = LocalOrderBook::new();
+        book.update(&parse_book(snapshot).unwrap());
+        assert_eq!(book.asks.len(), 5);
+
+        let update = r#"[1, {"a": [["1.0", "0", "1"], ["2.0", "0.0", "1"], ["3.0", "0.000000", "1"], ["4.0", "0.00000000", "1"], ["5.0", "0.00000001", "1"]]}, {"b": [["0.5", "0.0000", "1"]]}, "book-10", "XBT/USD"]"#;
+        book.update(&parse_book(update).unwrap());
+        let asks: Vec<(&str, &str)> = book
+            .asks
+            .iter()
+            .map(|(p, v)| (p.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(asks, vec![("5.0", "0.00000001")]);
+        assert!(book.bids.is_empty());
+    }
+
+    #[test]
+    fn test_rest_snapshot_seeds_book_for_deltas() {
+        let mut book = LocalOrderBook::new();
+        book.apply_rest_snapshot([("1.0", "9")], [("0.1", "9")]);
+        book.apply_rest_snapshot(
+            vec![
+                ("101.0".to_string(), "3".to_string()),
+                ("100.5".to_string(), "1".to_string()),
+            ],
+            vec![
+                ("100.0".to_string(), "0.000".to_string()),
+                ("99.0".to_string(), "2".to_string()),
+            ],
+        );
+        assert_eq!(
+            book.to_json(),
+            serde_json::json!({"asks": [["100.5", "1"], ["101.0", "3"]], "bids": [["99.0", "2"]]})
+        );
+
+        let update = r#"[1, {"a": [["100.5", "0.00000000", "1"]], "b": [["99.5", "4", "1"]]}, "book-10", "XBT/USD"]"#;
+        book.update(&parse_book(update).unwrap());
+        assert_eq!(book.best_ask(), Some((101.0, 3.0)));
+        assert_eq!(book.best_bid(), Some((99.5, 4.0)));
+    }
+
+    #[test]
+    fn test_display_summaries() {
+        let candle = Candle {
+            open: 100.0,
+            high: 105.0,
+            low: 99.5,
+            close: 104.0,
+            volume: 12.34567,
+            ..Default::default()
+        };
+        assert_eq!(
+            candle.to_string(),
+            "O: 100.00 H: 105.00 L: 99.50 C: 104.00 V: 12.3457"
+        );
+        assert!(format!("{:.1}", candle).starts_with("O: 100.0 H: 105.0"));
+
+        let snapshot = r#"[1, {"as": [["101.0", "3", "0"], ["100.5", "1", "0"]], "bs": [["99.0", "1", "0"]]}, "book-10", "XBT/USD"]"#;
+        let update = r#"[1, {"a": [["101.0", "0", "1"]]}, "book-10", "XBT/USD"]"#;
+        assert_eq!(
+            parse_book(snapshot).unwrap().to_string(),
+            "SNAPSHOT XBT/USD: 2 asks / 1 bids"
+        );
+        assert_eq!(
+            parse_book(update).unwrap().to_string(),
+            "UPDATE XBT/USD: 1 asks / 0 bids"
+        );
+    }
+
+    #[test]
+    fn test_depth_limited_book_truncates_both_sides() {
+        let mut book = LocalOrderBook::with_depth(3);
+        let snapshot = r#"[1, {"as": [["105.0", "1", "0"], ["101.0", "1", "0"], ["102.0", "1", "0"], ["103.0", "1", "0"], ["104.0", "1", "0"]], "bs": [["96.0", "1", "0"], ["100.0", "1", "0"], ["99.0", "1", "0"], ["97.0", "1", "0"], ["98.0", "1", "0"]]}, "book-3", "XBT/USD"]"#;
+        book.update(&parse_book(snapshot).unwrap());
+        let levels = |side: &BTreeMap<PriceKey, String>| -> Vec<String> {
+            side.keys().map(|p| p.to_string()).collect()
+        };
+        assert_eq!(levels(&book.asks), ["101.0", "102.0", "103.0"]);
+        assert_eq!(levels(&book.bids), ["98.0", "99.0", "100.0"]);
+
+        // Better levels push the worst ones out; they must not come back on deletion
+        let update =
+            r#"[1, {"a": [["100.5", "2", "1"]], "b": [["100.2", "2", "1"]]}, "book-3", "XBT/USD"]"#;
+        book.update(&parse_book(update).unwrap());
+        assert_eq!(levels(&book.asks), ["100.5", "101.0", "102.0"]);
+        assert_eq!(levels(&book.bids), ["99.0", "100.0", "100.2"]);
+        let delete = r#"[1, {"a": [["100.5", "0", "2"]]}, "book-3", "XBT/USD"]"#;
+        book.update(&parse_book(delete).unwrap());
+        assert_eq!(levels(&book.asks), ["101.0", "102.0"]);
+
+        let mut unlimited = LocalOrderBook::new();
+        unlimited.update(&parse_book(snapshot).unwrap());
+        assert_eq!(unlimited.asks.len(), 5);
+        assert_eq!(unlimited.depth(), None);
+    }
+
+    #[test]
+    fn test_top_levels_best_first() {
+        let snapshot = r#"[1, {"as": [["102.0", "2", "0"], ["100.5", "1", "0"], ["101.0", "3", "0"]], "bs": [["99.0", "1", "0"], ["100.0", "4", "0"], ["98.5", "2", "0"]]}, "book-10", "XBT/USD"]"#;
+        let mut book = LocalOrderBook::new();
+        book.update(&parse_book(snapshot).unwrap());
+
+        let top = book.top(2);
+        assert_eq!(
+            top.asks,
+            vec![
+                Level {
+                    price: 100.5,
+                    volume: 1.0
+                },
+                Level {
+                    price: 101.0,
+                    volume: 3.0
+                }
+            ]
+        );
+        assert_eq!(top.bids[0].price, 100.0);
+        assert_eq!(top.bids[1].price, 99.0);
+        assert_eq!(
+            serde_json::to_value(book.top(1)).unwrap(),
+            serde_json::json!({
+                "asks": [{"price": 100.5, "volume": 1.0}],
+                "bids": [{"price": 100.0, "volume": 4.0}],
+            })
+        );
+        assert_eq!(LocalOrderBook::new().top(5), BookSnapshot::default());
+    }
+
+    #[test]
+    fn test_book_json_round_trip() {
+        let snapshot = r#"[1, {"as": [["101.0", "3.0", "0"], ["100.5", "1.10", "0"]], "bs": [["99.0", "1", "0"], ["100.0", "4", "0"]]}, "book-10", "XBT/USD"]"#;
+        let mut book = LocalOrderBook::new();
+        book.update(&parse_book(snapshot).unwrap());
+
+        let json = book.to_json();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "asks": [["100.5", "1.10"], ["101.0", "3.0"]],
+                "bids": [["100.0", "4"], ["99.0", "1"]],
+            })
+        );
+        let restored = LocalOrderBook::from_json(&json).unwrap();
+        assert_eq!(restored.to_json(), json);
+        assert_eq!(restored.calculate_checksum(), book.calculate_checksum());
+        assert!(LocalOrderBook::from_json(&serde_json::json!({"asks": []})).is_err());
+    }
+
+    #[test]
+    fn test_book_imbalance_and_depth_queries() {
+        let empty = LocalOrderBook::new();
+        assert_eq!(empty.imbalance(10), 0.5);
+        assert_eq!(empty.volume_within(1.0), (0.0, 0.0));
+        assert_eq!(empty.depth_at(100.0), None);
+
+        let mut book = LocalOrderBook::new();
+        for (price, volume) in [("101.0", "1.0"), ("102.0", "2.0"), ("110.0", "5.0")] {
+            book.asks.insert(price.into(), volume.to_string());
+        }
+        for (price, volume) in [("99.0", "3.0"), ("98.0", "4.0"), ("90.0", "8.0")] {
+            book.bids.insert(price.into(), volume.to_string());
+        }
+
+        // Top 1: bid 3 vs ask 1; top 2: 7 vs 3
+        assert_eq!(book.imbalance(1), 0.75);
+        assert_eq!(book.imbalance(2), 0.7);
+
+        // Mid is 100, a 2.5% band is 97.5..=102.5
+        assert_eq!(book.volume_within(2.5), (7.0, 3.0));
+        assert_eq!(book.volume_within(0.0), (0.0, 0.0));
+
+        assert_eq!(book.depth_at(102.0), Some(2.0));
+        assert_eq!(book.depth_at(90.0), Some(8.0));
+        assert_eq!(book.depth_at(100.0), None);
+    }
+
+    #[test]
+    fn test_book_fair_price_estimators() {
+        let mut book = LocalOrderBook::new();
+        assert_eq!(book.microprice(), None);
+        book.bids.insert("99.0".into(), "3.0".to_string());
+        assert_eq!(book.best_bid(), Some((99.0, 3.0)));
+        assert_eq!(book.mid(), None);
+        assert_eq!(book.weighted_mid(5), None);
+
+        book.bids.insert("98.0".into(), "1.0".to_string());
+        book.asks.insert("101.0".into(), "1.0".to_string());
+        book.asks.insert("103.0".into(), "3.0".to_string());
+        assert_eq!(book.best_ask(), Some((101.0, 1.0)));
+        assert_eq!(book.mid(), Some(100.0));
+
+        // Heavier bid pulls the fair price towards the ask: (101*3 + 99*1) / 4
+        assert_eq!(book.microprice(), Some(100.5));
+        assert_eq!(book.weighted_mid(1), book.microprice());
+
+        // Bid VWAP 98.75 (vol 4), ask VWAP 102.5 (vol 4): (102.5*4 + 98.75*4) / 8
+        assert_eq!(book.weighted_mid(2), Some(100.625));
+    }
+
+    #[test]
+    fn test_price_key_orders_numerically() {
+        let mut prices: Vec<PriceKey> = ["100.0", "99.5", "1000", "0.05", "99.45", "0.5"]
+            .into_iter()
+            .map(PriceKey::from)
+            .collect();
+        prices.sort();
+        let sorted: Vec<&str> = prices.iter().map(|p| p.as_str()).collect();
+        assert_eq!(sorted, ["0.05", "0.5", "99.45", "99.5", "100.0", "1000"]);
+        assert_eq!(PriceKey::from("100.0"), PriceKey::from("100.00000"));
+    }
+
+    include!("../benches/support/reference_checksum.rs");
+
+    #[test]
+    fn test_checksum_matches_reference_across_magnitudes() {
+        let mut book = LocalOrderBook::new();
+        // Prices straddle 100 so lexicographic order would differ from numeric order
+        for i in 0..15 {
+            let ask = format!("{:.5}", 99.5 + i as f64 * 0.25);
+            let bid = format!("{:.5}", 99.0 - i as f64 * 0.25);
+            book.asks.insert(ask.into(), format!("0.{:08}", i + 1));
+            book.bids.insert(bid.into(), format!("{}.50000000", i));
+        }
+        assert_eq!(book.calculate_checksum(), reference_checksum(&book));
+        assert!(book.validate_checksum(&reference_checksum(&book).to_string()));
+    }
+
+    #[test]
+    fn test_free_checksum_matches_book_method() {
+        let mut book = LocalOrderBook::new();
+        let asks: Vec<(String, String)> = (0..12)
+            .map(|i| (format!("{}.5", 100 + i), format!("0.{:03}00000", i + 1)))
+            .collect();
+        let bids: Vec<(String, String)> = (0..12)
+            .map(|i| (format!("{}.0", 100 - i), "1.00000000".to_string()))
+            .collect();
+        for (price, volume) in &asks {
+            book.asks.insert(price.as_str().into(), volume.clone());
+        }
+        for (price, volume) in &bids {
+            book.bids.insert(price.as_str().into(), volume.clone());
+        }
+
+        fn as_refs(levels: &[(String, String)]) -> Vec<(&str, &str)> {
+            levels
+                .iter()
+                .map(|(p, v)| (p.as_str(), v.as_str()))
+                .collect()
         }
+        let checksum = kraken_checksum(&as_refs(&asks), &as_refs(&bids));
+        assert_eq!(checksum, book.calculate_checksum());
+        assert_eq!(checksum, reference_checksum(&book));
+        // Levels beyond the top 10 do not matter
+        assert_eq!(
+            kraken_checksum(&as_refs(&asks[..10]), &as_refs(&bids[..10])),
+            checksum
+        );
     }
 }
@@ -0,0 +1,146 @@
+use serde::Serialize;
+
+/// Parameters for placing an order over the WebSocket (`addOrder`).
+///
+/// Prices and volumes are strings, exactly as Kraken expects them, so no precision is
+/// lost in transit. The reply arrives as a
+/// [`KrakenEvent::AddOrderStatus`](crate::models::KrakenEvent::AddOrderStatus).
+#[derive(Debug, Clone, Serialize)]
+pub struct AddOrderRequest {
+    #[serde(rename = "ordertype")]
+    pub order_type: String, // "market", "limit", ...
+    #[serde(rename = "type")]
+    pub side: String, // "buy" or "sell"
+    pub pair: String,
+    pub volume: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub userref: Option<String>,
+}
+
+impl AddOrderRequest {
+    /// A limit order for `volume` of `pair` at `price`.
+    pub fn limit(side: &str, pair: &str, volume: &str, price: &str) -> Self {
+        Self {
+            order_type: "limit".to_string(),
+            side: side.to_string(),
+            pair: pair.to_string(),
+            volume: volume.to_string(),
+            price: Some(price.to_string()),
+            userref: None,
+        }
+    }
+
+    /// A market order for `volume` of `pair`.
+    pub fn market(side: &str, pair: &str, volume: &str) -> Self {
+        Self {
+            order_type: "market".to_string(),
+            side: side.to_string(),
+            pair: pair.to_string(),
+            volume: volume.to_string(),
+            price: None,
+            userref: None,
+        }
+    }
+
+    /// Tags the order with a user reference id.
+    pub fn userref(mut self, userref: impl Into<String>) -> Self {
+        self.userref = Some(userref.into());
+        self
+    }
+
+    /// Builds the `addOrder` message, authenticated with a WebSocket `token`.
+    pub(crate) fn to_message(&self, token: &str, reqid: u64) -> serde_json::Value {
+        let mut msg = serde_json::to_value(self).expect("AddOrderRequest serializes to JSON");
+        msg["event"] = serde_json::json!("addOrder");
+        msg["token"] = serde_json::json!(token);
+        msg["reqid"] = serde_json::json!(reqid);
+        msg
+    }
+}
+
+/// Builds a `cancelOrder` message for the order `txid`.
+pub(crate) fn cancel_order_message(txid: &str, token: &str, reqid: u64) -> serde_json::Value {
+    serde_json::json!({
+        "event": "cancelOrder",
+        "token": token,
+        "reqid": reqid,
+        "txid": [txid],
+    })
+}
+
+/// Builds a `cancelAll` message.
+pub(crate) fn cancel_all_message(token: &str, reqid: u64) -> serde_json::Value {
+    serde_json::json!({
+        "event": "cancelAll",
+        "token": token,
+        "reqid": reqid,
+    })
+}
+
+/// Builds a `cancelAllOrdersAfter` message; a `timeout` of 0 disables the timer.
+pub(crate) fn cancel_all_after_message(
+    timeout: u64,
+    token: &str,
+    reqid: Option<u64>,
+) -> serde_json::Value {
+    let mut msg = serde_json::json!({
+        "event": "cancelAllOrdersAfter",
+        "token": token,
+        "timeout": timeout,
+    });
+    if let Some(reqid) = reqid {
+        msg["reqid"] = serde_json::json!(reqid);
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_order_message() {
+        let msg = AddOrderRequest::limit("buy", "XBT/USD", "0.01", "9000.0")
+            .userref("42")
+            .to_message("token123", 7);
+        assert_eq!(
+            msg,
+            serde_json::json!({
+                "event": "addOrder",
+                "token": "token123",
+                "reqid": 7,
+                "ordertype": "limit",
+                "type": "buy",
+                "pair": "XBT/USD",
+                "volume": "0.01",
+                "price": "9000.0",
+                "userref": "42"
+            })
+        );
+
+        let market = AddOrderRequest::market("sell", "XBT/USD", "1").to_message("t", 8);
+        assert!(market.get("price").is_none());
+        assert!(market.get("userref").is_none());
+    }
+
+    #[test]
+    fn test_cancel_messages() {
+        assert_eq!(
+            cancel_order_message("OGTT3Y-C6I3P-XRI6HX", "t", 9),
+            serde_json::json!({
+                "event": "cancelOrder",
+                "token": "t",
+                "reqid": 9,
+                "txid": ["OGTT3Y-C6I3P-XRI6HX"]
+            })
+        );
+        assert_eq!(cancel_all_message("t", 10)["event"], "cancelAll");
+        assert_eq!(
+            cancel_all_after_message(60, "t", None),
+            serde_json::json!({"event": "cancelAllOrdersAfter", "token": "t", "timeout": 60})
+        );
+        assert_eq!(cancel_all_after_message(0, "t", Some(11))["reqid"], 11);
+    }
+}
@@ -0,0 +1,74 @@
+//! Asset pair naming.
+//!
+//! Kraken's v1 API uses its own asset codes: `XBT` for Bitcoin and `XDG` for Dogecoin.
+//! Subscribing to `BTC/USD` on v1 produces only a `subscriptionStatus` error and no data.
+
+/// (common symbol, Kraken v1 symbol)
+const ALIASES: &[(&str, &str)] = &[("BTC", "XBT"), ("DOGE", "XDG")];
+
+/// Converts a pair as users tend to type it into Kraken's canonical v1 form.
+///
+/// Uppercases, accepts `-` or `_` as the separator and maps aliases such as
+/// `BTC` → `XBT`. Input without a separator is only uppercased and aliased as a whole.
+///
+/// ```rust
+/// use kraken_sdk::pairs::normalize_pair;
+///
+/// assert_eq!(normalize_pair("btc-usd"), "XBT/USD");
+/// assert_eq!(normalize_pair("DOGE/EUR"), "XDG/EUR");
+/// assert_eq!(normalize_pair("XBT/USD"), "XBT/USD");
+/// ```
+pub fn normalize_pair(input: &str) -> String {
+    map_assets(input, |asset| {
+        ALIASES
+            .iter()
+            .find(|(common, _)| *common == asset)
+            .map(|(_, kraken)| *kraken)
+    })
+}
+
+/// Converts a Kraken pair into the commonly used symbols, for display
+/// (and for the v2 API, which uses them natively).
+///
+/// ```rust
+/// use kraken_sdk::pairs::display_pair;
+///
+/// assert_eq!(display_pair("XBT/USD"), "BTC/USD");
+/// assert_eq!(display_pair("XDG/EUR"), "DOGE/EUR");
+/// ```
+pub fn display_pair(pair: &str) -> String {
+    map_assets(pair, |asset| {
+        ALIASES
+            .iter()
+            .find(|(_, kraken)| *kraken == asset)
+            .map(|(common, _)| *common)
+    })
+}
+
+fn map_assets(pair: &str, alias: impl Fn(&str) -> Option<&'static str>) -> String {
+    let pair = pair.trim().to_uppercase().replace(['-', '_'], "/");
+    pair.split('/')
+        .map(|asset| alias(asset).unwrap_or(asset))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_and_display_round_trip() {
+        for (typed, kraken) in [
+            ("BTC/USD", "XBT/USD"),
+            (" eth_btc ", "ETH/XBT"),
+            ("doge/usd", "XDG/USD"),
+            ("btc", "XBT"),
+            ("SOL/EUR", "SOL/EUR"),
+        ] {
+            assert_eq!(normalize_pair(typed), kraken);
+        }
+        assert_eq!(display_pair("ETH/XBT"), "ETH/BTC");
+        assert_eq!(display_pair(&normalize_pair("doge-btc")), "DOGE/BTC");
+    }
+}
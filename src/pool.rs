@@ -0,0 +1,207 @@
+use crate::builder::KrakenClientBuilder;
+use crate::error::Result;
+use crate::models::KrakenEvent;
+use crate::{KrakenClient, PendingResponse, SubscriptionArgs};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Spreads subscriptions over several connections, for consumers that need more pairs
+/// than Kraken allows on one socket.
+///
+/// Each connection is a regular [`KrakenClient`] with its own driver, so it reconnects
+/// on its own and replays exactly the subscriptions assigned to it. Their events are
+/// merged into one broadcast channel; connection-level events such as
+/// [`KrakenEvent::ConnectionState`] arrive once per connection.
+///
+/// New pairs go to the connection with the fewest subscriptions. A pair stays on its
+/// connection until it is unsubscribed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use kraken_sdk::pool::ConnectionPool;
+/// # async fn example(pairs: Vec<String>) -> kraken_sdk::error::Result<()> {
+/// let pool = ConnectionPool::new(4);
+/// let mut rx = pool.subscribe_events();
+/// pool.connect().await?;
+/// pool.subscribe(pairs, "ticker").await?;
+/// while let Ok(event) = rx.recv().await {
+///     if let Some(ticker) = event.try_into_ticker_data() {
+///         println!("{}: {:?}", ticker.pair, ticker.ticker.last_price());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ConnectionPool {
+    clients: Vec<KrakenClient>,
+    event_sender: broadcast::Sender<KrakenEvent>,
+    // Taken by connect(); subscribed at construction so no early event is missed
+    receivers: Mutex<Vec<broadcast::Receiver<KrakenEvent>>>,
+    // (pair, channel name) -> index of the connection carrying it
+    assignments: Mutex<HashMap<(String, String), usize>>,
+}
+
+impl ConnectionPool {
+    /// A pool of `size` connections (at least one) with the default client settings.
+    pub fn new(size: usize) -> Self {
+        Self::from_builder(KrakenClient::builder(), size)
+    }
+
+    /// A pool of `size` connections (at least one), each built from `builder`.
+    ///
+    /// The merged event channel gets the builder's
+    /// [`event_buffer`](KrakenClientBuilder::event_buffer) capacity times `size`.
+    pub fn from_builder(builder: KrakenClientBuilder, size: usize) -> Self {
+        let size = size.max(1);
+        let (event_sender, _) = broadcast::channel(builder.event_buffer_capacity() * size);
+        let clients: Vec<KrakenClient> = (0..size).map(|_| builder.clone().build()).collect();
+        let receivers = clients.iter().map(|c| c.subscribe_events()).collect();
+        Self {
+            clients,
+            event_sender,
+            receivers: Mutex::new(receivers),
+            assignments: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns a receiver for the events of all connections.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<KrakenEvent> {
+        self.event_sender.subscribe()
+    }
+
+    /// Connects every client and starts merging their events.
+    ///
+    /// The returned handles are the forwarding tasks; they finish once their client
+    /// is dropped.
+    pub async fn connect(&self) -> Result<Vec<JoinHandle<()>>> {
+        let receivers = std::mem::take(&mut *self.receivers.lock().unwrap());
+        let forwarders = receivers
+            .into_iter()
+            .map(|rx| tokio::spawn(forward(rx, self.event_sender.clone())))
+            .collect();
+        for client in &self.clients {
+            client.connect().await?;
+        }
+        Ok(forwarders)
+    }
+
+    /// Subscribes `pairs` to channel `name`, see [`KrakenClient::subscribe`].
+    pub async fn subscribe(&self, pairs: Vec<String>, name: &str) -> Result<Vec<PendingResponse>> {
+        self.subscribe_with(pairs, SubscriptionArgs::new(name))
+            .await
+    }
+
+    /// Subscribes with the full set of options, see [`KrakenClient::subscribe_with`].
+    ///
+    /// Returns one pending response per connection the pairs were sent to. Pairs
+    /// already subscribed to this channel are sent again on their own connection.
+    pub async fn subscribe_with(
+        &self,
+        pairs: Vec<String>,
+        subscription: SubscriptionArgs,
+    ) -> Result<Vec<PendingResponse>> {
+        let groups = self.assign(pairs, &subscription.name);
+        let mut pending = Vec::with_capacity(groups.len());
+        for (index, pairs) in groups {
+            let response = self.clients[index]
+                .subscribe_with(pairs, subscription.clone())
+                .await?;
+            pending.push(response);
+        }
+        Ok(pending)
+    }
+
+    /// Unsubscribes `pairs` from channel `name` on the connections carrying them.
+    ///
+    /// Pairs this pool never subscribed are ignored.
+    pub async fn unsubscribe(
+        &self,
+        pairs: Vec<String>,
+        name: &str,
+    ) -> Result<Vec<PendingResponse>> {
+        let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+        {
+            let mut assignments = self.assignments.lock().unwrap();
+            for pair in pairs {
+                if let Some(index) = assignments.remove(&(pair.clone(), name.to_string())) {
+                    groups.entry(index).or_default().push(pair);
+                }
+            }
+        }
+        let mut pending = Vec::with_capacity(groups.len());
+        for (index, pairs) in groups {
+            pending.push(self.clients[index].unsubscribe(pairs, name).await?);
+        }
+        Ok(pending)
+    }
+
+    /// Index of the connection carrying `pair` on channel `name`.
+    pub fn connection_for(&self, pair: &str, name: &str) -> Option<usize> {
+        self.assignments
+            .lock()
+            .unwrap()
+            .get(&(pair.to_string(), name.to_string()))
+            .copied()
+    }
+
+    /// The pooled clients, e.g. to read each connection's
+    /// [`metrics`](KrakenClient::metrics).
+    pub fn clients(&self) -> &[KrakenClient] {
+        &self.clients
+    }
+
+    /// Number of connections in the pool.
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Groups `pairs` by connection, placing new ones on the least loaded connection.
+    fn assign(&self, pairs: Vec<String>, name: &str) -> Vec<(usize, Vec<String>)> {
+        let mut assignments = self.assignments.lock().unwrap();
+        let mut load = vec![0usize; self.clients.len()];
+        for index in assignments.values() {
+            load[*index] += 1;
+        }
+        let mut groups: Vec<(usize, Vec<String>)> = Vec::new();
+        for pair in pairs {
+            let key = (pair.clone(), name.to_string());
+            let index = match assignments.get(&key) {
+                Some(index) => *index,
+                None => {
+                    let index = (0..load.len()).min_by_key(|i| load[*i]).unwrap_or(0);
+                    load[index] += 1;
+                    assignments.insert(key, index);
+                    index
+                }
+            };
+            match groups.iter_mut().find(|(i, _)| *i == index) {
+                Some((_, group)) => group.push(pair),
+                None => groups.push((index, vec![pair])),
+            }
+        }
+        groups
+    }
+}
+
+/// Copies one connection's events into the merged channel until the client is dropped.
+async fn forward(mut rx: broadcast::Receiver<KrakenEvent>, merged: broadcast::Sender<KrakenEvent>) {
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                let _ = merged.send(event);
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Connection pool lagged behind, skipped {} events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
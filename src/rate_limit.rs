@@ -0,0 +1,105 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Token bucket spacing out outgoing messages.
+///
+/// Holds up to `per_second` tokens (so short bursts go out immediately) and refills
+/// continuously at `per_second` tokens per second.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(per_second: u32, now: Instant) -> Self {
+        let rate = f64::from(per_second.max(1));
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+
+    /// How long until a token is available (zero if one is available now).
+    pub(crate) fn wait_time(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+
+    /// Consumes a token. Call only after `wait_time` returned zero.
+    pub(crate) fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+/// Whether a close reason or `errorMessage` says Kraken is throttling this client, e.g.
+/// `"Exceeded msg rate"` or `"Rate limit exceeded"`.
+pub(crate) fn is_rate_limit_message(text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    [
+        "exceeded msg rate",
+        "rate limit exceeded",
+        "too many requests",
+        "throttl",
+    ]
+    .iter()
+    .any(|indicator| text.contains(indicator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_spaces_out() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+
+        for _ in 0..2 {
+            assert_eq!(bucket.wait_time(start), Duration::ZERO);
+            bucket.take();
+        }
+        assert_eq!(bucket.wait_time(start), Duration::from_millis(500));
+
+        let later = start + Duration::from_millis(500);
+        assert_eq!(bucket.wait_time(later), Duration::ZERO);
+        bucket.take();
+        assert!(bucket.wait_time(later) > Duration::ZERO);
+
+        // Idle time never banks more than one second's worth of tokens
+        let much_later = later + Duration::from_secs(60);
+        bucket.wait_time(much_later);
+        for _ in 0..2 {
+            assert_eq!(bucket.wait_time(much_later), Duration::ZERO);
+            bucket.take();
+        }
+        assert!(bucket.wait_time(much_later) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rate_limit_messages_are_recognized() {
+        assert!(is_rate_limit_message("Exceeded msg rate"));
+        assert!(is_rate_limit_message("EGeneral:Too many requests"));
+        assert!(is_rate_limit_message("Subscription rate limit exceeded"));
+        assert!(!is_rate_limit_message(
+            "Currency pair not supported XBT/XYZ"
+        ));
+        assert!(!is_rate_limit_message("EOrder:Orders limit exceeded"));
+        assert!(!is_rate_limit_message("EOrder:Positions limit exceeded"));
+        assert!(!is_rate_limit_message(""));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::error::{KrakenError, Result};
+use crate::models::KrakenEvent;
+use futures_util::future::BoxFuture;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, Mutex};
+
+/// A destination for parsed events, registered with
+/// [`KrakenClient::add_sink`](crate::KrakenClient::add_sink).
+///
+/// Unlike [`record_to`](crate::KrakenClientBuilder::record_to), which keeps the raw
+/// frames, a sink receives every broadcast event in its typed form, including the ones
+/// the client generates itself (e.g. [`KrakenEvent::ConnectionState`]).
+pub trait MessageSink: Send + Sync {
+    /// Persists one event. An error is logged by the client; later events are still written.
+    fn write<'a>(&'a self, event: &'a KrakenEvent) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Appends every event to a file as one JSON object per line.
+///
+/// Each line is flushed before the next event is taken, so the file is complete up to
+/// the last event written even if the process dies.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use kraken_sdk::{sink::JsonlSink, KrakenClient};
+/// # async fn example() -> kraken_sdk::error::Result<()> {
+/// let client = KrakenClient::new();
+/// client.add_sink(JsonlSink::create("events.jsonl").await?);
+/// client.connect().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JsonlSink {
+    file: Mutex<tokio::fs::File>,
+}
+
+impl JsonlSink {
+    /// Opens `path` for appending, creating it if needed.
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl MessageSink for JsonlSink {
+    fn write<'a>(&'a self, event: &'a KrakenEvent) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(event)?;
+            line.push(b'\n');
+            let mut file = self.file.lock().await;
+            file.write_all(&line).await?;
+            file.flush().await?;
+            Ok(())
+        })
+    }
+}
+
+/// Forwards every event into an `mpsc` channel, e.g. to a database writer task.
+///
+/// A full channel makes the sink wait, which holds back only this sink. Once the
+/// receiver is dropped, writes fail with [`KrakenError::ChannelClosed`].
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    sender: mpsc::Sender<KrakenEvent>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: mpsc::Sender<KrakenEvent>) -> Self {
+        Self { sender }
+    }
+}
+
+impl MessageSink for ChannelSink {
+    fn write<'a>(&'a self, event: &'a KrakenEvent) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.sender
+                .send(event.clone())
+                .await
+                .map_err(|_| KrakenError::ChannelClosed)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_jsonl_sink_writes_one_event_per_line() {
+        let path = std::env::temp_dir().join(format!("kraken_sink_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = JsonlSink::create(&path).await.unwrap();
+        let trade: KrakenEvent = serde_json::from_str(
+            r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","XBT/USD"]"#,
+        )
+        .unwrap();
+        sink.write(&trade).await.unwrap();
+        sink.write(&KrakenEvent::ChecksumMismatch {
+            pair: "XBT/USD".to_string(),
+        })
+        .await
+        .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["Channel"]["Trade"]["data"][0]["price"], "5541.2");
+        assert_eq!(lines[1]["ChecksumMismatch"]["pair"], "XBT/USD");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+use crate::error::{KrakenError, Result};
+use futures_util::future::BoxFuture;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::StreamExt;
+use std::collections::VecDeque;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
+use tracing::error;
+
+/// A source of inbound text frames, fed through the client's parse and broadcast
+/// pipeline by [`KrakenClient::run_source`](crate::KrakenClient::run_source).
+///
+/// The in-memory sources make it possible to test event handling with canned frames
+/// instead of a live connection.
+pub trait MessageSource: Send {
+    /// Returns the next text frame, or `None` once the source is exhausted.
+    fn next_frame(&mut self) -> BoxFuture<'_, Option<String>>;
+}
+
+impl<S: MessageSource + ?Sized> MessageSource for &mut S {
+    fn next_frame(&mut self) -> BoxFuture<'_, Option<String>> {
+        (**self).next_frame()
+    }
+}
+
+/// Yields a fixed list of frames in order.
+///
+/// # Example
+///
+/// ```rust
+/// # use kraken_sdk::{source::VecSource, KrakenClient};
+/// # #[tokio::main]
+/// # async fn main() {
+/// let client = KrakenClient::new();
+/// let mut rx = client.subscribe_events();
+/// let source = VecSource::new([r#"{"event":"heartbeat"}"#]);
+/// assert_eq!(client.run_source(source).await, 1);
+/// assert!(rx.recv().await.is_ok());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VecSource {
+    frames: VecDeque<String>,
+}
+
+impl VecSource {
+    pub fn new<I, S>(frames: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            frames: frames.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl MessageSource for VecSource {
+    fn next_frame(&mut self) -> BoxFuture<'_, Option<String>> {
+        Box::pin(async move { self.frames.pop_front() })
+    }
+}
+
+/// Yields frames pushed into an `mpsc` channel, ending when every sender is dropped.
+///
+/// Useful when a test needs to interleave frames with assertions.
+#[derive(Debug)]
+pub struct ChannelSource {
+    receiver: mpsc::Receiver<String>,
+}
+
+impl ChannelSource {
+    pub fn new(receiver: mpsc::Receiver<String>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl MessageSource for ChannelSource {
+    fn next_frame(&mut self) -> BoxFuture<'_, Option<String>> {
+        Box::pin(self.receiver.recv())
+    }
+}
+
+/// Yields the lines of a recording made with
+/// [`record_to`](crate::KrakenClientBuilder::record_to), skipping blank ones.
+///
+/// A read error ends the source; it is kept for [`take_error`](Self::take_error).
+pub(crate) struct RecordingSource {
+    lines: Lines<BufReader<File>>,
+    error: Option<std::io::Error>,
+}
+
+impl RecordingSource {
+    pub(crate) fn new(file: File) -> Self {
+        Self {
+            lines: BufReader::new(file).lines(),
+            error: None,
+        }
+    }
+
+    /// The error that ended the source early, if any.
+    pub(crate) fn take_error(&mut self) -> Option<std::io::Error> {
+        self.error.take()
+    }
+}
+
+impl MessageSource for RecordingSource {
+    fn next_frame(&mut self) -> BoxFuture<'_, Option<String>> {
+        Box::pin(async move {
+            loop {
+                match self.lines.next_line().await {
+                    Ok(Some(line)) if line.trim().is_empty() => continue,
+                    Ok(line) => return line,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return None;
+                    }
+                }
+            }
+        })
+    }
+}
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Yields text frames from a single WebSocket connection.
+///
+/// Unlike [`KrakenClient::connect`](crate::KrakenClient::connect), this does not reconnect,
+/// ping or replay subscriptions: the source ends when the connection closes or errors.
+/// Send subscriptions through [`sink`](Self::sink) before running it.
+pub struct WsSource {
+    stream: SplitStream<WsStream>,
+    sink: SplitSink<WsStream, Message>,
+}
+
+impl WsSource {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (ws, _) = crate::tls::connect(url)
+            .await
+            .map_err(|e| KrakenError::Connection(e.to_string()))?;
+        let (sink, stream) = ws.split();
+        Ok(Self { stream, sink })
+    }
+
+    /// The write half of the connection, for sending subscribe requests.
+    pub fn sink(&mut self) -> &mut SplitSink<WsStream, Message> {
+        &mut self.sink
+    }
+}
+
+impl MessageSource for WsSource {
+    fn next_frame(&mut self) -> BoxFuture<'_, Option<String>> {
+        Box::pin(async move {
+            loop {
+                match self.stream.next().await? {
+                    Ok(Message::Text(text)) => return Some(text),
+                    Ok(Message::Close(_)) => return None,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        error!("WS Error: {}", e);
+                        return None;
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_sources_yield_in_order() {
+        let mut source = VecSource::new(["a", "b"]);
+        assert_eq!(source.next_frame().await.as_deref(), Some("a"));
+        assert_eq!(source.next_frame().await.as_deref(), Some("b"));
+        assert_eq!(source.next_frame().await, None);
+
+        let (tx, rx) = mpsc::channel(2);
+        let mut source = ChannelSource::new(rx);
+        tx.send("c".to_string()).await.unwrap();
+        drop(tx);
+        assert_eq!(source.next_frame().await.as_deref(), Some("c"));
+        assert_eq!(source.next_frame().await, None);
+    }
+}
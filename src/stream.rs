@@ -0,0 +1,74 @@
+use crate::models::KrakenEvent;
+use futures_util::Stream;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tracing::warn;
+
+/// A `Stream` of [`KrakenEvent`]s backed by the client's broadcast channel.
+///
+/// Created with [`KrakenClient::event_stream`](crate::KrakenClient::event_stream).
+/// If the consumer falls behind and the channel overwrites events, the missed
+/// events are skipped (and logged) rather than ending the stream.
+pub struct EventStream {
+    inner: BroadcastStream<KrakenEvent>,
+}
+
+impl EventStream {
+    pub(crate) fn new(receiver: broadcast::Receiver<KrakenEvent>) -> Self {
+        Self {
+            inner: BroadcastStream::new(receiver),
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = KrakenEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                Some(Ok(event)) => return Poll::Ready(Some(event)),
+                Some(Err(BroadcastStreamRecvError::Lagged(skipped))) => {
+                    warn!("Event stream lagged, skipped {} events", skipped);
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Heartbeat;
+    use futures_util::StreamExt;
+
+    fn heartbeat(n: usize) -> KrakenEvent {
+        KrakenEvent::Heartbeat(Heartbeat {
+            event: format!("heartbeat-{}", n),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_skips_lagged_events() {
+        let (tx, rx) = broadcast::channel(2);
+        let stream = EventStream::new(rx);
+
+        // Overflow the channel so the receiver lags behind.
+        for n in 0..4 {
+            tx.send(heartbeat(n)).unwrap();
+        }
+        drop(tx);
+
+        let events: Vec<String> = stream
+            .map(|e| match e {
+                KrakenEvent::Heartbeat(h) => h.event,
+                other => panic!("unexpected event: {:?}", other),
+            })
+            .collect()
+            .await;
+        assert_eq!(events, vec!["heartbeat-2", "heartbeat-3"]);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::models::{Side, Trade};
+use std::collections::VecDeque;
+
+/// A fixed-capacity buffer of the most recent trades with running buy/sell volume.
+///
+/// Pushing beyond the capacity evicts the oldest trade, and the volume totals always
+/// cover exactly the trades currently on the tape. Trades whose volume cannot be parsed
+/// are kept for display but do not count towards the totals.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use kraken_sdk::tape::TradeTape;
+/// use kraken_sdk::models::TradeData;
+///
+/// # fn example(trade_data: TradeData) {
+/// let mut tape = TradeTape::new(50);
+/// tape.extend(trade_data.data);
+/// for trade in tape.latest(10) {
+///     println!("{} {} @ {}", trade.side, trade.volume, trade.price);
+/// }
+/// if let Some(ratio) = tape.buy_sell_ratio() {
+///     println!("buy/sell volume: {:.2}", ratio);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TradeTape {
+    trades: VecDeque<Trade>,
+    capacity: usize,
+    buy_volume: f64,
+    sell_volume: f64,
+}
+
+impl TradeTape {
+    /// An empty tape holding at most `capacity` trades (at least one).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            trades: VecDeque::with_capacity(capacity),
+            capacity,
+            buy_volume: 0.0,
+            sell_volume: 0.0,
+        }
+    }
+
+    /// Adds a trade, evicting the oldest one if the tape is full.
+    pub fn push(&mut self, trade: Trade) {
+        if self.trades.len() == self.capacity {
+            if let Some(evicted) = self.trades.pop_front() {
+                self.account(&evicted, -1.0);
+            }
+        }
+        self.account(&trade, 1.0);
+        self.trades.push_back(trade);
+    }
+
+    /// The most recent `n` trades, newest first.
+    pub fn latest(&self, n: usize) -> impl Iterator<Item = &Trade> {
+        self.trades.iter().rev().take(n)
+    }
+
+    /// All trades on the tape, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Trade> {
+        self.trades.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Base volume of the buyer-initiated trades on the tape.
+    pub fn buy_volume(&self) -> f64 {
+        self.buy_volume
+    }
+
+    /// Base volume of the seller-initiated trades on the tape.
+    pub fn sell_volume(&self) -> f64 {
+        self.sell_volume
+    }
+
+    /// Buy volume divided by sell volume, or `None` while there is no sell volume.
+    ///
+    /// Above `1.0` takers have mostly been lifting offers; below it, hitting bids.
+    pub fn buy_sell_ratio(&self) -> Option<f64> {
+        (self.sell_volume > 0.0).then(|| self.buy_volume / self.sell_volume)
+    }
+
+    fn account(&mut self, trade: &Trade, sign: f64) {
+        let Ok(volume) = trade.volume_f64() else {
+            return;
+        };
+        let total = match trade.taker_side() {
+            Some(Side::Buy) => &mut self.buy_volume,
+            Some(Side::Sell) => &mut self.sell_volume,
+            None => return,
+        };
+        // Clamp so float error from repeated add/subtract never goes negative
+        *total = (*total + sign * volume).max(0.0);
+    }
+}
+
+impl Extend<Trade> for TradeTape {
+    fn extend<I: IntoIterator<Item = Trade>>(&mut self, trades: I) {
+        for trade in trades {
+            self.push(trade);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(volume: &str, side: &str) -> Trade {
+        Trade {
+            price: "100.0".to_string(),
+            volume: volume.to_string(),
+            time: "0".to_string(),
+            side: side.to_string(),
+            order_type: "m".to_string(),
+            misc: None,
+            trade_id: None,
+        }
+    }
+
+    #[test]
+    fn test_tape_evicts_oldest_and_keeps_totals() {
+        let mut tape = TradeTape::new(3);
+        assert_eq!(tape.buy_sell_ratio(), None);
+
+        tape.extend([trade("1", "b"), trade("2", "s"), trade("3", "b")]);
+        assert_eq!(tape.buy_volume(), 4.0);
+        assert_eq!(tape.sell_volume(), 2.0);
+        assert_eq!(tape.buy_sell_ratio(), Some(2.0));
+
+        // Evicts the first buy of 1.0
+        tape.push(trade("4", "s"));
+        assert_eq!(tape.len(), 3);
+        assert_eq!(tape.buy_volume(), 3.0);
+        assert_eq!(tape.sell_volume(), 6.0);
+
+        let newest: Vec<&str> = tape.latest(2).map(|t| t.volume.as_str()).collect();
+        assert_eq!(newest, vec!["4", "3"]);
+        let all: Vec<&str> = tape.iter().map(|t| t.volume.as_str()).collect();
+        assert_eq!(all, vec!["2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_unparseable_volume_is_kept_but_not_counted() {
+        let mut tape = TradeTape::new(0);
+        assert_eq!(tape.capacity(), 1);
+
+        tape.push(trade("oops", "b"));
+        assert_eq!(tape.len(), 1);
+        assert_eq!(tape.buy_volume(), 0.0);
+
+        tape.push(trade("2", "b"));
+        assert_eq!(tape.buy_volume(), 2.0);
+        assert_eq!(tape.sell_volume(), 0.0);
+    }
+}
@@ -0,0 +1,61 @@
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{handshake::client::Response, Result};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Connection = (WebSocketStream<MaybeTlsStream<TcpStream>>, Response);
+
+/// Opens a WebSocket connection, using the TLS backend selected by the crate features.
+///
+/// `native-tls` wins when both backends are enabled, so turning it on next to the
+/// default `rustls` switches to the platform library.
+#[cfg(feature = "native-tls")]
+pub(crate) async fn connect(url: &str) -> Result<Connection> {
+    use tokio_tungstenite::tungstenite::error::{Error, TlsError};
+    let connector = native_tls::TlsConnector::new().map_err(|e| Error::Tls(TlsError::Native(e)))?;
+    tokio_tungstenite::connect_async_tls_with_config(
+        url,
+        None,
+        false,
+        Some(tokio_tungstenite::Connector::NativeTls(connector)),
+    )
+    .await
+}
+
+/// Opens a WebSocket connection, using rustls with the webpki root certificates for
+/// `wss://` endpoints.
+#[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+pub(crate) async fn connect(url: &str) -> Result<Connection> {
+    use std::sync::{Arc, OnceLock};
+    // Building the root store is not free, and reconnects reuse it
+    static CONFIG: OnceLock<Arc<rustls::ClientConfig>> = OnceLock::new();
+    let config = CONFIG.get_or_init(|| {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        Arc::new(
+            rustls::ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        )
+    });
+    tokio_tungstenite::connect_async_tls_with_config(
+        url,
+        None,
+        false,
+        Some(tokio_tungstenite::Connector::Rustls(config.clone())),
+    )
+    .await
+}
+
+/// Opens a plain WebSocket connection. Without a TLS feature, `wss://` endpoints (such
+/// as Kraken's) fail to connect.
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+pub(crate) async fn connect(url: &str) -> Result<Connection> {
+    tokio_tungstenite::connect_async(url).await
+}
@@ -0,0 +1,232 @@
+//! Types for Kraken's WebSocket API v2 (`wss://ws.kraken.com/v2`).
+//!
+//! Unlike v1, every v2 message is a JSON object: channel data carries `channel`, `type`
+//! and `data` fields, and request acknowledgements carry `method` and `success`.
+//! Select v2 with [`KrakenClientBuilder::protocol`](crate::KrakenClientBuilder::protocol);
+//! events then arrive as [`KrakenEvent::V2`](crate::models::KrakenEvent::V2).
+//!
+//! Note that v2 uses ISO symbols, e.g. `BTC/USD` instead of v1's `XBT/USD`.
+
+use crate::SubscriptionArgs;
+use serde::{Deserialize, Serialize};
+
+/// Default endpoint for the v2 public API.
+pub const WS_URL_V2: &str = "wss://ws.kraken.com/v2";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum KrakenEventV2 {
+    /// Streaming data and status messages (`{"channel": ..., "type": ..., "data": [...]}`).
+    Channel(ChannelMessage),
+    /// Responses to requests such as `subscribe` or `ping`.
+    Method(MethodResponse),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "channel", rename_all = "lowercase")]
+pub enum ChannelMessage {
+    Heartbeat,
+    Status {
+        data: Vec<StatusV2>,
+    },
+    Trade {
+        #[serde(rename = "type")]
+        kind: MessageType,
+        data: Vec<TradeV2>,
+    },
+    Book {
+        #[serde(rename = "type")]
+        kind: MessageType,
+        data: Vec<BookV2>,
+    },
+    Ticker {
+        #[serde(rename = "type")]
+        kind: MessageType,
+        data: Vec<TickerV2>,
+    },
+    /// A channel this crate does not model yet.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageType {
+    Snapshot,
+    Update,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatusV2 {
+    pub api_version: String,
+    pub connection_id: u64,
+    pub system: String, // "online", "maintenance", ...
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TradeV2 {
+    pub symbol: String,
+    pub side: String, // "buy" or "sell"
+    pub price: f64,
+    pub qty: f64,
+    pub ord_type: String, // "market" or "limit"
+    pub trade_id: u64,
+    pub timestamp: String, // RFC3339
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BookLevelV2 {
+    pub price: f64,
+    pub qty: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BookV2 {
+    pub symbol: String,
+    #[serde(default)]
+    pub bids: Vec<BookLevelV2>,
+    #[serde(default)]
+    pub asks: Vec<BookLevelV2>,
+    pub checksum: u32,
+    pub timestamp: Option<String>, // Only present on updates
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TickerV2 {
+    pub symbol: String,
+    pub bid: f64,
+    pub bid_qty: f64,
+    pub ask: f64,
+    pub ask_qty: f64,
+    pub last: f64,
+    pub volume: f64,
+    pub vwap: f64,
+    pub low: f64,
+    pub high: f64,
+    pub change: f64,
+    pub change_pct: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MethodResponse {
+    pub method: String, // "subscribe", "unsubscribe", "pong", ...
+    pub success: Option<bool>,
+    pub error: Option<String>,
+    pub result: Option<serde_json::Value>,
+    pub req_id: Option<u64>,
+}
+
+/// Builds a v2 `subscribe`/`unsubscribe` request.
+///
+/// v2 has no `ratecounter` parameter, so that option is ignored.
+pub(crate) fn request_message(
+    method: &str,
+    symbols: &[String],
+    subscription: &SubscriptionArgs,
+    req_id: Option<u64>,
+) -> serde_json::Value {
+    let mut params = serde_json::json!({ "channel": subscription.name });
+    if !symbols.is_empty() {
+        params["symbol"] = serde_json::json!(symbols);
+    }
+    if let Some(token) = &subscription.token {
+        params["token"] = serde_json::json!(token);
+    }
+    if let Some(snapshot) = subscription.snapshot {
+        params["snapshot"] = serde_json::json!(snapshot);
+    }
+    if let Some(interval) = subscription.interval {
+        params["interval"] = serde_json::json!(interval);
+    }
+    if let Some(depth) = subscription.depth {
+        params["depth"] = serde_json::json!(depth);
+    }
+    let mut msg = serde_json::json!({ "method": method, "params": params });
+    if let Some(req_id) = req_id {
+        msg["req_id"] = serde_json::json!(req_id);
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_v2_trade() {
+        let msg = r#"{"channel":"trade","type":"update","data":[{"symbol":"BTC/USD","side":"sell","price":64000.5,"qty":0.01,"ord_type":"market","trade_id":4665906,"timestamp":"2024-04-17T10:18:06.123456Z"}]}"#;
+        let event: KrakenEventV2 = serde_json::from_str(msg).unwrap();
+        match event {
+            KrakenEventV2::Channel(ChannelMessage::Trade { kind, data }) => {
+                assert_eq!(kind, MessageType::Update);
+                assert_eq!(data[0].symbol, "BTC/USD");
+                assert_eq!(data[0].trade_id, 4665906);
+            }
+            other => panic!("Expected trade, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_v2_book_snapshot() {
+        let msg = r#"{"channel":"book","type":"snapshot","data":[{"symbol":"BTC/USD","bids":[{"price":64000.1,"qty":1.5}],"asks":[{"price":64000.2,"qty":0.5}],"checksum":2439117997}]}"#;
+        let event: KrakenEventV2 = serde_json::from_str(msg).unwrap();
+        match event {
+            KrakenEventV2::Channel(ChannelMessage::Book { kind, data }) => {
+                assert_eq!(kind, MessageType::Snapshot);
+                assert_eq!(data[0].bids[0].qty, 1.5);
+                assert_eq!(data[0].checksum, 2439117997);
+            }
+            other => panic!("Expected book, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_v2_heartbeat_and_ack() {
+        let heartbeat: KrakenEventV2 = serde_json::from_str(r#"{"channel":"heartbeat"}"#).unwrap();
+        assert!(matches!(
+            heartbeat,
+            KrakenEventV2::Channel(ChannelMessage::Heartbeat)
+        ));
+
+        let ack = r#"{"method":"subscribe","result":{"channel":"trade","symbol":"BTC/USD"},"success":true,"time_in":"2024-04-17T10:18:06.000000Z","time_out":"2024-04-17T10:18:06.000001Z"}"#;
+        match serde_json::from_str::<KrakenEventV2>(ack).unwrap() {
+            KrakenEventV2::Method(resp) => {
+                assert_eq!(resp.method, "subscribe");
+                assert_eq!(resp.success, Some(true));
+            }
+            other => panic!("Expected method response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_request_message_format() {
+        let msg = request_message(
+            "subscribe",
+            &["BTC/USD".to_string()],
+            &SubscriptionArgs::new("trade"),
+            None,
+        );
+        assert_eq!(
+            msg,
+            serde_json::json!({"method": "subscribe", "params": {"channel": "trade", "symbol": ["BTC/USD"]}})
+        );
+    }
+
+    #[test]
+    fn test_request_message_options() {
+        let args = SubscriptionArgs::new("book")
+            .depth(25)
+            .snapshot(false)
+            .ratecounter(true);
+        let msg = request_message("subscribe", &["BTC/USD".to_string()], &args, Some(7));
+        assert_eq!(
+            msg,
+            serde_json::json!({
+                "method": "subscribe",
+                "params": {"channel": "book", "symbol": ["BTC/USD"], "snapshot": false, "depth": 25},
+                "req_id": 7
+            })
+        );
+    }
+}
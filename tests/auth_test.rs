@@ -1,11 +1,66 @@
 #[cfg(test)]
 mod tests {
-    use kraken_sdk::auth::sign_request;
+    use kraken_sdk::auth::{sign_request, Authenticator};
+    use kraken_sdk::KrakenError;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const SECRET: &str =
+        "kQH5HW/8p1uGOVjbgWA7FunAmGO8lsSUXNsu3eow76sz84Q18fWxnyRzBHCd3pd5nE9qa99HAZtuZuj6F1huXg==";
+
+    /// Spawns a one-shot HTTP server that answers any request with `body` as JSON.
+    async fn mock_kraken(body: &'static str) -> String {
+        mock_kraken_sequence(vec![("200 OK", body)]).await
+    }
+
+    /// Spawns an HTTP server that answers successive requests with the given status and body.
+    async fn mock_kraken_sequence(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                // Read until the end of the headers and the full `nonce=...` body have arrived.
+                loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf);
+                    if let Some(idx) = text.find("\r\n\r\n") {
+                        let content_length = text
+                            .lines()
+                            .find_map(|l| {
+                                l.to_ascii_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|v| v.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if buf.len() >= idx + 4 + content_length {
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
 
     #[test]
     fn test_hmac_sha512_signing() {
         // Test with known input/output from Kraken docs (or simulated)
-        let secret = "kQH5HW/8p1uGOVjbgWA7FunAmGO8lsSUXNsu3eow76sz84Q18fWxnyRzBHCd3pd5nE9qa99HAZtuZuj6F1huXg==";
+        let secret = SECRET;
         let nonce = "1616492376594";
         let post_data =
             "nonce=1616492376594&ordertype=limit&pair=XBTUSD&price=37500&type=buy&volume=1.25";
@@ -21,4 +76,194 @@ mod tests {
 
         println!("Generated Signature: {}", signature);
     }
+
+    #[tokio::test]
+    async fn test_validate_reports_invalid_key() {
+        let url = mock_kraken(r#"{"error":["EAPI:Invalid key"]}"#).await;
+        let auth = Authenticator::new("bad-key".to_string(), SECRET.to_string()).with_base_url(url);
+
+        let err = auth.validate().await.expect_err("validate should fail");
+        assert!(
+            matches!(&err, KrakenError::Auth(msg) if msg.starts_with("Invalid API key")),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_invalid_signature() {
+        let url = mock_kraken(r#"{"error":["EAPI:Invalid signature"]}"#).await;
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string()).with_base_url(url);
+
+        let err = auth.validate().await.expect_err("validate should fail");
+        assert!(
+            matches!(&err, KrakenError::Auth(msg) if msg.starts_with("Invalid signature")),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_accepts_valid_credentials() {
+        let url = mock_kraken(r#"{"error":[],"result":{"token":"abc","expires":900}}"#).await;
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string()).with_base_url(url);
+
+        auth.validate().await.expect("credentials should be valid");
+    }
+
+    #[tokio::test]
+    async fn test_with_client_uses_the_given_client() {
+        // A server that accepts the connection but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        });
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let auth = Authenticator::with_client("key".to_string(), SECRET.to_string(), client)
+            .with_base_url(url)
+            .with_retries(0, std::time::Duration::ZERO);
+
+        // The server never answers, so only the client's own timeout can end the request.
+        let started = std::time::Instant::now();
+        let err = auth
+            .get_ws_token()
+            .await
+            .expect_err("request should time out");
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert!(
+            matches!(err, KrakenError::Auth(_)),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_nonces_are_strictly_increasing() {
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string());
+        let mut last = auth.next_nonce();
+        for _ in 0..1000 {
+            let nonce = auth.next_nonce();
+            assert!(
+                nonce > last,
+                "nonce {} did not increase past {}",
+                nonce,
+                last
+            );
+            last = nonce;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_fetch_retries_transient_errors() {
+        let url = mock_kraken_sequence(vec![
+            ("503 Service Unavailable", ""),
+            ("200 OK", r#"{"error":["EService:Unavailable"]}"#),
+            (
+                "200 OK",
+                r#"{"error":[],"result":{"token":"abc123","expires":900}}"#,
+            ),
+        ])
+        .await;
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string())
+            .with_base_url(url)
+            .with_retries(3, std::time::Duration::from_millis(10));
+
+        assert_eq!(auth.get_ws_token().await.unwrap(), "abc123");
+    }
+
+    #[tokio::test]
+    async fn test_token_fetch_gives_up_with_auth_error() {
+        let url = mock_kraken_sequence(vec![
+            ("200 OK", r#"{"error":["EService:Busy"]}"#),
+            ("200 OK", r#"{"error":["EService:Busy"]}"#),
+        ])
+        .await;
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string())
+            .with_base_url(url)
+            .with_retries(1, std::time::Duration::from_millis(10));
+
+        match auth.get_ws_token().await.unwrap_err() {
+            KrakenError::Auth(msg) => {
+                assert!(msg.contains("2 attempt(s)"), "{}", msg);
+                assert!(msg.contains("EService:Busy"), "{}", msg);
+            }
+            other => panic!("expected Auth error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_fetch_does_not_retry_invalid_key() {
+        // Only one response is served, and a retry would wait 5s first.
+        let url = mock_kraken(r#"{"error":["EAPI:Invalid key"]}"#).await;
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string())
+            .with_base_url(url)
+            .with_retries(3, std::time::Duration::from_secs(5));
+
+        let started = std::time::Instant::now();
+        let err = auth.get_ws_token().await.unwrap_err();
+        assert!(err.to_string().contains("Invalid key"), "{}", err);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_token_fetch_does_not_retry_temporary_lockout() {
+        // A retry would be answered with a token, so success means the lockout was retried.
+        let url = mock_kraken_sequence(vec![
+            ("200 OK", r#"{"error":["EGeneral:Temporary lockout"]}"#),
+            (
+                "200 OK",
+                r#"{"error":[],"result":{"token":"abc123","expires":900}}"#,
+            ),
+        ])
+        .await;
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string())
+            .with_base_url(url)
+            .with_retries(3, std::time::Duration::from_millis(10));
+
+        let err = auth.get_ws_token().await.unwrap_err();
+        assert!(err.to_string().contains("Temporary lockout"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_opens_after_consecutive_failures() {
+        let invalid = r#"{"error":["EAPI:Invalid key"]}"#;
+        let ok = r#"{"error":[],"result":{"token":"abc","expires":900}}"#;
+        let url = mock_kraken_sequence(vec![
+            ("200 OK", invalid),
+            ("200 OK", invalid),
+            ("200 OK", ok),
+        ])
+        .await;
+        let (tx, mut fatal) = tokio::sync::mpsc::unbounded_channel();
+        let auth = Authenticator::new("key".to_string(), SECRET.to_string())
+            .with_base_url(url)
+            .with_retries(0, std::time::Duration::ZERO)
+            .with_circuit_breaker(2, std::time::Duration::from_millis(300))
+            .with_status_channel(tx);
+
+        assert!(auth.get_ws_token().await.is_err());
+        assert!(fatal.try_recv().is_err());
+        assert!(auth.get_ws_token().await.is_err());
+        match fatal.try_recv().unwrap() {
+            KrakenError::Auth(msg) => assert!(msg.contains("2 consecutive"), "{}", msg),
+            other => panic!("expected Auth error, got {:?}", other),
+        }
+
+        // Open: fails without using up the server's remaining response
+        let err = auth.get_ws_token().await.unwrap_err();
+        assert!(err.to_string().contains("circuit breaker open"), "{}", err);
+
+        // Half-open: only one of two concurrent requests reaches Kraken
+        tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+        let (probe, concurrent) = tokio::join!(auth.get_ws_token(), auth.get_ws_token());
+        assert_eq!(probe.unwrap(), "abc");
+        let err = concurrent.unwrap_err();
+        assert!(err.to_string().contains("half-open"), "{}", err);
+    }
 }
@@ -0,0 +1,1600 @@
+#[cfg(test)]
+mod tests {
+    use futures_util::{SinkExt, StreamExt};
+    use kraken_sdk::source::{ChannelSource, VecSource};
+    use kraken_sdk::v2::{ChannelMessage, KrakenEventV2};
+    use kraken_sdk::{
+        book::BookMaintainer,
+        models::{ConnectionState, KrakenEvent},
+        pool::ConnectionPool,
+        KrakenClient, KrakenError, ProtocolVersion, ReplayStrategy, SubscriptionArgs,
+    };
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::broadcast;
+    use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+
+    /// Binds a local WebSocket server and returns its URL with the listener.
+    async fn mock_server() -> (String, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        (url, listener)
+    }
+
+    async fn accept(listener: &TcpListener) -> WebSocketStream<tokio::net::TcpStream> {
+        let (socket, _) = listener.accept().await.unwrap();
+        accept_async(socket).await.unwrap()
+    }
+
+    /// Waits for the driver to announce `expected`, failing the test after a timeout.
+    async fn expect_state(rx: &mut broadcast::Receiver<KrakenEvent>, expected: ConnectionState) {
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("no connection state event")
+            .unwrap();
+        match event {
+            KrakenEvent::ConnectionState(state) => assert_eq!(state, expected),
+            other => panic!("expected {:?}, got {:?}", expected, other),
+        }
+    }
+
+    /// Reads the next text frame the client wrote, failing the test after a timeout.
+    async fn next_text(ws: &mut WebSocketStream<tokio::net::TcpStream>) -> String {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+                .await
+                .expect("timed out waiting for a frame")
+                .expect("stream ended")
+                .unwrap();
+            if let Message::Text(text) = msg {
+                return text;
+            }
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_client_subscribes_and_reads_events() {
+        use kraken_sdk::blocking::BlockingKrakenClient;
+
+        // The mock server needs its own runtime; the blocking client brings one too
+        let (url_tx, url_rx) = std::sync::mpsc::channel();
+        let server = std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async move {
+                    let (url, listener) = mock_server().await;
+                    url_tx.send(url).unwrap();
+                    let mut ws = accept(&listener).await;
+                    assert!(next_text(&mut ws).await.contains(r#""event":"subscribe""#));
+                    let trade =
+                        r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","XBT/USD"]"#;
+                    ws.send(Message::Text(trade.to_string())).await.unwrap();
+                    // Keep the socket open until the client has read the trade
+                    let _ = ws.next().await;
+                });
+        });
+
+        let client = KrakenClient::builder()
+            .ws_url(url_rx.recv().unwrap())
+            .build();
+        let mut blocking = BlockingKrakenClient::connect_with(client).unwrap();
+        blocking
+            .subscribe(vec!["XBT/USD".to_string()], "trade")
+            .unwrap();
+
+        let trades = loop {
+            let event = blocking
+                .next_event_timeout(Duration::from_secs(5))
+                .expect("no trade received");
+            if let Some(trades) = event.try_into_trade_data() {
+                break trades;
+            }
+        };
+        assert_eq!(trades.pair, "XBT/USD");
+        assert!(blocking.client().is_connected());
+        drop(blocking);
+        server.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sink_receives_events_and_survives_errors() {
+        use futures_util::future::BoxFuture;
+        use kraken_sdk::sink::{ChannelSink, MessageSink};
+
+        /// Fails every write, to check the client keeps feeding the other sinks.
+        struct FailingSink;
+        impl MessageSink for FailingSink {
+            fn write<'a>(
+                &'a self,
+                _: &'a KrakenEvent,
+            ) -> BoxFuture<'a, kraken_sdk::error::Result<()>> {
+                Box::pin(async { Err(KrakenError::ChannelClosed) })
+            }
+        }
+
+        let client = KrakenClient::new();
+        let (tx, mut archived) = tokio::sync::mpsc::channel(8);
+        client.add_sink(FailingSink);
+        client.add_sink(ChannelSink::new(tx));
+        let source = VecSource::new([
+            r#"{"event":"heartbeat"}"#,
+            r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","XBT/USD"]"#,
+        ]);
+        client.run_source(source).await;
+
+        let timeout = Duration::from_secs(5);
+        let first = tokio::time::timeout(timeout, archived.recv())
+            .await
+            .unwrap();
+        assert!(matches!(first, Some(KrakenEvent::Heartbeat(_))));
+        let second = tokio::time::timeout(timeout, archived.recv())
+            .await
+            .unwrap();
+        assert!(second.unwrap().try_into_trade_data().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_on_connect_sends_and_tracks_subscriptions() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .normalize_pairs(true)
+            .subscribe_on_connect(vec!["btc-usd".to_string()], &["trade", "book"])
+            .build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        for name in ["trade", "book"] {
+            let request = next_text(&mut ws).await;
+            assert!(request.contains(r#""event":"subscribe""#));
+            assert!(request.contains(r#""pair":["XBT/USD"]"#));
+            assert!(request.contains(&format!(r#""name":"{}""#, name)));
+        }
+        expect_state(&mut rx, ConnectionState::Connected).await;
+        assert_eq!(
+            client.active_subscriptions(),
+            vec![
+                (vec!["XBT/USD".to_string()], "trade".to_string()),
+                (vec!["XBT/USD".to_string()], "book".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_typed_streams_skip_other_events() {
+        let client = KrakenClient::new();
+        let mut trades = client.trade_stream();
+        let mut books = client.orderbook_stream();
+        let source = VecSource::new([
+            r#"{"event":"heartbeat"}"#,
+            r#"[1234,{"as":[["5541.30000","2.50700000","1534614248.123678"]],"bs":[["5541.20000","1.52900000","1534614248.765567"]]},"book-10","XBT/USD"]"#,
+            r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","XBT/USD"]"#,
+        ]);
+        client.run_source(source).await;
+
+        let timeout = Duration::from_secs(5);
+        let trade = tokio::time::timeout(timeout, trades.next()).await.unwrap();
+        assert_eq!(trade.unwrap().data[0].price, "5541.2");
+        let book = tokio::time::timeout(timeout, books.next()).await.unwrap();
+        assert!(book.unwrap().is_snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_events_for_pair_skip_other_pairs() {
+        let client = KrakenClient::new();
+        let mut eth = client.subscribe_events_for("ETH/USD");
+        let source = VecSource::new([
+            r#"{"event":"heartbeat"}"#,
+            r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","XBT/USD"]"#,
+            r#"{"channelID":10,"event":"subscriptionStatus","pair":"ETH/USD","status":"subscribed","subscription":{"name":"trade"}}"#,
+            r#"[10,[["3000.1","0.5","1534614057.3","s","l",""]],"trade","ETH/USD"]"#,
+            r#"[11,[["3000.0","3000.2","1534614057.3","1.0","2.0"]],"spread","ETH/USD"]"#,
+        ]);
+        client.run_source(source).await;
+
+        let mut events = Vec::new();
+        for _ in 0..3 {
+            let event = tokio::time::timeout(Duration::from_secs(5), eth.next())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(event.pair(), Some("ETH/USD"));
+            events.push(event);
+        }
+        assert!(matches!(events[0], KrakenEvent::SubscriptionStatus(_)));
+        let trade = events[1].clone().try_into_trade_data().unwrap();
+        assert_eq!(trade.data[0].price, "3000.1");
+        assert!(matches!(events[2], KrakenEvent::Data(_)));
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_trades_broadcasts_closed_candles() {
+        let client = KrakenClient::builder().aggregate_trades(60).build();
+        let mut rx = client.subscribe_events();
+        let source = VecSource::new([
+            r#"[0,[["100.0","1.0","10.0","b","m",""],["102.0","0.5","50.0","s","m",""]],"trade","XBT/USD"]"#,
+            r#"[1,[["3000.0","2.0","20.0","b","m",""]],"trade","ETH/USD"]"#,
+            r#"[0,[["101.0","1.0","65.0","b","m",""]],"trade","XBT/USD"]"#,
+        ]);
+        client.run_source(source).await;
+
+        for _ in 0..3 {
+            assert!(rx.recv().await.unwrap().try_into_trade_data().is_some());
+        }
+        match rx.recv().await.unwrap() {
+            KrakenEvent::CandleClosed { pair, candle } => {
+                assert_eq!(pair, "XBT/USD");
+                assert_eq!(
+                    (candle.start_time, candle.open, candle.close),
+                    (0, 100.0, 102.0)
+                );
+                assert_eq!(candle.count, 2);
+            }
+            other => panic!("expected CandleClosed, got {:?}", other),
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_text_writes_exact_text() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        let raw = r#"{"event":"ping","reqid":42}"#.to_string();
+        client.send_raw_text(raw.clone()).await.unwrap();
+
+        assert_eq!(next_text(&mut ws).await, raw);
+        assert!(client.active_subscriptions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_json_is_not_tracked() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        let raw = serde_json::json!({
+            "event": "subscribe",
+            "pair": ["XBT/USD"],
+            "subscription": {"name": "spread"}
+        });
+        client.send_raw(raw.clone()).await.unwrap();
+
+        let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(sent, raw);
+        assert!(client.active_subscriptions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_requests_resolve_with_their_status() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        let cancel = client
+            .cancel_order("OGTT3Y-C6I3P-XRI6HX", "t")
+            .await
+            .unwrap();
+        let cancel_all = client.cancel_all("t").await.unwrap();
+        for reply in [
+            r#"{"event":"cancelOrderStatus","status":"ok","reqid":REQID}"#,
+            r#"{"count":3,"event":"cancelAllStatus","status":"ok","reqid":REQID}"#,
+        ] {
+            let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+            assert_eq!(sent["token"], "t");
+            let reply = reply.replace("REQID", &sent["reqid"].to_string());
+            ws.send(Message::Text(reply)).await.unwrap();
+        }
+
+        let timeout = Duration::from_secs(5);
+        let reply = tokio::time::timeout(timeout, cancel.response())
+            .await
+            .unwrap();
+        assert!(matches!(reply.unwrap(), KrakenEvent::CancelOrderStatus(s) if s.status == "ok"));
+        let reply = tokio::time::timeout(timeout, cancel_all.response())
+            .await
+            .unwrap();
+        assert!(matches!(reply.unwrap(), KrakenEvent::CancelAllStatus(s) if s.count == Some(3)));
+    }
+
+    #[tokio::test]
+    async fn test_dead_mans_switch_renews_and_is_disarmed_on_disconnect() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        client
+            .cancel_all_after_renewing(60, "t", Duration::from_millis(50))
+            .await
+            .unwrap();
+        let armed: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(armed["event"], "cancelAllOrdersAfter");
+        assert_eq!(armed["timeout"], 60);
+        assert!(armed["reqid"].is_u64());
+        let renewed: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(renewed["timeout"], 60);
+        assert_eq!(renewed["token"], "t");
+
+        tokio::time::timeout(Duration::from_secs(5), client.disconnect())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!client.is_connected());
+        // Renewals queued before the disconnect may still come first
+        loop {
+            let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+            if sent["timeout"] == 0 {
+                assert_eq!(sent["token"], "t");
+                break;
+            }
+        }
+        let close = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(matches!(close, Message::Close(_)));
+    }
+
+    #[tokio::test]
+    async fn test_pool_spreads_subscriptions_and_merges_events() {
+        let (url, listener) = mock_server().await;
+        let pool = ConnectionPool::from_builder(KrakenClient::builder().ws_url(url), 2);
+        let mut rx = pool.subscribe_events();
+        pool.connect().await.unwrap();
+        let pairs = ["XBT/USD", "ETH/USD", "SOL/USD", "ADA/USD"].map(String::from);
+        assert_eq!(
+            pool.subscribe(pairs.to_vec(), "trade").await.unwrap().len(),
+            2
+        );
+
+        let mut sockets = vec![accept(&listener).await, accept(&listener).await];
+        let mut seen = Vec::new();
+        for ws in &mut sockets {
+            let sent: serde_json::Value = serde_json::from_str(&next_text(ws).await).unwrap();
+            let sent: Vec<String> = serde_json::from_value(sent["pair"].clone()).unwrap();
+            assert_eq!(sent.len(), 2);
+            let trade = format!(
+                r#"[0,[["5541.2","0.1","1534614057.3","b","m",""]],"trade","{}"]"#,
+                sent[0]
+            );
+            ws.send(Message::Text(trade)).await.unwrap();
+            seen.push(sent);
+        }
+        let mut all: Vec<String> = seen.concat();
+        all.sort();
+        let mut expected = pairs.to_vec();
+        expected.sort();
+        assert_eq!(all, expected);
+
+        let mut traded = Vec::new();
+        while traded.len() < 2 {
+            let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let Some(trade) = event.try_into_trade_data() {
+                traded.push(trade.pair);
+            }
+        }
+        assert_ne!(traded[0], traded[1]);
+
+        // Unsubscribing goes to the connection carrying the pair
+        let pair = seen[1][1].clone();
+        assert!(pool.connection_for(&pair, "trade").is_some());
+        pool.unsubscribe(vec![pair.clone()], "trade").await.unwrap();
+        assert_eq!(pool.connection_for(&pair, "trade"), None);
+        let sent: serde_json::Value =
+            serde_json::from_str(&next_text(&mut sockets[1]).await).unwrap();
+        assert_eq!(sent["event"], "unsubscribe");
+        assert_eq!(sent["pair"], serde_json::json!([pair]));
+    }
+
+    #[tokio::test]
+    async fn test_reqid_routes_reply_to_caller() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        let first = client
+            .subscribe(vec!["XBT/USD".to_string()], "trade", None)
+            .await
+            .unwrap();
+        let second = client
+            .subscribe(vec!["ETH/USD".to_string()], "trade", None)
+            .await
+            .unwrap();
+        assert_ne!(first.reqid(), second.reqid());
+
+        for _ in 0..2 {
+            let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+            let reply = serde_json::json!({
+                "event": "subscriptionStatus",
+                "status": "subscribed",
+                "pair": sent["pair"][0],
+                "reqid": sent["reqid"],
+            });
+            ws.send(Message::Text(reply.to_string())).await.unwrap();
+        }
+
+        for (pending, pair) in [(second, "ETH/USD"), (first, "XBT/USD")] {
+            let reqid = pending.reqid();
+            let reply = tokio::time::timeout(Duration::from_secs(5), pending.response())
+                .await
+                .unwrap()
+                .unwrap();
+            match reply {
+                KrakenEvent::SubscriptionStatus(status) => {
+                    assert_eq!(status.reqid, Some(reqid));
+                    assert_eq!(status.pair.as_deref(), Some(pair));
+                }
+                other => panic!("Expected SubscriptionStatus, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("kraken_record_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).record_to(&path).build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+
+        let frames = [
+            r#"{"event":"heartbeat"}"#,
+            r#"[0,[["5541.20000","0.15850568","1534614057.321597","s","l",""]],"trade","XBT/USD"]"#,
+        ];
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Connected).await;
+        for frame in frames {
+            ws.send(Message::Text(frame.to_string())).await.unwrap();
+        }
+        for _ in frames {
+            tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+        }
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(recorded, format!("{}\n{}\n", frames[0], frames[1]));
+
+        // Replay through a fresh, never-connected client
+        let replayer = KrakenClient::new();
+        let mut rx = replayer.subscribe_events();
+        assert_eq!(replayer.replay_from(&path).await.unwrap(), 2);
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            KrakenEvent::Heartbeat(_)
+        ));
+        let trade = rx.recv().await.unwrap().try_into_trade_data().unwrap();
+        assert_eq!(trade.data[0].price, "5541.20000");
+        assert_eq!(replayer.metrics().messages, 2);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_canned_frames_yield_expected_events() {
+        let client = KrakenClient::new();
+        let mut rx = client.subscribe_events();
+        let source = VecSource::new([
+            r#"{"event":"systemStatus","status":"online","version":"1.9.0"}"#,
+            r#"[0,[["5541.20000","0.15850568","1534614057.321597","s","l",""]],"trade","XBT/USD"]"#,
+            "not json",
+            r#"[1234,{"as":[["5541.30000","2.50700000","1534614248.123678"]],"bs":[["5541.20000","1.52900000","1534614248.765567"]]},"book-10","XBT/USD"]"#,
+        ]);
+        assert_eq!(client.run_source(source).await, 4);
+
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            KrakenEvent::SystemStatus(status) if status.status == "online"
+        ));
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            KrakenEvent::SystemStatusChanged { from: None, .. }
+        ));
+        let trades = rx.recv().await.unwrap().try_into_trade_data().unwrap();
+        assert_eq!(trades.data[0].side, "s");
+        let book = rx.recv().await.unwrap().try_into_orderbook_data().unwrap();
+        assert!(book.is_snapshot);
+        assert!(rx.try_recv().is_err());
+
+        let metrics = client.metrics();
+        assert_eq!(metrics.messages, 4);
+        assert_eq!(metrics.errors, 1);
+        assert_eq!(metrics.parse_errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_callbacks_receive_matching_events() {
+        let client = KrakenClient::new();
+        let (trade_tx, mut trades) = tokio::sync::mpsc::unbounded_channel();
+        let (book_tx, mut books) = tokio::sync::mpsc::unbounded_channel();
+        client.on_trade(move |data| trade_tx.send(data.pair).unwrap());
+        client.on_orderbook(move |data| book_tx.send(data.is_snapshot).unwrap());
+
+        let source = VecSource::new([
+            r#"{"event":"heartbeat"}"#,
+            r#"[0,[["5541.20000","0.15850568","1534614057.321597","s","l",""]],"trade","XBT/USD"]"#,
+            r#"[1234,{"as":[["5541.30000","2.50700000","1534614248.123678"]],"bs":[["5541.20000","1.52900000","1534614248.765567"]]},"book-10","XBT/USD"]"#,
+        ]);
+        client.run_source(source).await;
+
+        let timeout = Duration::from_secs(5);
+        let pair = tokio::time::timeout(timeout, trades.recv()).await.unwrap();
+        assert_eq!(pair.as_deref(), Some("XBT/USD"));
+        let snapshot = tokio::time::timeout(timeout, books.recv()).await.unwrap();
+        assert_eq!(snapshot, Some(true));
+        assert!(trades.try_recv().is_err());
+        assert!(books.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_channel_ids_follow_subscription_status() {
+        let client = KrakenClient::new();
+        let source = VecSource::new([
+            r#"{"event":"subscriptionStatus","status":"subscribed","channelID":10001,"channelName":"book-10","pair":"XBT/USD","subscription":{"name":"book","depth":10}}"#,
+            r#"{"event":"subscriptionStatus","status":"subscribed","channelID":10002,"channelName":"trade","pair":"ETH/USD","subscription":{"name":"trade"}}"#,
+            r#"{"event":"subscriptionStatus","status":"error","errorMessage":"Currency pair not supported","pair":"FOO/BAR","subscription":{"name":"trade"}}"#,
+            r#"{"event":"subscriptionStatus","status":"unsubscribed","channelID":10002,"channelName":"trade","pair":"ETH/USD","subscription":{"name":"trade"}}"#,
+        ]);
+        client.run_source(source).await;
+
+        assert_eq!(
+            client.channel_for_id(10001),
+            Some(("XBT/USD".to_string(), "book-10".to_string()))
+        );
+        assert_eq!(client.channel_for_id(10002), None);
+        assert_eq!(client.channel_ids().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_parse_errors_can_be_surfaced() {
+        let client = KrakenClient::builder().surface_parse_errors(true).build();
+        let mut rx = client.subscribe_events();
+        let source = VecSource::new([r#"[0,[["5541.2""#, r#"{"event":"heartbeat"}"#]);
+        assert_eq!(client.run_source(source).await, 2);
+
+        match rx.recv().await.unwrap() {
+            KrakenEvent::ParseError { raw, error } => {
+                assert_eq!(raw, r#"[0,[["5541.2""#);
+                assert!(error.contains("EOF"), "{}", error);
+            }
+            other => panic!("expected ParseError, got {:?}", other),
+        }
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            KrakenEvent::Heartbeat(_)
+        ));
+        assert_eq!(client.metrics().parse_errors, 1);
+    }
+
+    #[tokio::test]
+    async fn test_channel_source_interleaves_with_assertions() {
+        let client = KrakenClient::new();
+        let mut rx = client.subscribe_events();
+        let (tx, frames) = tokio::sync::mpsc::channel(8);
+
+        tx.send(r#"{"event":"heartbeat"}"#.to_string())
+            .await
+            .unwrap();
+        let runner = async {
+            client.run_source(ChannelSource::new(frames)).await;
+        };
+        let checks = async move {
+            assert!(matches!(
+                rx.recv().await.unwrap(),
+                KrakenEvent::Heartbeat(_)
+            ));
+            tx.send(r#"{"event":"addOrderStatus","status":"ok","reqid":3}"#.to_string())
+                .await
+                .unwrap();
+            assert_eq!(rx.recv().await.unwrap().reqid(), Some(3));
+        };
+        tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(runner, checks)
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_system_status_transitions_are_broadcast() {
+        let client = KrakenClient::new();
+        let mut rx = client.subscribe_events();
+        let status = |s: &str, id: u64| {
+            format!(
+                r#"{{"connectionID":{},"event":"systemStatus","status":"{}","version":"1.9.0"}}"#,
+                id, s
+            )
+        };
+        assert_eq!(client.connection_id(), None);
+        let source = VecSource::new([
+            status("online", 1),
+            status("online", 2),
+            status("maintenance", 3),
+        ]);
+        client.run_source(source).await;
+        assert_eq!(client.connection_id(), Some(3));
+
+        let mut changes = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let KrakenEvent::SystemStatusChanged { from, to } = event {
+                changes.push((from, to));
+            }
+        }
+        assert_eq!(
+            changes,
+            vec![
+                (None, "online".to_string()),
+                (Some("online".to_string()), "maintenance".to_string()),
+            ]
+        );
+        assert_eq!(client.system_status().as_deref(), Some("maintenance"));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_normalizes_pairs_when_enabled() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .normalize_pairs(true)
+            .build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        client
+            .subscribe(vec!["btc/usd".to_string()], "trade", None)
+            .await
+            .unwrap();
+        let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(sent["pair"], serde_json::json!(["XBT/USD"]));
+        assert_eq!(
+            client.active_subscriptions(),
+            vec![(vec!["XBT/USD".to_string()], "trade".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_large_subscription_is_sent_in_batches() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .subscription_batch_size(25)
+            .subscription_batch_delay(Duration::from_millis(10))
+            .build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        let pairs: Vec<String> = (0..60).map(|i| format!("PAIR{}/USD", i)).collect();
+        let pending = client
+            .subscribe(pairs.clone(), "ticker", None)
+            .await
+            .unwrap();
+
+        let mut sent_pairs = Vec::new();
+        for expected_len in [25, 25, 10] {
+            let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+            assert_eq!(sent["reqid"], pending.reqid());
+            let batch = sent["pair"].as_array().unwrap();
+            assert_eq!(batch.len(), expected_len);
+            sent_pairs.extend(batch.iter().map(|p| p.as_str().unwrap().to_string()));
+        }
+        assert_eq!(sent_pairs, pairs);
+    }
+
+    #[tokio::test]
+    async fn test_connect_returns_supervisable_handle() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        let driver = client.connect().await.unwrap();
+        let _ws = accept(&listener).await;
+        assert!(!driver.is_finished());
+
+        // Dropping the client closes the command channel, which stops the driver
+        drop(client);
+        tokio::time::timeout(Duration::from_secs(5), driver)
+            .await
+            .expect("driver did not stop")
+            .unwrap();
+
+        let (url, _listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        let driver = client.connect().await.unwrap();
+        driver.abort();
+        assert!(driver.await.unwrap_err().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_spaces_out_commands() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).rate_limit(5).build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+
+        for i in 0..7 {
+            client
+                .send_raw(serde_json::json!({"event": "ping", "reqid": i}))
+                .await
+                .unwrap();
+        }
+        let start = tokio::time::Instant::now();
+        for _ in 0..5 {
+            next_text(&mut ws).await;
+        }
+        assert!(
+            start.elapsed() < Duration::from_millis(150),
+            "burst was throttled"
+        );
+
+        // Inbound messages are still handled while commands wait for tokens
+        ws.send(Message::Text(r#"{"event":"heartbeat"}"#.to_string()))
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        next_text(&mut ws).await;
+        next_text(&mut ws).await;
+        // Two more tokens at 5/s take at least ~400ms
+        assert!(start.elapsed() >= Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn test_cloned_client_shares_connection() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        let handle = client.clone();
+        let mut events = handle.subscribe_events();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+        expect_state(&mut events, ConnectionState::Connected).await;
+
+        // Commands from the clone go out over the original's connection
+        handle
+            .subscribe(vec!["XBT/USD".to_string()], "ticker", None)
+            .await
+            .unwrap();
+        let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(sent["event"], "subscribe");
+        assert_eq!(client.active_subscriptions().len(), 1);
+        assert!(
+            handle.connect().await.is_err(),
+            "clone must not start a second driver"
+        );
+
+        // ...and events from it reach receivers created on the clone
+        ws.send(Message::Text(r#"{"event":"heartbeat"}"#.to_string()))
+            .await
+            .unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(event, KrakenEvent::Heartbeat(_)));
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshake_times_out_and_retries() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .connect_timeout(Duration::from_millis(200))
+            .build();
+        client.connect().await.unwrap();
+
+        // Accept the TCP connection but never answer the WebSocket upgrade
+        let (_stalled, _) = listener.accept().await.unwrap();
+        assert!(!client.is_connected());
+
+        // The driver gives up on the attempt and connects again after its backoff
+        let _ws = tokio::time::timeout(Duration::from_secs(8), accept(&listener))
+            .await
+            .expect("driver did not retry after the connect timeout");
+    }
+
+    #[tokio::test]
+    async fn test_binary_frames_are_parsed_and_close_reconnects() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Connected).await;
+
+        let frame =
+            r#"[0,[["5541.20000","0.15850568","1534614057.321597","s","l",""]],"trade","XBT/USD"]"#;
+        ws.send(Message::Binary(frame.as_bytes().to_vec()))
+            .await
+            .unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            event.try_into_trade_data().unwrap().data[0].price,
+            "5541.20000"
+        );
+
+        ws.send(Message::Binary(vec![0xff, 0xfe])).await.unwrap();
+        ws.close(Some(tokio_tungstenite::tungstenite::protocol::CloseFrame {
+            code: tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode::Away,
+            reason: "maintenance".into(),
+        }))
+        .await
+        .unwrap();
+        let _ws = tokio::time::timeout(Duration::from_secs(5), accept(&listener))
+            .await
+            .expect("client did not reconnect after close");
+        assert_eq!(client.metrics().parse_errors, 1);
+        expect_state(&mut rx, ConnectionState::Reconnecting).await;
+        expect_state(&mut rx, ConnectionState::Reconnected).await;
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_disconnect_backs_off_before_reconnecting() {
+        let (url, listener) = mock_server().await;
+        let backoff = Duration::from_millis(1500);
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .rate_limit_backoff(backoff)
+            .build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Connected).await;
+
+        ws.send(Message::Text(
+            r#"{"event":"error","errorMessage":"Exceeded msg rate"}"#.to_string(),
+        ))
+        .await
+        .unwrap();
+        ws.close(None).await.unwrap();
+        let closed_at = tokio::time::Instant::now();
+        let error = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(error, KrakenEvent::Unknown(_)), "{:?}", error);
+        expect_state(&mut rx, ConnectionState::Reconnecting).await;
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        match event {
+            KrakenEvent::RateLimited { reason, retry_in } => {
+                assert_eq!(reason, "Exceeded msg rate");
+                assert_eq!(retry_in, backoff);
+            }
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+        let mut ws = tokio::time::timeout(Duration::from_secs(5), accept(&listener))
+            .await
+            .expect("client did not reconnect after the backoff");
+        assert!(closed_at.elapsed() >= backoff);
+        expect_state(&mut rx, ConnectionState::Reconnected).await;
+
+        // A subscription rejected for the same reason is reported as RateLimited
+        let server = tokio::spawn(async move {
+            let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+            ws.send(Message::Text(
+                serde_json::json!({
+                    "event": "subscriptionStatus",
+                    "status": "error",
+                    "pair": "XBT/USD",
+                    "errorMessage": "Exceeded msg rate",
+                    "reqid": sent["reqid"],
+                })
+                .to_string(),
+            ))
+            .await
+            .unwrap();
+            ws
+        });
+        let err = client
+            .subscribe_many(vec!["XBT/USD".to_string()], &["ticker"])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, KrakenError::RateLimited(msg) if msg.contains("Exceeded msg rate")),
+            "{:?}",
+            err
+        );
+        let _ws = server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_strategy_filters_subscriptions_on_reconnect() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .replay_strategy(ReplayStrategy::filter(|_, sub| sub.name != "book"))
+            .build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Connected).await;
+        for name in ["book", "trade"] {
+            client
+                .subscribe(vec!["XBT/USD".to_string()], name, None)
+                .await
+                .unwrap();
+            assert!(next_text(&mut ws).await.contains(name));
+        }
+
+        ws.close(None).await.unwrap();
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Reconnecting).await;
+        expect_state(&mut rx, ConnectionState::Reconnected).await;
+        // Only the trade subscription came back, and only it is still tracked
+        assert!(next_text(&mut ws).await.contains(r#""name":"trade""#));
+        assert_eq!(
+            client.active_subscriptions(),
+            vec![(vec!["XBT/USD".to_string()], "trade".to_string())]
+        );
+        assert!(
+            tokio::time::timeout(Duration::from_millis(300), ws.next())
+                .await
+                .is_err(),
+            "book must not be replayed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_reconnect_attempts_stops_the_driver() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .max_reconnect_attempts(Some(1))
+            .build();
+        let mut rx = client.subscribe_events();
+        let driver = client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Connected).await;
+
+        // Drop the connection and stop listening: the one allowed retry fails
+        drop(listener);
+        ws.close(None).await.unwrap();
+        expect_state(&mut rx, ConnectionState::Reconnecting).await;
+        expect_state(&mut rx, ConnectionState::Disconnected { permanent: true }).await;
+        tokio::time::timeout(Duration::from_secs(5), driver)
+            .await
+            .expect("driver kept running")
+            .unwrap();
+        assert!(!client.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_before_connect_is_sent_once() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        let pending = client
+            .subscribe(vec!["XBT/USD".to_string()], "trade", None)
+            .await
+            .unwrap();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+
+        let sent: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(sent["event"], "subscribe");
+        assert_eq!(sent["reqid"], pending.reqid());
+        assert_eq!(client.active_subscriptions().len(), 1);
+
+        // Nothing else follows: neither a replay nor the queued command again
+        let extra = tokio::time::timeout(Duration::from_millis(300), next_text(&mut ws)).await;
+        assert!(extra.is_err(), "unexpected frame: {:?}", extra);
+
+        ws.send(Message::Text(format!(
+            r#"{{"event":"subscriptionStatus","status":"subscribed","pair":"XBT/USD","reqid":{},"subscription":{{"name":"trade"}}}}"#,
+            pending.reqid()
+        )))
+        .await
+        .unwrap();
+        let reply = tokio::time::timeout(Duration::from_secs(5), pending.response())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(reply, KrakenEvent::SubscriptionStatus(_)));
+    }
+
+    /// Collects formatted log output for assertions.
+    #[derive(Clone, Default)]
+    struct LogBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_driver_logs_carry_client_id() {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        // The current-thread test runtime polls the driver on this thread
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url.clone())
+            .client_id("eu-1")
+            .build();
+        client.connect().await.unwrap();
+        let _ws = accept(&listener).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = output
+            .lines()
+            .find(|l| l.contains("Connected to Kraken"))
+            .expect("no connection log line");
+        assert!(line.contains("client_id=\"eu-1\""), "{}", line);
+        assert!(line.contains(&format!("ws_url={}", url)), "{}", line);
+    }
+
+    #[tokio::test]
+    async fn test_silent_connection_triggers_reconnect() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .heartbeat_timeout(Duration::from_millis(200))
+            .build();
+        client.connect().await.unwrap();
+
+        // Accept and then say nothing: the watchdog should drop us and reconnect.
+        let _silent = accept(&listener).await;
+        tokio::time::timeout(Duration::from_secs(5), listener.accept())
+            .await
+            .expect("client did not reconnect after heartbeat timeout")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_metrics_reset_returns_counts_then_zeroes() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        ws.send(Message::Text("not json".to_string()))
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            ws.send(Message::Text(r#"{"event":"heartbeat"}"#.to_string()))
+                .await
+                .unwrap();
+        }
+        // Frames are processed in order, so the third event means all four were handled.
+        for _ in 0..3 {
+            tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let snapshot = client.metrics_reset();
+        assert_eq!(snapshot.messages, 4);
+        assert_eq!(snapshot.errors, 1);
+
+        let after = client.metrics();
+        assert_eq!(after.messages, 0);
+        assert_eq!(after.errors, 0);
+    }
+
+    #[tokio::test]
+    async fn test_driver_sends_periodic_pings() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .ping_interval(Some(Duration::from_millis(100)))
+            .build();
+        client.connect().await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("no ping received")
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(msg, Message::Ping(_)),
+            "unexpected frame: {:?}",
+            msg
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_ping_triggers_reconnect() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .heartbeat_timeout(Duration::from_secs(60))
+            .ping_interval(Some(Duration::from_millis(100)))
+            .build();
+        client.connect().await.unwrap();
+
+        // Never read from the socket, so the client's pings are never answered.
+        let _unresponsive = accept(&listener).await;
+        tokio::time::timeout(Duration::from_secs(5), listener.accept())
+            .await
+            .expect("client did not reconnect after unanswered ping")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_checksum_mismatch_resubscribes_book() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        client
+            .subscribe(vec!["XBT/USD".to_string()], "book", None)
+            .await
+            .unwrap();
+
+        let mut ws = accept(&listener).await;
+        assert!(next_text(&mut ws).await.contains(r#""event":"subscribe""#));
+        expect_state(&mut rx, ConnectionState::Connected).await;
+
+        let mut books = BookMaintainer::new();
+        let snapshot = r#"[1, {"as": [["5541.3", "2.5", "1.0"]], "bs": [["5541.2", "1.0", "1.0"]]}, "book-10", "XBT/USD"]"#;
+        let bad_update =
+            r#"[1, {"a": [["5541.4", "1.0", "2.0"]], "c": "1"}, "book-10", "XBT/USD"]"#;
+        for frame in [snapshot, bad_update] {
+            let data = serde_json::from_str::<KrakenEvent>(frame)
+                .unwrap()
+                .try_into_orderbook_data()
+                .unwrap();
+            books.apply(&client, &data).await.unwrap();
+        }
+        assert!(books.is_resyncing("XBT/USD"));
+
+        let unsubscribe = next_text(&mut ws).await;
+        assert!(unsubscribe.contains(r#""event":"unsubscribe""#));
+        assert!(unsubscribe.contains("XBT/USD"));
+        assert!(next_text(&mut ws).await.contains(r#""event":"subscribe""#));
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(event, KrakenEvent::ChecksumMismatch { pair } if pair == "XBT/USD"));
+        assert_eq!(
+            client.active_subscriptions(),
+            vec![(vec!["XBT/USD".to_string()], "book".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_managed_books_track_updates_and_resync() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .manage_order_books(true)
+            .build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        client
+            .subscribe_with(
+                vec!["XBT/USD".to_string()],
+                SubscriptionArgs::new("book").depth(25),
+            )
+            .await
+            .unwrap();
+
+        let mut ws = accept(&listener).await;
+        assert!(next_text(&mut ws).await.contains(r#""event":"subscribe""#));
+        expect_state(&mut rx, ConnectionState::Connected).await;
+
+        let snapshot = r#"[1, {"as": [["5541.3", "2.5", "1.0"]], "bs": [["5541.2", "1.0", "1.0"]]}, "book-25", "XBT/USD"]"#;
+        ws.send(Message::Text(snapshot.to_string())).await.unwrap();
+        rx.recv().await.unwrap();
+        let books = client.books().unwrap();
+        assert_eq!(
+            books.read().unwrap().book("XBT/USD").unwrap().best_bid(),
+            Some((5541.2, 1.0))
+        );
+
+        let bad_update =
+            r#"[1, {"a": [["5541.4", "1.0", "2.0"]], "c": "1"}, "book-25", "XBT/USD"]"#;
+        ws.send(Message::Text(bad_update.to_string()))
+            .await
+            .unwrap();
+        // Both halves of the resync target the subscribed depth
+        for event in ["unsubscribe", "subscribe"] {
+            let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+            assert_eq!(msg["event"], event);
+            assert_eq!(msg["subscription"]["depth"], 25);
+        }
+        assert!(books.read().unwrap().is_resyncing("XBT/USD"));
+
+        rx.recv().await.unwrap();
+        let event = rx.recv().await.unwrap();
+        assert!(matches!(event, KrakenEvent::ChecksumMismatch { pair } if pair == "XBT/USD"));
+        assert_eq!(client.metrics().checksum_mismatches, 1);
+        assert_eq!(
+            client.active_subscriptions(),
+            vec![(vec!["XBT/USD".to_string()], "book".to_string())]
+        );
+        assert!(KrakenClient::new().books().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_books_are_cleared_on_reconnect_until_the_next_snapshot() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .manage_order_books(true)
+            .build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Connected).await;
+
+        let mut books = BookMaintainer::new();
+        let snapshot = r#"[1, {"as": [["5541.3", "2.5", "1.0"]], "bs": [["5541.2", "1.0", "1.0"]]}, "book-10", "XBT/USD"]"#;
+        ws.send(Message::Text(snapshot.to_string())).await.unwrap();
+        let event = rx.recv().await.unwrap();
+        assert!(books.process(&client, &event).await.unwrap());
+        assert_eq!(books.book("XBT/USD").unwrap().asks.len(), 1);
+
+        ws.close(None).await.unwrap();
+        let event = rx.recv().await.unwrap();
+        assert!(matches!(
+            event,
+            KrakenEvent::ConnectionState(ConnectionState::Reconnecting)
+        ));
+        assert!(!books.process(&client, &event).await.unwrap());
+        for books in [&books, &*client.books().unwrap().read().unwrap()] {
+            assert!(books.is_resyncing("XBT/USD"));
+            let book = books.book("XBT/USD").unwrap();
+            assert!(book.asks.is_empty() && book.bids.is_empty());
+        }
+
+        let mut ws = accept(&listener).await;
+        expect_state(&mut rx, ConnectionState::Reconnected).await;
+        let update = r#"[1, {"a": [["5541.4", "1.0", "2.0"]]}, "book-10", "XBT/USD"]"#;
+        ws.send(Message::Text(update.to_string())).await.unwrap();
+        let event = rx.recv().await.unwrap();
+        assert!(!books.process(&client, &event).await.unwrap());
+        assert!(books.book("XBT/USD").unwrap().asks.is_empty());
+
+        ws.send(Message::Text(snapshot.to_string())).await.unwrap();
+        let event = rx.recv().await.unwrap();
+        assert!(books.process(&client, &event).await.unwrap());
+        for books in [&books, &*client.books().unwrap().read().unwrap()] {
+            assert!(!books.is_resyncing("XBT/USD"));
+            assert_eq!(
+                books.book("XBT/USD").unwrap().best_ask(),
+                Some((5541.3, 2.5))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_managed_books_skip_checksums_when_validation_is_off() {
+        let client = KrakenClient::builder()
+            .manage_order_books(true)
+            .auto_validate_checksums(false)
+            .build();
+        let mut rx = client.subscribe_events();
+        let source = VecSource::new([
+            r#"[1, {"as": [["5541.3", "2.5", "1.0"]], "bs": [["5541.2", "1.0", "1.0"]]}, "book-10", "XBT/USD"]"#,
+            r#"[1, {"a": [["5541.25", "1.0", "2.0"]], "c": "1"}, "book-10", "XBT/USD"]"#,
+        ]);
+        assert_eq!(client.run_source(source).await, 2);
+
+        rx.recv().await.unwrap();
+        rx.recv().await.unwrap();
+        assert!(rx.try_recv().is_err());
+        let books = client.books().unwrap();
+        let books = books.read().unwrap();
+        assert!(!books.is_resyncing("XBT/USD"));
+        assert_eq!(
+            books.book("XBT/USD").unwrap().best_ask(),
+            Some((5541.25, 1.0))
+        );
+        assert_eq!(client.metrics().checksum_mismatches, 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_without_pairs_omits_pair_field() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+        let args = SubscriptionArgs::new("ownTrades").token("token");
+        client.subscribe_with(vec![], args).await.unwrap();
+
+        let mut ws = accept(&listener).await;
+        let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(msg["event"], "subscribe");
+        assert_eq!(msg["subscription"]["name"], "ownTrades");
+        assert!(msg.get("pair").is_none(), "{}", msg);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_with_sends_options_and_resubscribe_keeps_them() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+        let args = SubscriptionArgs::new("book").depth(25).snapshot(false);
+        client
+            .subscribe_with(vec!["XBT/USD".to_string()], args)
+            .await
+            .unwrap();
+
+        let mut ws = accept(&listener).await;
+        let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(
+            msg["subscription"],
+            serde_json::json!({"name": "book", "depth": 25, "snapshot": false})
+        );
+
+        client
+            .resubscribe(vec!["XBT/USD".to_string()], "book")
+            .await
+            .unwrap();
+        let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(msg["event"], "unsubscribe");
+        assert_eq!(msg["subscription"]["depth"], 25);
+        let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(msg["event"], "subscribe");
+        assert_eq!(msg["subscription"]["depth"], 25);
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe_keeps_options_for_unnormalized_pairs() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .ws_url(url)
+            .normalize_pairs(true)
+            .build();
+        client.connect().await.unwrap();
+        client
+            .subscribe_with(
+                vec!["btc/usd".to_string()],
+                SubscriptionArgs::new("book").depth(25),
+            )
+            .await
+            .unwrap();
+
+        let mut ws = accept(&listener).await;
+        let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(msg["pair"], serde_json::json!(["XBT/USD"]));
+
+        client
+            .resubscribe(vec!["btc/usd".to_string()], "book")
+            .await
+            .unwrap();
+        for event in ["unsubscribe", "subscribe"] {
+            let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+            assert_eq!(msg["event"], event);
+            assert_eq!(msg["pair"], serde_json::json!(["XBT/USD"]));
+            assert_eq!(msg["subscription"]["depth"], 25);
+        }
+    }
+
+    /// Answers each subscribe request with one status per pair, rejecting `reject` if given.
+    async fn confirm_subscriptions(
+        ws: &mut WebSocketStream<tokio::net::TcpStream>,
+        requests: usize,
+        reject: Option<&str>,
+    ) {
+        for _ in 0..requests {
+            let request: serde_json::Value = serde_json::from_str(&next_text(ws).await).unwrap();
+            for pair in request["pair"].as_array().unwrap() {
+                let status = if pair.as_str() == reject {
+                    serde_json::json!({"event": "subscriptionStatus", "status": "error", "errorMessage": "Currency pair not supported", "pair": pair, "reqid": request["reqid"]})
+                } else {
+                    serde_json::json!({"event": "subscriptionStatus", "status": "subscribed", "channelName": request["subscription"]["name"], "pair": pair, "reqid": request["reqid"]})
+                };
+                ws.send(Message::Text(status.to_string())).await.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_subscriptions_only_sends_the_difference() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+        let mut ws = accept(&listener).await;
+        let pairs = |pairs: &[&str]| pairs.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        client
+            .set_subscriptions(vec![(pairs(&["XBT/USD", "ETH/USD"]), "ticker".to_string())])
+            .await
+            .unwrap();
+        let msg: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(msg["event"], "subscribe");
+        assert_eq!(msg["pair"], serde_json::json!(["XBT/USD", "ETH/USD"]));
+
+        let pending = client
+            .set_subscriptions(vec![
+                (pairs(&["ETH/USD", "SOL/USD"]), "ticker".to_string()),
+                (pairs(&["XBT/USD"]), "trade".to_string()),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 3);
+        let sent: Vec<serde_json::Value> = [
+            next_text(&mut ws).await,
+            next_text(&mut ws).await,
+            next_text(&mut ws).await,
+        ]
+        .iter()
+        .map(|text| serde_json::from_str(text).unwrap())
+        .collect();
+        let summary: Vec<_> = sent
+            .iter()
+            .map(|msg| {
+                (
+                    msg["event"].as_str().unwrap(),
+                    msg["pair"].clone(),
+                    msg["subscription"]["name"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("unsubscribe", serde_json::json!(["XBT/USD"]), "ticker"),
+                ("subscribe", serde_json::json!(["SOL/USD"]), "ticker"),
+                ("subscribe", serde_json::json!(["XBT/USD"]), "trade"),
+            ]
+        );
+
+        // Already in place: nothing to send
+        let pending = client
+            .set_subscriptions(vec![
+                (pairs(&["SOL/USD", "ETH/USD"]), "ticker".to_string()),
+                (pairs(&["XBT/USD"]), "trade".to_string()),
+            ])
+            .await
+            .unwrap();
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_many_waits_for_every_confirmation() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+        let server = tokio::spawn(async move {
+            let mut ws = accept(&listener).await;
+            confirm_subscriptions(&mut ws, 2, None).await;
+            confirm_subscriptions(&mut ws, 1, Some("FOO/BAR")).await;
+            ws
+        });
+
+        let pairs = vec!["XBT/USD".to_string(), "ETH/USD".to_string()];
+        let confirmations = client
+            .subscribe_many(pairs, &["trade", "book"])
+            .await
+            .unwrap();
+        assert_eq!(confirmations.len(), 4);
+        assert!(confirmations
+            .iter()
+            .all(|event| matches!(event, KrakenEvent::SubscriptionStatus(_))));
+
+        let pairs = vec!["XBT/USD".to_string(), "FOO/BAR".to_string()];
+        let err = client.subscribe_many(pairs, &["ticker"]).await.unwrap_err();
+        assert!(
+            matches!(&err, KrakenError::Subscription(msg) if msg.starts_with("FOO/BAR")),
+            "{:?}",
+            err
+        );
+        let _ws = server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_await_book_snapshot_subscribes_and_skips_updates() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+
+        let server = tokio::spawn(async move {
+            let mut ws = accept(&listener).await;
+            let subscribe = next_text(&mut ws).await;
+            assert!(subscribe.contains(r#""name":"book""#));
+            let frames = [
+                r#"[1, {"a": [["5541.4", "1.0", "2.0"]]}, "book-10", "XBT/USD"]"#,
+                r#"[2, {"as": [["3.0", "1.0", "1.0"]], "bs": [["2.0", "1.0", "1.0"]]}, "book-10", "ETH/USD"]"#,
+                r#"[1, {"as": [["5541.3", "2.5", "1.0"]], "bs": [["5541.2", "1.0", "1.0"]]}, "book-10", "XBT/USD"]"#,
+            ];
+            for frame in frames {
+                ws.send(Message::Text(frame.to_string())).await.unwrap();
+            }
+            ws
+        });
+
+        let book = client
+            .await_book_snapshot("XBT/USD", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(book.is_snapshot);
+        assert_eq!(book.asks[0].price, "5541.3");
+        let _ws = server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_await_book_snapshot_times_out() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder().ws_url(url).build();
+        client.connect().await.unwrap();
+        let _server = tokio::spawn(async move {
+            let mut ws = accept(&listener).await;
+            next_text(&mut ws).await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let err = client
+            .await_book_snapshot("XBT/USD", Duration::from_millis(200))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, KrakenError::Timeout(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_v2_protocol_subscribes_and_parses() {
+        let (url, listener) = mock_server().await;
+        let client = KrakenClient::builder()
+            .protocol(ProtocolVersion::V2)
+            .ws_url(url)
+            .build();
+        let mut rx = client.subscribe_events();
+        client.connect().await.unwrap();
+        let pending = client
+            .subscribe(vec!["BTC/USD".to_string()], "trade", None)
+            .await
+            .unwrap();
+
+        let mut ws = accept(&listener).await;
+        let request: serde_json::Value = serde_json::from_str(&next_text(&mut ws).await).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "method": "subscribe",
+                "params": {"channel": "trade", "symbol": ["BTC/USD"]},
+                "req_id": pending.reqid()
+            })
+        );
+        expect_state(&mut rx, ConnectionState::Connected).await;
+
+        ws.send(Message::Text(r#"{"channel":"heartbeat"}"#.to_string()))
+            .await
+            .unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            event,
+            KrakenEvent::V2(KrakenEventV2::Channel(ChannelMessage::Heartbeat))
+        ));
+    }
+}